@@ -0,0 +1,44 @@
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, Criterion};
+use ccs811::MODE;
+use ccs811::chip::{CCS811, NoPin};
+use ccs811::codec::{float_to_bytes, parse_alg_result};
+use ccs811::filter::{Ewma, MovingAverage};
+use ccs811::pipeline::{OutlierRejection, Pipeline, Validity};
+use ccs811::sim::SimCcs811;
+
+// ALG_RESULT_DATA of 812ppm eCO2 and 64ppb tVOC with data ready
+const ALG_RESULT: [u8; 8] = [0x03, 0x2C, 0x00, 0x40, 0x98, 0x00, 0x50, 0x00];
+
+fn codec(c: &mut Criterion) {
+    c.bench_function("float_to_bytes", |b| b.iter(|| float_to_bytes(black_box(48.25))));
+    c.bench_function("parse_alg_result", |b| {
+        b.iter(|| parse_alg_result(black_box(ALG_RESULT), MODE::Sec1, None))
+    });
+
+    // Encoding and writing ENV_DATA on the simulated bus
+    let mut ccs811 = CCS811::new(SimCcs811::new(), None::<NoPin>);
+    ccs811.begin().unwrap();
+    c.bench_function("set_env_data", |b| {
+        b.iter(|| ccs811.set_env_data(black_box(48.25), black_box(23.5)).unwrap())
+    });
+}
+
+fn pipeline(c: &mut Criterion) {
+    let mut e_co2 = 400;
+    let source = move || {
+        e_co2 = if e_co2 >= 1400 { 400 } else { e_co2 + 7 };
+        let [high, low] = u16::to_be_bytes(e_co2);
+        parse_alg_result([high, low, 0x00, 0x40, 0x98, 0x00, 0x50, 0x00], MODE::Sec1, None)
+    };
+    let mut pipeline = Pipeline::new(source)
+        .stage(Validity)
+        .stage(OutlierRejection::new(10, 400, 200))
+        .stage(MovingAverage::new(5))
+        .stage(Ewma::new(0.3));
+
+    c.bench_function("pipeline", |b| b.iter(|| pipeline.run_once().unwrap()));
+}
+
+criterion_group!(benches, codec, pipeline);
+criterion_main!(benches);
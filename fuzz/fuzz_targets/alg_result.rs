@@ -0,0 +1,30 @@
+#![no_main]
+use std::convert::TryFrom;
+use std::time::Duration;
+use libfuzzer_sys::fuzz_target;
+use ccs811::MODE;
+use ccs811::chip::Status;
+use ccs811::codec::parse_alg_result;
+
+// ALG_RESULT_DATA as read from the bus, followed by the mode and the seconds since the start
+fuzz_target!(|input: ([u8; 8], u8, Option<u16>)| {
+    let (buffer, mode, since_start) = input;
+    let mode = MODE::try_from(mode).unwrap_or(MODE::Sec1);
+    let since_start = since_start.map(|seconds| Duration::from_secs(u64::from(seconds)));
+
+    match parse_alg_result(buffer, mode, since_start) {
+        Ok(data) => {
+            assert!(data.e_co2 <= 8192 && data.t_voc <= 1187);
+            let _ = data.to_string();
+            let _ = data.air_quality();
+            let _ = data.is_valid();
+            let _ = data.raw.resistance_ohms();
+            assert_eq!(data.status, Status::from(buffer[4]));
+            let _ = data.status.to_string();
+        },
+        Err(error) => {
+            let _ = error.to_string();
+            let _ = error.kind();
+        }
+    }
+});
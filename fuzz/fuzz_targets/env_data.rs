@@ -0,0 +1,30 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use ccs811::codec::{baseline_from_bytes, baseline_to_bytes, bytes_to_float, checked_float_to_bytes, float_to_bytes, TemperatureEncoding};
+
+fuzz_target!(|input: (f32, u16, [u8; 2])| {
+    let (value, baseline, bytes) = input;
+
+    // Humidity and temperature are encoded in 1/512 steps
+    let encoded = bytes_to_float(float_to_bytes(value));
+    if (0.0..127.0).contains(&value) {
+        assert!((encoded - value).abs() <= 1.0 / 1024.0, "{} was encoded as {}", value, encoded);
+    }
+    assert_eq!(float_to_bytes(bytes_to_float(bytes)), bytes);
+    // The checked encoding only differs by refusing what saturates
+    match checked_float_to_bytes(value) {
+        Some(checked) => assert_eq!(checked, float_to_bytes(value)),
+        None => assert!(value.is_nan() || !(0.0..=127.0).contains(&value), "{} was refused", value)
+    }
+
+    for encoding in [TemperatureEncoding::Offset25, TemperatureEncoding::Absolute] {
+        let (min, max) = encoding.range();
+        match encoding.to_bytes(value) {
+            Some(bytes) => assert!((encoding.from_bytes(bytes) - value).abs() <= 1.0 / 1024.0, "{} was encoded as {:?}", value, bytes),
+            None => assert!(value.is_nan() || !(min..=max).contains(&value), "{} was refused by {:?}", value, encoding)
+        }
+    }
+
+    assert_eq!(baseline_from_bytes(baseline_to_bytes(baseline)), baseline);
+    assert_eq!(baseline_to_bytes(baseline_from_bytes(bytes)), bytes);
+});
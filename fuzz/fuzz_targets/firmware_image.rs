@@ -0,0 +1,16 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use ccs811::firmware::{FirmwareImage, FirmwareVersion};
+
+// File name of the image and its content
+fuzz_target!(|input: (&str, &[u8])| {
+    let (name, data) = input;
+
+    if let Some(version) = FirmwareVersion::from_file_name(name) {
+        assert_eq!(FirmwareVersion::from_bytes(version.to_bytes()), version);
+    }
+
+    let image = FirmwareImage::new(data.to_vec());
+    let _ = image.verify(name);
+    assert_eq!(image.verify(&image.sha256()).is_ok(), !data.is_empty());
+});
@@ -0,0 +1,346 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::sink::{first_error, ReadingSink};
+
+/// Value of a reading an alert level applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    ECo2,
+    TVoc
+}
+
+impl Metric {
+    fn name(&self) -> &'static str {
+        match self {
+            Metric::ECo2 => "eco2",
+            Metric::TVoc => "tvoc"
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            Metric::ECo2 => "ppm",
+            Metric::TVoc => "ppb"
+        }
+    }
+
+    fn value(&self, data: &Ccs811Data) -> u16 {
+        match self {
+            Metric::ECo2 => data.e_co2,
+            Metric::TVoc => data.t_voc
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::ECo2 => write!(f, "eCO2"),
+            Metric::TVoc => write!(f, "tVOC")
+        }
+    }
+}
+
+/// Direction a level was crossed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Above,
+    Below
+}
+
+impl Direction {
+    fn name(&self) -> &'static str {
+        match self {
+            Direction::Above => "above",
+            Direction::Below => "below"
+        }
+    }
+}
+
+/// A level crossed by eCO2 or tVOC, which is passed to the `Notifier`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alert {
+    pub metric: Metric,
+    pub level: u16,
+    pub direction: Direction,
+    /// Value of the metric which crossed the level
+    pub value: u16,
+    pub e_co2: u16,
+    pub t_voc: u16
+}
+
+impl Alert {
+
+    /// `{"metric":"eco2","level":1000,"direction":"above","value":1040,"e_co2":1040,"t_voc":60}`
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"metric\":\"{}\",\"level\":{},\"direction\":\"{}\",\"value\":{},\"e_co2\":{},\"t_voc\":{}}}",
+            self.metric.name(), self.level, self.direction.name(), self.value, self.e_co2, self.t_voc
+        )
+    }
+}
+
+/// Text for humans like `eCO2 above 1000ppm: 1040ppm`, e.g. for e-mails or chat messages
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = self.metric.unit();
+        write!(f, "{} {} {}{}: {}{}", self.metric, self.direction.name(), self.level, unit, self.value, unit)
+    }
+}
+
+/// Delivers alerts, e.g. by e-mail, to a chat or as push notification. The crate implements it for
+/// webhooks and MQTT, closures taking an alert are notifiers as well.
+///
+/// # Examples
+///
+/// ```no_run
+/// # struct TelegramBot;
+/// # impl TelegramBot { fn send_message(&self, _: i64, _: &str) -> std::io::Result<()> { Ok(()) } }
+/// use ccs811::alert::{Alert, Notifier};
+///
+/// struct Telegram { bot: TelegramBot, chat_id: i64 }
+///
+/// impl Notifier for Telegram {
+///     fn notify(&mut self, alert: &Alert) -> Result<(), ccs811::error::BoxError> {
+///         Ok(self.bot.send_message(self.chat_id, &alert.to_string())?)
+///     }
+/// }
+/// ```
+pub trait Notifier {
+
+    fn notify(&mut self, alert: &Alert) -> Result<(), BoxError>;
+}
+
+impl<F> Notifier for F where F: FnMut(&Alert) -> Result<(), BoxError> {
+    fn notify(&mut self, alert: &Alert) -> Result<(), BoxError> {
+        self(alert)
+    }
+}
+
+struct Level {
+    metric: Metric,
+    level: u16,
+    above: bool,
+    // Since when the readings are on the other side of the level
+    crossing_since: Option<Instant>
+}
+
+impl Level {
+
+    /// Returns whether the level was crossed for at least the debounce duration
+    fn update(&mut self, value: u16, debounce: Duration, now: Instant) -> bool {
+        let above = value >= self.level;
+        if above == self.above {
+            self.crossing_since = None;
+            return false;
+        }
+
+        let since = *self.crossing_since.get_or_insert(now);
+        if now.duration_since(since) < debounce {
+            return false;
+        }
+
+        self.above = above;
+        self.crossing_since = None;
+        true
+    }
+}
+
+/// Levels of eCO2 and tVOC which raise an alert when crossed in either direction. To not alert on
+/// every reading around a level, the readings have to stay on the other side of the level for the
+/// debounce duration (1 minute by default).
+pub struct Thresholds {
+    levels: Vec<Level>,
+    debounce: Duration
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds { levels: Vec::new(), debounce: Duration::from_secs(60) }
+    }
+}
+
+impl Thresholds {
+
+    pub fn new() -> Self {
+        Thresholds::default()
+    }
+
+    /// Alert when the metric crosses the level
+    pub fn level(mut self, metric: Metric, level: u16) -> Self {
+        self.levels.push(Level { metric, level, above: false, crossing_since: None });
+        self
+    }
+
+    pub fn with_debounce(self, debounce: Duration) -> Self {
+        Thresholds { debounce, ..self }
+    }
+
+    /// Checks the reading against the levels and returns the crossed ones
+    pub fn check(&mut self, data: &Ccs811Data) -> Vec<Alert> {
+        self.check_at(data, Instant::now())
+    }
+
+    /// Checks the reading taken at the given time, e.g. from a recording
+    pub fn check_at(&mut self, data: &Ccs811Data, now: Instant) -> Vec<Alert> {
+        let debounce = self.debounce;
+
+        self.levels.iter_mut()
+            .filter_map(|level| {
+                let value = level.metric.value(data);
+                if !level.update(value, debounce, now) {
+                    return None;
+                }
+
+                Some(Alert {
+                    metric: level.metric,
+                    level: level.level,
+                    direction: if level.above { Direction::Above } else { Direction::Below },
+                    value,
+                    e_co2: data.e_co2,
+                    t_voc: data.t_voc
+                })
+            })
+            .collect()
+    }
+}
+
+/// Checks every reading against `Thresholds` and passes the crossed levels to a `Notifier`
+///
+/// # Examples
+///
+/// ```no_run
+/// # use ccs811::sink::ReadingSink;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::alert::{Alert, AlertSink, Metric};
+///
+/// let mut alerts = AlertSink::new(|alert: &Alert| {
+///         println!("{}", alert);
+///         Ok(())
+///     })
+///     .level(Metric::ECo2, 1000)
+///     .level(Metric::TVoc, 500);
+///
+/// alerts.publish(&ccs811.read().expect("Could not read data")).expect("Could not notify");
+/// ```
+pub struct AlertSink<N> {
+    thresholds: Thresholds,
+    notifier: N
+}
+
+impl<N: Notifier> AlertSink<N> {
+
+    pub fn new(notifier: N) -> Self {
+        AlertSink { thresholds: Thresholds::new(), notifier }
+    }
+
+    /// Alert when the metric crosses the level
+    pub fn level(self, metric: Metric, level: u16) -> Self {
+        AlertSink { thresholds: self.thresholds.level(metric, level), ..self }
+    }
+
+    pub fn with_debounce(self, debounce: Duration) -> Self {
+        AlertSink { thresholds: self.thresholds.with_debounce(debounce), ..self }
+    }
+
+    /// Notifies about every crossed level, even if notifying about one of them fails. Returns the
+    /// first error.
+    pub fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        let notifier = &mut self.notifier;
+
+        first_error(self.thresholds.check(data).iter().map(|alert| notifier.notify(alert)))
+    }
+}
+
+impl<N: Notifier> ReadingSink for AlertSink<N> {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        AlertSink::publish(self, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::sim::reading;
+    use super::*;
+
+    fn after(start: Instant, secs: u64) -> Instant {
+        start.checked_add(Duration::from_secs(secs)).unwrap()
+    }
+
+    #[test]
+    fn crossing_is_debounced() {
+        let mut thresholds = Thresholds::new().level(Metric::ECo2, 1000);
+        let start = Instant::now();
+
+        assert!(thresholds.check_at(&reading(1040, 60), start).is_empty());
+        assert!(thresholds.check_at(&reading(1040, 60), after(start, 59)).is_empty());
+
+        let alerts = thresholds.check_at(&reading(1050, 60), after(start, 60));
+        assert_eq!(alerts, [Alert {
+            metric: Metric::ECo2, level: 1000, direction: Direction::Above, value: 1050, e_co2: 1050, t_voc: 60
+        }]);
+        // Staying above doesn't alert again
+        assert!(thresholds.check_at(&reading(1100, 60), after(start, 120)).is_empty());
+    }
+
+    #[test]
+    fn dropping_back_restarts_the_debounce() {
+        let mut thresholds = Thresholds::new().level(Metric::ECo2, 1000);
+        let start = Instant::now();
+
+        assert!(thresholds.check_at(&reading(1040, 60), start).is_empty());
+        assert!(thresholds.check_at(&reading(990, 60), after(start, 30)).is_empty());
+        assert!(thresholds.check_at(&reading(1040, 60), after(start, 40)).is_empty());
+        assert!(thresholds.check_at(&reading(1040, 60), after(start, 90)).is_empty());
+        assert_eq!(thresholds.check_at(&reading(1040, 60), after(start, 100)).len(), 1);
+    }
+
+    #[test]
+    fn crossing_back_below_alerts() {
+        let mut thresholds = Thresholds::new()
+            .level(Metric::TVoc, 500)
+            .with_debounce(Duration::ZERO);
+        let start = Instant::now();
+
+        let alerts = thresholds.check_at(&reading(800, 520), start);
+        assert_eq!((alerts[0].metric, alerts[0].direction, alerts[0].value), (Metric::TVoc, Direction::Above, 520));
+
+        let alerts = thresholds.check_at(&reading(800, 499), after(start, 1));
+        assert_eq!((alerts[0].direction, alerts[0].value), (Direction::Below, 499));
+        assert!(thresholds.check_at(&reading(800, 300), after(start, 2)).is_empty());
+    }
+
+    #[test]
+    fn alert_as_text_and_json() {
+        let alert = Alert {
+            metric: Metric::ECo2, level: 1000, direction: Direction::Above, value: 1040, e_co2: 1040, t_voc: 60
+        };
+
+        assert_eq!(alert.to_string(), "eCO2 above 1000ppm: 1040ppm");
+        assert_eq!(
+            alert.to_json(),
+            r#"{"metric":"eco2","level":1000,"direction":"above","value":1040,"e_co2":1040,"t_voc":60}"#
+        );
+    }
+
+    #[test]
+    fn sink_notifies_every_crossed_level() {
+        let notified = Rc::new(RefCell::new(Vec::new()));
+        let recorded = notified.clone();
+        let mut alerts = AlertSink::new(move |alert: &Alert| -> Result<(), BoxError> {
+                recorded.borrow_mut().push(alert.to_string());
+                Err("offline".into())
+            })
+            .level(Metric::ECo2, 1000)
+            .level(Metric::TVoc, 500)
+            .with_debounce(Duration::ZERO);
+
+        assert!(alerts.publish(&reading(900, 100)).is_ok());
+        assert!(alerts.publish(&reading(1200, 600)).is_err());
+        assert_eq!(*notified.borrow(), ["eCO2 above 1000ppm: 1200ppm", "tVOC above 500ppb: 600ppb"]);
+    }
+}
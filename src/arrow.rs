@@ -0,0 +1,80 @@
+use ::arrow::array::{ArrayRef, BooleanArray, DurationMillisecondArray, TimestampMillisecondArray, UInt16Array, UInt8Array};
+use ::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use ::arrow::error::ArrowError;
+use ::arrow::record_batch::RecordBatch;
+use std::convert::TryFrom;
+use std::path::Path;
+use std::sync::Arc;
+use crate::file::read_log;
+use crate::format::LoggedReading;
+use crate::history::History;
+
+/// Converts the readings of the ring buffer of the driver to a record batch, oldest first. The
+/// readings have no timestamp, but `since_start` tells when they were sampled relative to `start()`.
+/// Columns are `e_co2`, `t_voc`, `warming_up`, `current_ua`, `adc` and `since_start`, which is null in
+/// Idle mode.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+/// let mut ccs811 = ccs811::new(i2c, None).with_history(3600);
+///
+/// // ... read for a while
+///
+/// let batch = ccs811::arrow::from_history(ccs811.history()).expect("Could not convert history");
+/// ```
+pub fn from_history(history: &History) -> Result<RecordBatch, ArrowError> {
+    let schema = Schema::new(vec![
+        Field::new("e_co2", DataType::UInt16, false),
+        Field::new("t_voc", DataType::UInt16, false),
+        Field::new("warming_up", DataType::Boolean, false),
+        Field::new("current_ua", DataType::UInt8, false),
+        Field::new("adc", DataType::UInt16, false),
+        Field::new("since_start", DataType::Duration(TimeUnit::Millisecond), true)
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt16Array::from_iter_values(history.iter().map(|data| data.e_co2))),
+        Arc::new(UInt16Array::from_iter_values(history.iter().map(|data| data.t_voc))),
+        Arc::new(BooleanArray::from(history.iter().map(|data| data.is_warming_up()).collect::<Vec<_>>())),
+        Arc::new(UInt8Array::from_iter_values(history.iter().map(|data| data.raw.current_ua()))),
+        Arc::new(UInt16Array::from_iter_values(history.iter().map(|data| data.raw.adc()))),
+        Arc::new(history.iter()
+            .map(|data| data.since_start.map(|since_start| i64::try_from(since_start.as_millis()).unwrap_or(i64::MAX)))
+            .collect::<DurationMillisecondArray>())
+    ];
+
+    RecordBatch::try_new(Arc::new(schema), columns)
+}
+
+/// Converts readings parsed from a log to a record batch. Columns are `timestamp` in milliseconds UTC,
+/// `e_co2`, `t_voc` and `warming_up`.
+pub fn from_log(readings: &[LoggedReading]) -> Result<RecordBatch, ArrowError> {
+    let schema = Schema::new(vec![
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+        Field::new("e_co2", DataType::UInt16, false),
+        Field::new("t_voc", DataType::UInt16, false),
+        Field::new("warming_up", DataType::Boolean, false)
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(TimestampMillisecondArray::from_iter_values(readings.iter().map(LoggedReading::timestamp_millis))
+            .with_timezone("UTC")),
+        Arc::new(UInt16Array::from_iter_values(readings.iter().map(|reading| reading.e_co2))),
+        Arc::new(UInt16Array::from_iter_values(readings.iter().map(|reading| reading.t_voc))),
+        Arc::new(BooleanArray::from(readings.iter().map(|reading| reading.warming_up).collect::<Vec<_>>()))
+    ];
+
+    RecordBatch::try_new(Arc::new(schema), columns)
+}
+
+/// Reads a log written by `FileSink` into a record batch, see `from_log()` and `file::read_log()`
+///
+/// # Examples
+///
+/// ```no_run
+/// let batch = ccs811::arrow::read_log_file("/var/log/ccs811/readings.jsonl").expect("Could not read log");
+/// println!("{} readings", batch.num_rows());
+/// ```
+pub fn read_log_file<P: AsRef<Path>>(path: P) -> Result<RecordBatch, ArrowError> {
+    from_log(&read_log(path)?)
+}
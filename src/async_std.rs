@@ -0,0 +1,193 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+#[cfg(feature = "flash")]
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use async_std::task::spawn_blocking;
+use crate::chip::{CCS811, Ccs811Data, Interrupts};
+use crate::constants::Ccs811Mode;
+#[cfg(feature = "flash")]
+use crate::firmware::FirmwareVersion;
+use crate::error::{Ccs811Error, SourceError};
+#[cfg(feature = "rppal")]
+use crate::interrupt::DataReadyInterrupt;
+
+/// Async wrapper of the CCS811 chip for the async-std runtime. All chip operations are moved to
+/// async-std's blocking thread pool, so neither the I2C transactions nor the waits the chip needs
+/// between commands (up to 500ms while flashing) block the executor.
+/// The wrapper is cheap to clone and all clones share the same chip.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async_std::task::block_on(async {
+/// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+/// let mut ccs811 = ccs811::async_std::AsyncCCS811::new(ccs811::new(i2c, None));
+///
+/// ccs811.begin().await.expect("Could not init the chip");
+/// ccs811.start(ccs811::MODE::Sec1).await.expect("Could not start");
+///
+/// match ccs811.read().await {
+///   Ok(data) => println!("t_voc: {}, e_co2: {}", data.t_voc, data.e_co2),
+///   Err(error) => println!("Could not read data: {}", error)
+/// }
+/// # });
+/// ```
+pub struct AsyncCCS811<I2C, WAKE, RESET> {
+    chip: Arc<Mutex<CCS811<I2C, WAKE, RESET>>>
+}
+
+impl<I2C, WAKE, RESET> Clone for AsyncCCS811<I2C, WAKE, RESET> {
+    fn clone(&self) -> Self {
+        AsyncCCS811 { chip: self.chip.clone() }
+    }
+}
+
+impl<I2C, WAKE, RESET, E> AsyncCCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E> + Send + 'static,
+          E: SourceError,
+          WAKE: OutputPin + Send + 'static,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin + Send + 'static,
+          <RESET as OutputPin>::Error: SourceError {
+
+    pub fn new(chip: CCS811<I2C, WAKE, RESET>) -> Self {
+        AsyncCCS811 { chip: Arc::new(Mutex::new(chip)) }
+    }
+
+    /// Runs the operation with exclusive access to the chip on the blocking thread pool
+    async fn run<T, F>(&self, operation: F) -> T
+        where F: FnOnce(&mut CCS811<I2C, WAKE, RESET>) -> T + Send + 'static,
+              T: Send + 'static {
+        let chip = self.chip.clone();
+
+        spawn_blocking(move || {
+            // A panic in another operation doesn't leave the chip in an unusable state
+            let mut chip = chip.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            operation(&mut chip)
+        }).await
+    }
+
+    /// See [`CCS811::begin`](../chip/struct.CCS811.html#method.begin)
+    pub async fn begin(&self) -> Result<(), Ccs811Error> {
+        self.run(|chip| chip.begin()).await
+    }
+
+    /// See [`CCS811::hard_reset`](../chip/struct.CCS811.html#method.hard_reset)
+    pub async fn hard_reset(&self) -> Result<(), Ccs811Error> {
+        self.run(|chip| chip.hard_reset()).await
+    }
+
+    /// See [`CCS811::start`](../chip/struct.CCS811.html#method.start)
+    pub async fn start(&self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.run(move |chip| chip.start(mode)).await
+    }
+
+    /// See [`CCS811::quick_start`](../chip/struct.CCS811.html#method.quick_start)
+    pub async fn quick_start(&self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.run(move |chip| chip.quick_start(mode)).await
+    }
+
+    /// See [`CCS811::quick_start_with_baseline`](../chip/struct.CCS811.html#method.quick_start_with_baseline)
+    pub async fn quick_start_with_baseline(&self, mode: Ccs811Mode, baseline: u16) -> Result<(), Ccs811Error> {
+        self.run(move |chip| chip.quick_start_with_baseline(mode, baseline)).await
+    }
+
+    /// See [`CCS811::read`](../chip/struct.CCS811.html#method.read)
+    pub async fn read(&self) -> Result<Ccs811Data, Ccs811Error> {
+        self.run(|chip| chip.read()).await
+    }
+
+    /// Same as [`CCS811::sleep_until_next_sample`](../chip/struct.CCS811.html#method.sleep_until_next_sample)
+    /// but suspends instead of blocking a thread while waiting
+    pub async fn sleep_until_next_sample(&self) -> Result<Ccs811Data, Ccs811Error> {
+        let eta = self.run(|chip| chip.next_sample_eta().ok_or(Ccs811Error::NotSampling)).await?;
+        async_std::task::sleep(eta.saturating_duration_since(Instant::now())).await;
+
+        self.read().await
+    }
+
+    /// See [`CCS811::start_with_interrupt`](../chip/struct.CCS811.html#method.start_with_interrupt)
+    pub async fn start_with_interrupt(&self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.run(move |chip| chip.start_with_interrupt(mode)).await
+    }
+
+    /// See [`CCS811::set_interrupts`](../chip/struct.CCS811.html#method.set_interrupts)
+    pub async fn set_interrupts(&self, interrupts: Interrupts) -> Result<(), Ccs811Error> {
+        self.run(move |chip| chip.set_interrupts(interrupts)).await
+    }
+
+    /// See [`CCS811::interrupts`](../chip/struct.CCS811.html#method.interrupts)
+    pub async fn interrupts(&self) -> Result<Interrupts, Ccs811Error> {
+        self.run(|chip| chip.interrupts()).await
+    }
+
+    /// Suspends until the chip signals a new sample on nINT and reads it. In contrast to polling
+    /// with `read()` no CPU is used between the samples.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async_std::task::block_on(async {
+    /// # let ccs811 = ccs811::async_std::AsyncCCS811::new(ccs811::new(rppal::i2c::I2c::with_bus(1).unwrap(), None));
+    /// # let mut interrupt = ccs811::interrupt::DataReadyInterrupt::new(rppal::gpio::Gpio::new().unwrap().get(22).unwrap()).unwrap();
+    /// ccs811.start_with_interrupt(ccs811::MODE::Sec10).await.expect("Could not start");
+    ///
+    /// loop {
+    ///   match ccs811.next_sample(&mut interrupt).await {
+    ///     Ok(data) => println!("t_voc: {}, e_co2: {}", data.t_voc, data.e_co2),
+    ///     Err(error) => println!("Could not read data: {}", error)
+    ///   }
+    /// }
+    /// # });
+    /// ```
+    #[cfg(feature = "rppal")]
+    pub async fn next_sample(&self, interrupt: &mut DataReadyInterrupt) -> Result<Ccs811Data, Ccs811Error> {
+        interrupt.wait().await;
+
+        self.read().await
+    }
+
+    /// See [`CCS811::set_env_data`](../chip/struct.CCS811.html#method.set_env_data)
+    pub async fn set_env_data(&self, humidity: f32, temperature: f32) -> Result<(), Ccs811Error> {
+        self.run(move |chip| chip.set_env_data(humidity, temperature)).await
+    }
+
+    /// See [`CCS811::get_baseline`](../chip/struct.CCS811.html#method.get_baseline)
+    pub async fn get_baseline(&self) -> Result<u16, Ccs811Error> {
+        self.run(|chip| chip.get_baseline()).await
+    }
+
+    /// See [`CCS811::set_baseline`](../chip/struct.CCS811.html#method.set_baseline)
+    pub async fn set_baseline(&self, baseline: u16) -> Result<(), Ccs811Error> {
+        self.run(move |chip| chip.set_baseline(baseline)).await
+    }
+
+    /// See [`CCS811::hardware_version`](../chip/struct.CCS811.html#method.hardware_version)
+    pub async fn hardware_version(&self) -> Result<u8, Ccs811Error> {
+        self.run(|chip| chip.hardware_version()).await
+    }
+
+    /// See [`CCS811::bootloader_version`](../chip/struct.CCS811.html#method.bootloader_version)
+    pub async fn bootloader_version(&self) -> Result<[u8; 2], Ccs811Error> {
+        self.run(|chip| chip.bootloader_version()).await
+    }
+
+    /// See [`CCS811::application_version`](../chip/struct.CCS811.html#method.application_version)
+    pub async fn application_version(&self) -> Result<[u8; 2], Ccs811Error> {
+        self.run(|chip| chip.application_version()).await
+    }
+
+    /// See [`CCS811::flash`](../chip/struct.CCS811.html#method.flash)
+    #[cfg(feature = "flash")]
+    pub async fn flash(&self, data: Vec<u8>) -> Result<FirmwareVersion, Ccs811Error> {
+        self.run(move |chip| chip.flash(data)).await
+    }
+
+    /// See [`CCS811::flash_from_reader`](../chip/struct.CCS811.html#method.flash_from_reader)
+    #[cfg(feature = "flash")]
+    pub async fn flash_from_reader<R: Read + Send + 'static>(&self, reader: R) -> Result<FirmwareVersion, Ccs811Error> {
+        self.run(move |chip| chip.flash_from_reader(reader)).await
+    }
+}
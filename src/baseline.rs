@@ -0,0 +1,164 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::chip::CCS811;
+use crate::error::{Ccs811Error, SourceError};
+
+/// Saves the baseline as decimal number to the file, so it can be restored with `load()` after a
+/// reboot. The file is replaced at once, an interrupted save keeps the previous baseline.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// let baseline = ccs811.get_baseline().expect("Could not read baseline");
+/// ccs811::baseline::save("/var/lib/ccs811/baseline", baseline).expect("Could not save baseline");
+/// ```
+pub fn save<P: AsRef<Path>>(path: P, baseline: u16) -> io::Result<()> {
+    let path = path.as_ref();
+    let temporary = path.with_extension("tmp");
+
+    fs::write(&temporary, baseline.to_string())?;
+    fs::rename(&temporary, path)
+}
+
+/// Loads a baseline saved by `save()`, a file not containing a baseline is reported as
+/// `io::ErrorKind::InvalidData`
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// let baseline = ccs811::baseline::load("/var/lib/ccs811/baseline").expect("Could not load baseline");
+/// ccs811.set_baseline(baseline).expect("Could not restore baseline");
+/// ```
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<u16> {
+    fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Raised when the baseline changes faster than configured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftAlert {
+    /// Oldest baseline within the observed window
+    pub previous: u16,
+    pub current: u16,
+    /// Time between the previous and the current baseline
+    pub elapsed: Duration,
+    /// Absolute change of the baseline per hour
+    pub rate_per_hour: f32
+}
+
+type DriftCallback = Box<dyn FnMut(&DriftAlert) + Send>;
+
+/// Keeps track of the baseline over time. The chip corrects its baseline automatically, but a
+/// baseline drifting fast usually means the sensor got poisoned, the environment is bad or the
+/// sensor needs to be recalibrated.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::thread::sleep;
+/// # use std::time::Duration;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::baseline::BaselineMonitor;
+///
+/// let mut monitor = BaselineMonitor::new(50.0)
+///     .on_drift(|alert| println!("Baseline drifts by {} per hour", alert.rate_per_hour));
+///
+/// loop {
+///   monitor.check(&mut ccs811).expect("Could not read baseline");
+///   sleep(Duration::from_secs(600));
+/// }
+/// ```
+pub struct BaselineMonitor {
+    max_rate_per_hour: f32,
+    window: Duration,
+    min_elapsed: Duration,
+    history: VecDeque<(u16, Instant)>,
+    callback: Option<DriftCallback>
+}
+
+impl BaselineMonitor {
+
+    /// Alerts when the baseline changes by more than `max_rate_per_hour` within 24 hours. The rate
+    /// is only evaluated after at least one hour of history to not alert on short fluctuations.
+    pub fn new(max_rate_per_hour: f32) -> BaselineMonitor {
+        BaselineMonitor {
+            max_rate_per_hour,
+            window: Duration::from_secs(24 * 3600),
+            min_elapsed: Duration::from_secs(3600),
+            history: VecDeque::new(),
+            callback: None
+        }
+    }
+
+    /// Time span the drift is calculated over
+    pub fn with_window(self, window: Duration) -> BaselineMonitor {
+        BaselineMonitor { window, ..self }
+    }
+
+    /// Minimum history before the drift is evaluated
+    pub fn with_min_elapsed(self, min_elapsed: Duration) -> BaselineMonitor {
+        BaselineMonitor { min_elapsed, ..self }
+    }
+
+    /// Called for every recorded baseline which drifted too fast
+    pub fn on_drift<F>(self, callback: F) -> BaselineMonitor where F: FnMut(&DriftAlert) + Send + 'static {
+        BaselineMonitor { callback: Some(Box::new(callback)), ..self }
+    }
+
+    /// Reads the current baseline from the chip and records it
+    pub fn check<I2C, WAKE, RESET, E>(&mut self, chip: &mut CCS811<I2C, WAKE, RESET>) -> Result<Option<DriftAlert>, Ccs811Error>
+        where I2C: Write<Error = E> + WriteRead<Error = E>,
+              E: SourceError,
+              WAKE: OutputPin,
+              <WAKE as OutputPin>::Error: SourceError,
+              RESET: OutputPin,
+              <RESET as OutputPin>::Error: SourceError {
+        let baseline = chip.get_baseline()?;
+
+        Ok(self.record(baseline))
+    }
+
+    /// Records a baseline read just now
+    pub fn record(&mut self, baseline: u16) -> Option<DriftAlert> {
+        self.record_at(baseline, Instant::now())
+    }
+
+    /// Records a baseline read at the given time
+    pub fn record_at(&mut self, baseline: u16, at: Instant) -> Option<DriftAlert> {
+        while let Some((_, recorded)) = self.history.front() {
+            if at.saturating_duration_since(*recorded) <= self.window {
+                break;
+            }
+            self.history.pop_front();
+        }
+        self.history.push_back((baseline, at));
+
+        let (previous, since) = *self.history.front()?;
+        let elapsed = at.saturating_duration_since(since);
+        if elapsed < self.min_elapsed || elapsed.as_secs_f32() <= 0.0 {
+            return None;
+        }
+
+        let change = (f32::from(baseline) - f32::from(previous)).abs();
+        let rate_per_hour = change / elapsed.as_secs_f32() * 3600.0;
+        if rate_per_hour <= self.max_rate_per_hour {
+            return None;
+        }
+
+        let alert = DriftAlert { previous, current: baseline, elapsed, rate_per_hour };
+        if let Some(callback) = &mut self.callback {
+            callback(&alert);
+        }
+
+        Some(alert)
+    }
+}
@@ -0,0 +1,627 @@
+//! Command line tool to read the chip on the Raspberry Pi.
+//!
+//! Usage: ccs811ctl [--bus <bus>] [--registry <file> --sensor <label>] <command>
+//!
+//! `--sensor kitchen` uses the bus, address and wake pin of a sensor of the registry, see
+//! `ccs811::registry::SensorRegistry`. `read` and `watch` apply its calibration profile and validation
+//! and `watch` samples in its mode unless `--mode` or `--interval` is given.
+//!
+//! `read` prints a single reading, `watch` keeps printing readings until it's interrupted. Both take
+//! `--format json|csv|influx|plain` so the output can be piped into other tools. `watch --interval 10`
+//! prints a reading every 10 seconds and picks the slowest mode sampling often enough for it. Readings
+//! which aren't valid yet are noted on stderr. `watch --advice` notes on stderr when to open a window,
+//! `--room-volume` tells the volume of the room in m³ for it.
+//!
+//! The plain readings of `watch` end with sparklines of eCO2 and tVOC over the last readings printed.
+//! On a terminal they're colored by the air quality unless `NO_COLOR` is set, `--color always|never`
+//! overrides it.
+//!
+//! `flash <firmware> [--sha256 <hash>]` verifies the image, shows the versions on the chip and in the image
+//! and asks before erasing the application on the chip, unless `--yes` is given.
+//!
+//! `baseline save <file>` and `baseline restore <file>` keep the baseline of a running chip across
+//! reboots, e.g. from cron. The chip isn't reset for them as this would discard the baseline.
+//!
+//! `env --humidity 48.5 --temperature 23.3` writes the environment to the running chip for its
+//! compensation, e.g. from a script reading another sensor.
+//!
+//! `dashboard` shows the live readings, charts of the last hour, the baseline, the status of the chip
+//! and the errors so far in the terminal until `q` is pressed, e.g. for debugging over SSH. It samples
+//! in the mode of the sensor or 1s unless `--mode` is given and puts the chip into Idle mode on exit.
+//!
+//! `bridge --listen 0.0.0.0:8811` serves the bus to `ccs811::remote::RemoteBus` clients, so programs on
+//! a workstation drive the chip over TCP. The wake pin of the sensor is held low meanwhile.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use rppal::gpio::Gpio;
+use rppal::i2c::I2c;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph, Wrap};
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::mem;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant, SystemTime};
+use ccs811::MODE;
+use ccs811::baseline;
+use ccs811::chip::{AirQuality, CCS811, Ccs811Data};
+use ccs811::diagnostics::Metrics;
+use ccs811::firmware::{FirmwareImage, FirmwareVersion};
+use ccs811::format::{sparkline, Format};
+use ccs811::health::Health;
+use ccs811::registry::{SensorConfig, SensorRegistry};
+use ccs811::remote::Bridge;
+use ccs811::rppal::RppalPin;
+use ccs811::ventilation::{Room, VentilationAdvisor};
+
+#[derive(Parser)]
+#[command(version, about = "Read the CCS811 air quality sensor")]
+struct Cli {
+    /// I2C bus the chip is connected to
+    #[arg(long, default_value_t = 1)]
+    bus: u8,
+    /// File listing the sensors of the deployment
+    #[arg(long, requires = "sensor")]
+    registry: Option<PathBuf>,
+    /// Sensor of the registry to use instead of the chip at 0x5A on the bus
+    #[arg(long, requires = "registry", conflicts_with = "bus")]
+    sensor: Option<String>,
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print a single reading
+    Read {
+        #[arg(long, default_value_t = Format::Plain)]
+        format: Format
+    },
+    /// Print readings until interrupted
+    Watch {
+        #[arg(long, default_value_t = Format::Plain)]
+        format: Format,
+        /// Seconds between printed readings, every sample is printed without
+        #[arg(long)]
+        interval: Option<u64>,
+        /// Idle is not allowed as nothing would be read. Defaults to 1s or the slowest mode sampling at
+        /// least once per interval.
+        #[arg(long, value_parser = parse_mode)]
+        mode: Option<MODE>,
+        /// Note on stderr when and how long to open a window
+        #[arg(long)]
+        advice: bool,
+        /// Volume of the room in m³ for the advice
+        #[arg(long, default_value_t = Room::default().volume, requires = "advice")]
+        room_volume: f32,
+        /// Color plain readings by the air quality, by default on a terminal unless NO_COLOR is set
+        #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+        color: ColorChoice
+    },
+    /// Flash a new application firmware
+    Flash {
+        /// Binary like CCS811_FW_App_v2-0-1.bin, the version is taken from the file name
+        firmware: PathBuf,
+        /// SHA-256 hash the firmware has to match, by default the known hash of its version
+        #[arg(long)]
+        sha256: Option<String>,
+        /// Don't ask before erasing the application on the chip
+        #[arg(long)]
+        yes: bool
+    },
+    /// Save or restore the baseline of the running chip
+    Baseline {
+        #[command(subcommand)]
+        command: BaselineCommand
+    },
+    /// Write the humidity and temperature for compensation to the running chip
+    Env {
+        /// Relative humidity in %
+        #[arg(long)]
+        humidity: f32,
+        /// Temperature in °C
+        #[arg(long)]
+        temperature: f32
+    },
+    /// Show live readings, charts of the last hour, the baseline, chip status and errors
+    Dashboard {
+        /// Idle is not allowed as nothing would be read. Defaults to the mode of the sensor or 1s.
+        #[arg(long, value_parser = parse_mode)]
+        mode: Option<MODE>
+    },
+    /// Serve the I2C bus over TCP to remote programs using ccs811::remote::RemoteBus
+    Bridge {
+        /// Address and port to listen on
+        #[arg(long, default_value = "0.0.0.0:8811")]
+        listen: String
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never
+}
+
+impl ColorChoice {
+
+    fn enabled(&self) -> bool {
+        match self {
+            ColorChoice::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false
+        }
+    }
+}
+
+// Readings the sparklines of watch span
+const SPARKLINE_LENGTH: usize = 20;
+
+/// ANSI escape code of the color of the quality
+fn ansi_color(quality: AirQuality) -> &'static str {
+    match quality {
+        AirQuality::Excellent => "\x1b[32m",
+        AirQuality::Good => "\x1b[92m",
+        AirQuality::Moderate => "\x1b[33m",
+        AirQuality::Poor => "\x1b[91m",
+        AirQuality::Unhealthy => "\x1b[31m"
+    }
+}
+
+#[derive(Subcommand)]
+enum BaselineCommand {
+    /// Save the current baseline to the file
+    Save {
+        file: PathBuf
+    },
+    /// Write the baseline saved in the file to the chip
+    Restore {
+        file: PathBuf
+    }
+}
+
+/// The slowest mode sampling at least once per interval
+fn mode_for(interval: Duration) -> MODE {
+    [MODE::Sec60, MODE::Sec10]
+        .iter()
+        .copied()
+        .find(|mode| mode.period().is_some_and(|period| period <= interval))
+        .unwrap_or(MODE::Sec1)
+}
+
+fn parse_mode(mode: &str) -> Result<MODE, String> {
+    match mode.parse()? {
+        MODE::Idle => Err("Idle mode doesn't sample, use 1s, 10s, 60s or 250ms".to_string()),
+        mode => Ok(mode)
+    }
+}
+
+fn print(format: Format, data: &Ccs811Data) {
+    println!("{}", format.format(data, SystemTime::now()));
+}
+
+fn fail(message: &str, error: impl std::fmt::Display) -> ! {
+    eprintln!("{}: {}", message, error);
+    process::exit(1);
+}
+
+fn version(version: Result<[u8; 2], impl std::fmt::Display>) -> String {
+    version.map(|bytes| FirmwareVersion::from_bytes(bytes).to_string())
+        .unwrap_or_else(|error| format!("unknown ({})", error))
+}
+
+/// Attaches to the chip without initializing it, which would reset a running chip
+fn attach(sensor: &SensorConfig) -> CCS811<I2c, RppalPin> {
+    let i2c = I2c::with_bus(sensor.bus).unwrap_or_else(|error| fail("Could not open I2C bus", error));
+    let wake = sensor.wake_pin.map(|bcm| Gpio::new().and_then(|gpio| gpio.get(bcm))
+        .unwrap_or_else(|error| fail("Could not open wake pin", error)));
+
+    ccs811::new(i2c, wake).with_address(sensor.address)
+}
+
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y")
+}
+
+fn flash(sensor: &SensorConfig, firmware: PathBuf, sha256: Option<String>, yes: bool) {
+    let image = FirmwareImage::open(&firmware)
+        .unwrap_or_else(|error| fail("Could not load firmware", error));
+    // Checked again while flashing, but a wrong file should be reported before asking
+    match &sha256 {
+        Some(sha256) => image.verify(sha256),
+        None => image.verify_known()
+    }.unwrap_or_else(|error| fail("Invalid firmware", error));
+
+    // Not initialized as the application on the chip might be broken, which is why it's flashed
+    let mut ccs811 = attach(sensor);
+
+    println!("Boot loader version: {}", version(ccs811.bootloader_version()));
+    println!("Application version: {}", version(ccs811.application_version()));
+    println!("Image: {} ({} bytes, version {})", firmware.display(), image.len(),
+        image.version().map(|version| version.to_string()).unwrap_or_else(|| "unknown".to_string()));
+
+    if !yes && !confirm("Erase the application on the chip and flash the image?") {
+        return;
+    }
+
+    let bar = ProgressBar::new(image.len() as u64)
+        .with_style(ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} {eta}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()));
+    let result = ccs811.flash_image_with_progress(&image, sha256.as_deref(), |written| bar.set_position(written as u64));
+    bar.finish_and_clear();
+
+    match result {
+        Ok(version) => println!("Flashed version {}", version),
+        Err(error) => fail("Could not flash firmware", error)
+    }
+}
+
+fn baseline(sensor: &SensorConfig, command: BaselineCommand) {
+    // Attached without reset, which would discard the baseline of the running chip
+    let mut ccs811 = attach(sensor);
+
+    match command {
+        BaselineCommand::Save { file } => {
+            let baseline = ccs811.get_baseline()
+                .unwrap_or_else(|error| fail("Could not read baseline", error));
+            baseline::save(&file, baseline)
+                .unwrap_or_else(|error| fail(&format!("Could not save baseline to {}", file.display()), error));
+            println!("Saved baseline {}", baseline);
+        },
+        BaselineCommand::Restore { file } => {
+            let baseline = baseline::load(&file)
+                .unwrap_or_else(|error| fail(&format!("Could not load baseline from {}", file.display()), error));
+            ccs811.set_baseline(baseline)
+                .unwrap_or_else(|error| fail("Could not restore baseline", error));
+            println!("Restored baseline {}", baseline);
+        }
+    }
+}
+
+fn env(sensor: &SensorConfig, humidity: f32, temperature: f32) {
+    // Attached without reset, which would stop the running chip
+    let mut ccs811 = attach(sensor);
+    ccs811.set_env_data(humidity, temperature)
+        .unwrap_or_else(|error| fail("Could not set environment", error));
+    println!("Set humidity {:.1}% and temperature {:.1}°C", humidity, temperature);
+}
+
+fn bridge(sensor: &SensorConfig, listen: &str) {
+    let i2c = I2c::with_bus(sensor.bus).unwrap_or_else(|error| fail("Could not open I2C bus", error));
+    // Remote clients can't wake the chip, so it's kept awake while bridging
+    let _wake = sensor.wake_pin.map(|bcm| Gpio::new().and_then(|gpio| gpio.get(bcm))
+        .map(|pin| {
+            let mut pin = pin.into_output();
+            pin.set_low();
+            pin
+        })
+        .unwrap_or_else(|error| fail("Could not open wake pin", error)));
+    let listener = TcpListener::bind(listen).unwrap_or_else(|error| fail(&format!("Could not listen on {}", listen), error));
+
+    eprintln!("Serving I2C bus {} on {}", sensor.bus, listen);
+    let mut bridge = Bridge::new(i2c);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("Could not accept client: {}", error);
+                continue;
+            }
+        };
+        let client = stream.peer_addr().map(|address| address.to_string()).unwrap_or_else(|_| "unknown".to_string());
+        eprintln!("Client {} connected", client);
+        match bridge.handle(stream) {
+            Ok(()) => eprintln!("Client {} disconnected", client),
+            Err(error) => eprintln!("Client {} disconnected: {}", client, error)
+        }
+    }
+}
+
+// Time span of the charts
+const CHART_SPAN: Duration = Duration::from_secs(60 * 60);
+// How often the dashboard looks for key presses while waiting for the next sample
+const KEY_POLL: Duration = Duration::from_millis(250);
+
+/// What the dashboard shows, updated with every sample
+struct Dashboard {
+    mode: MODE,
+    latest: Option<Ccs811Data>,
+    baseline: Result<u16, String>,
+    health: Health,
+    metrics: Metrics,
+    // Failed reads by the kind of error
+    errors: BTreeMap<&'static str, u32>,
+    last_error: Option<String>,
+    // Seconds before now and value, oldest first
+    e_co2: Vec<(f64, f64)>,
+    t_voc: Vec<(f64, f64)>
+}
+
+impl Dashboard {
+
+    fn sample(&mut self, ccs811: &mut CCS811<I2c, RppalPin>) {
+        match ccs811.read() {
+            Ok(data) => self.latest = Some(data),
+            Err(error) => {
+                *self.errors.entry(error.kind()).or_default() += 1;
+                self.last_error = Some(error.to_string());
+            }
+        }
+        self.baseline = ccs811.get_baseline().map_err(|error| error.to_string());
+        self.health = ccs811.health();
+        self.metrics = ccs811.metrics();
+
+        // The history holds one sample per period
+        let period = self.mode.period().unwrap_or_default().as_secs_f64();
+        let count = ccs811.history().len();
+        let age = |index: usize| -((count - 1 - index) as f64 * period);
+        self.e_co2 = ccs811.history().iter().enumerate().map(|(index, data)| (age(index), f64::from(data.e_co2))).collect();
+        self.t_voc = ccs811.history().iter().enumerate().map(|(index, data)| (age(index), f64::from(data.t_voc))).collect();
+    }
+}
+
+fn quality_color(quality: AirQuality) -> Color {
+    match quality {
+        AirQuality::Excellent => Color::Green,
+        AirQuality::Good => Color::LightGreen,
+        AirQuality::Moderate => Color::Yellow,
+        AirQuality::Poor => Color::LightRed,
+        AirQuality::Unhealthy => Color::Red
+    }
+}
+
+fn render(frame: &mut Frame, dashboard: &Dashboard) {
+    let [top, e_co2, t_voc, footer] = Layout::vertical([
+        Constraint::Length(9),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+        Constraint::Length(1)
+    ]).areas(frame.area());
+    let [air, chip, errors] = Layout::horizontal([Constraint::Fill(1); 3]).areas(top);
+
+    let air_lines = match &dashboard.latest {
+        Some(data) => {
+            let quality = data.air_quality();
+            let mut lines = vec![
+                Line::from(format!("eCO2  {} ppm", data.e_co2)),
+                Line::from(format!("tVOC  {} ppb", data.t_voc)),
+                Line::from(Span::styled(quality.to_string(), Style::default().fg(quality_color(quality))))
+            ];
+            if data.is_warming_up() {
+                lines.push(Line::from(Span::styled("warming up", Style::default().fg(Color::DarkGray))));
+            }
+            if !data.is_valid() {
+                lines.push(Line::from(Span::styled("not valid yet", Style::default().fg(Color::DarkGray))));
+            }
+            lines
+        },
+        None => vec![Line::from("waiting for the first sample")]
+    };
+    frame.render_widget(Paragraph::new(air_lines).block(Block::bordered().title(" Air ")).wrap(Wrap { trim: true }), air);
+
+    let health_color = match dashboard.health {
+        Health::Healthy => Color::Green,
+        Health::Degraded(_) => Color::Yellow,
+        Health::Failed(_) => Color::Red
+    };
+    let chip_lines = vec![
+        Line::from(format!("Mode      {}", dashboard.mode)),
+        Line::from(format!("Status    {}", dashboard.latest.map_or("-".to_string(), |data| data.status.to_string()))),
+        Line::from(match &dashboard.baseline {
+            Ok(baseline) => format!("Baseline  {:#06x}", baseline),
+            Err(error) => format!("Baseline  {}", error)
+        }),
+        Line::from(Span::styled(format!("Health    {}", dashboard.health), Style::default().fg(health_color)))
+    ];
+    frame.render_widget(Paragraph::new(chip_lines).block(Block::bordered().title(" Chip ")).wrap(Wrap { trim: true }), chip);
+
+    let mut error_lines: Vec<Line> = dashboard.errors.iter()
+        .map(|(kind, count)| Line::from(format!("{:<16}{}", kind, count)))
+        .collect();
+    error_lines.push(Line::from(format!("{:<16}{}", "resets", dashboard.metrics.resets)));
+    error_lines.push(Line::from(format!("{:<16}{}", "wake retries", dashboard.metrics.wake_retries)));
+    error_lines.push(Line::from(format!("{:<16}{}", "stretch retries", dashboard.metrics.clock_stretch_retries)));
+    if let Some(error) = &dashboard.last_error {
+        error_lines.push(Line::from(Span::styled(error.as_str(), Style::default().fg(Color::Red))));
+    }
+    frame.render_widget(Paragraph::new(error_lines).block(Block::bordered().title(" Errors ")).wrap(Wrap { trim: true }), errors);
+
+    render_chart(frame, e_co2, " eCO2 (ppm) ", &dashboard.e_co2, Color::Cyan);
+    render_chart(frame, t_voc, " tVOC (ppb) ", &dashboard.t_voc, Color::Magenta);
+    frame.render_widget(Paragraph::new("q to quit").style(Style::default().fg(Color::DarkGray)), footer);
+}
+
+fn render_chart(frame: &mut Frame, area: Rect, title: &str, points: &[(f64, f64)], color: Color) {
+    // Some headroom above the highest value, rounded to whole tens
+    let max = points.iter().map(|&(_, value)| value).fold(0.0, f64::max);
+    let top = ((max * 1.1) / 10.0).ceil().max(1.0) * 10.0;
+    let span = CHART_SPAN.as_secs_f64();
+
+    let dataset = Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(color))
+        .data(points);
+    let chart = Chart::new(vec![dataset])
+        .block(Block::bordered().title(title))
+        .x_axis(Axis::default().bounds([-span, 0.0]).labels(["-60m", "-30m", "now"]))
+        .y_axis(Axis::default().bounds([0.0, top]).labels(["0".to_string(), format!("{:.0}", top / 2.0), format!("{:.0}", top)]));
+    frame.render_widget(chart, area);
+}
+
+fn dashboard(ccs811: CCS811<I2c, RppalPin>, mode: MODE) {
+    let period = mode.period().unwrap_or(Duration::from_secs(1));
+    let capacity = (CHART_SPAN.as_millis() / period.as_millis().max(1)) as usize;
+    let mut ccs811 = ccs811.with_history(capacity);
+    ccs811.start(mode).unwrap_or_else(|error| fail("Could not start the chip", error));
+
+    let mut dashboard = Dashboard {
+        mode,
+        latest: None,
+        baseline: Err("not read yet".to_string()),
+        health: ccs811.health(),
+        metrics: ccs811.metrics(),
+        errors: BTreeMap::new(),
+        last_error: None,
+        e_co2: Vec::new(),
+        t_voc: Vec::new()
+    };
+    // Restores the terminal on panics as well
+    let mut terminal = ratatui::init();
+    loop {
+        if let Err(error) = terminal.draw(|frame| render(frame, &dashboard)) {
+            ratatui::restore();
+            fail("Could not draw the dashboard", error);
+        }
+
+        let eta = ccs811.next_sample_eta().unwrap_or_else(Instant::now);
+        let timeout = eta.saturating_duration_since(Instant::now()).min(KEY_POLL);
+        if event::poll(timeout).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                let interrupted = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || interrupted) {
+                    break;
+                }
+            }
+        }
+        if Instant::now() >= eta {
+            dashboard.sample(&mut ccs811);
+        }
+    }
+    ratatui::restore();
+
+    // Don't leave the heater running after the session
+    if let Err(error) = ccs811.start(MODE::Idle) {
+        eprintln!("Could not stop the chip: {}", error);
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let registry = cli.registry.as_ref().map(|path| SensorRegistry::load(path)
+        .unwrap_or_else(|error| fail(&format!("Could not load registry {}", path.display()), error)));
+    let registered = registry.as_ref().zip(cli.sensor.as_ref()).map(|(registry, label)| registry.sensor(label)
+        .unwrap_or_else(|| fail("Unknown sensor", label)));
+    let sensor = registered.cloned().unwrap_or_else(|| SensorConfig { bus: cli.bus, ..SensorConfig::new("ccs811") });
+
+    let open = || {
+        let mut ccs811 = sensor.open().unwrap_or_else(|error| fail("Could not init the chip", error));
+        let profile = registry.as_ref().map(|registry| registry.profile(&sensor)).transpose()
+            .unwrap_or_else(|error| fail("Could not load calibration profile", error))
+            .flatten();
+        if let Some(profile) = profile {
+            profile.apply(&mut ccs811).unwrap_or_else(|error| fail("Could not apply calibration profile", error));
+        }
+        ccs811
+    };
+
+    match cli.command {
+        Command::Read { format } => {
+            let mut ccs811 = open();
+            ccs811.start(MODE::Sec1).unwrap_or_else(|error| fail("Could not start the chip", error));
+            let data = ccs811.sleep_until_next_sample();
+            // Don't leave the heater running after a single reading
+            if let Err(error) = ccs811.start(MODE::Idle) {
+                eprintln!("Could not stop the chip: {}", error);
+            }
+
+            if let Some(header) = format.header() {
+                println!("{}", header);
+            }
+            print(format, &data.unwrap_or_else(|error| fail("Could not read data", error)));
+        },
+        Command::Watch { format, interval, mode, advice, room_volume, color } => {
+            let mut ccs811 = open();
+            let interval = interval.map(Duration::from_secs).unwrap_or_default();
+            // An interval asks for the mode fitting it rather than the one of the sensor
+            let mode = mode.or_else(|| registered.filter(|_| interval.is_zero()).map(|sensor| sensor.mode))
+                .unwrap_or_else(|| mode_for(interval));
+            ccs811.start(mode).unwrap_or_else(|error| fail("Could not start the chip", error));
+            // Samples don't arrive exactly on time, so accept them half a period early
+            let tolerance = mode.period().unwrap_or_default() / 2;
+
+            if let Some(header) = format.header() {
+                println!("{}", header);
+            }
+            let mut validation = registered.and_then(|sensor| sensor.validation.stage());
+            let mut advisor = advice.then(|| VentilationAdvisor::new(Room { volume: room_volume, ..Room::default() }));
+            let mut last_advice = None;
+            let color = color.enabled();
+            let mut recent = VecDeque::with_capacity(SPARKLINE_LENGTH);
+            let mut next_print = Instant::now();
+            loop {
+                let data = match ccs811.sleep_until_next_sample() {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Could not read data: {}", error);
+                        continue;
+                    }
+                };
+                let data = match &mut validation {
+                    Some(validation) => match validation.process(data) {
+                        Some(data) => data,
+                        None => continue
+                    },
+                    None => data
+                };
+                // Every sample counts for the trend, but the advice is only noted when it changes
+                if let Some(advisor) = &mut advisor {
+                    let advice = advisor.record(&data);
+                    if last_advice.is_none_or(|last| mem::discriminant(&last) != mem::discriminant(&advice)) {
+                        eprintln!("{}", advice);
+                    }
+                    last_advice = Some(advice);
+                }
+                let now = Instant::now();
+                if now + tolerance < next_print {
+                    continue;
+                }
+                next_print = (next_print + interval).max(now);
+
+                if !data.is_valid() {
+                    eprintln!("Reading not valid yet, the chip reports less than 400ppm eCO2");
+                }
+                if format != Format::Plain {
+                    print(format, &data);
+                    continue;
+                }
+
+                if recent.len() == SPARKLINE_LENGTH {
+                    recent.pop_front();
+                }
+                recent.push_back((data.e_co2, data.t_voc));
+                let e_co2 = sparkline(recent.iter().map(|&(e_co2, _)| e_co2));
+                let t_voc = sparkline(recent.iter().map(|&(_, t_voc)| t_voc));
+                let line = format!("{}  eCO2 {} tVOC {}", format.format(&data, SystemTime::now()), e_co2, t_voc);
+                if color && data.is_valid() {
+                    println!("{}{}\x1b[0m", ansi_color(data.air_quality()), line);
+                } else {
+                    println!("{}", line);
+                }
+            }
+        },
+        Command::Flash { firmware, sha256, yes } => flash(&sensor, firmware, sha256, yes),
+        Command::Baseline { command } => baseline(&sensor, command),
+        Command::Env { humidity, temperature } => env(&sensor, humidity, temperature),
+        Command::Dashboard { mode } => {
+            let mode = mode.or(registered.map(|sensor| sensor.mode)).filter(|&mode| mode != MODE::Idle).unwrap_or(MODE::Sec1);
+            dashboard(open(), mode)
+        },
+        Command::Bridge { listen } => bridge(&sensor, &listen)
+    }
+}
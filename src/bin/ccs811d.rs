@@ -0,0 +1,713 @@
+//! Daemon reading the chip on the Raspberry Pi and publishing the readings to a MQTT broker, a StatsD
+//! server and/or a rotated file.
+//!
+//! Usage: ccs811d <config file>
+//!
+//! On SIGTERM or SIGINT the buffered readings are flushed, the baseline is saved, the chip is put into
+//! Idle mode and the wake pin is raised before exiting. The saved baseline is restored on start.
+//!
+//! A chip which got reset or unplugged is re-initialized when it's back, restoring its mode, baseline
+//! and environment. The outage is logged with `SENSOR` and `OUTAGE` in seconds.
+//!
+//! Built with the `mdns` feature, `[mdns]` announces an endpoint of the sensor as `_ccs811._tcp` service
+//! on the LAN, named after the sensor and the host unless `instance` is given. `txt` adds properties to
+//! the TXT record next to `sensor` and `version`.
+//!
+//! `[control]` serves authenticated HTTP endpoints to manage the sensor remotely, see
+//! `ccs811::control::ControlServer`. A mode set remotely applies until the schedule changes the mode.
+//! Lowering the sampling rate, e.g. from 1s to 60s, puts the chip into Idle mode for 10 minutes
+//! first as the datasheet asks, the request is answered with `202 Accepted` then. Saving and
+//! restoring the baseline use `baseline_file`.
+//!
+//! `[health]` serves `/healthz` and `/readyz` for the liveness and readiness probes of container
+//! deployments, see `ccs811::healthz::HealthProbes`. `/healthz` fails once the chip failed or the
+//! sampling loop hangs for three intervals, `/readyz` while no valid reading is published, e.g. during
+//! the warm-up or while the sensor is disconnected. A sensor which is back clears the failure.
+//!
+//! Logs go to journald with fields like `ERROR_KIND` or `E_CO2`, so `journalctl -u ccs811d ERROR_KIND=i2c`
+//! shows the bus errors. Without journald they go to syslog or stderr.
+//!
+//! The chip is the one at 0x5A on bus 1 unless `sensor` picks one from the `registry` of the deployment,
+//! see `ccs811::registry::SensorRegistry`. Its address, wake pin and calibration profile are used, its
+//! mode unless `mode` is given and its validation applies before the filters. A saved baseline takes
+//! precedence over the baseline of the profile.
+//!
+//! ```toml
+//! # Optional, the sensor of the registry to read
+//! registry = "/etc/ccs811/sensors.conf"
+//! sensor = "kitchen"
+//! # Sampling outside of the schedule, defaults to the mode of the sensor or 1s
+//! mode = "60s"
+//! interval = 60
+//! # Where the baseline is saved by the schedule and on shutdown
+//! baseline_file = "/var/lib/ccs811/baseline"
+//! # Log every published reading
+//! log_readings = false
+//!
+//! [mqtt]
+//! host = "localhost"
+//! topic = "home/air"
+//!
+//! [statsd]
+//! server = "127.0.0.1:8125"
+//! tags = { room = "kitchen" }
+//!
+//! [control]
+//! listen = "0.0.0.0:8081"
+//! token = "change-me"
+//!
+//! [health]
+//! listen = "0.0.0.0:8080"
+//!
+//! [mdns]
+//! port = 8080
+//! instance = "Kitchen"
+//! txt = { path = "/reading" }
+//!
+//! # Newline-delimited JSON or CSV, rotated at 10MB or after a day, 30 gzipped files are kept
+//! [file]
+//! path = "/var/log/ccs811/readings.jsonl"
+//! format = "json"
+//! max_size = 10485760
+//! max_age = 86400
+//! keep = 30
+//! gzip = true
+//!
+//! # Readings pass the filters in this order before being published
+//! [[filter]]
+//! type = "validity"
+//!
+//! [[filter]]
+//! type = "outlier"
+//! window = 5
+//! max_e_co2 = 1000
+//! max_t_voc = 500
+//!
+//! [[filter]]
+//! type = "ewma"
+//! alpha = 0.3
+//!
+//! [[schedule]]
+//! from = "22:00"
+//! to = "07:00"
+//! mode = "idle"
+//!
+//! [[schedule]]
+//! at = "03:00"
+//! action = "save-baseline"
+//! ```
+
+use chrono::{Local, Timelike};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant};
+use rppal::i2c::I2c;
+use ccs811::{CCS811_IDLE_BEFORE_SLOWER_MODE, MODE};
+use ccs811::baseline;
+use ccs811::chip::CCS811;
+use ccs811::control::{Command, ControlRequest, ControlServer};
+use ccs811::error::Ccs811Error;
+use ccs811::file::FileSink;
+use ccs811::filter::{Ewma, MovingAverage};
+use ccs811::format::Format;
+use ccs811::healthz::{HealthProbes, ProbeServer};
+use ccs811::journal::{Journal, JournalSink, Priority};
+#[cfg(feature = "mdns")]
+use ccs811::mdns::{self, Advertisement};
+use ccs811::mqtt::{MqttConfig, MqttPublisher};
+use ccs811::pipeline::{OutlierRejection, Pipeline, PipelineError, ReadingSource, Stage, Validity};
+use ccs811::recovery::RecoveryEvent;
+use ccs811::registry::{SensorConfig, SensorRegistry};
+use ccs811::rppal::RppalPin;
+use ccs811::schedule::{Action, Rule, Sampling, Schedule, TimeOfDay};
+use ccs811::shutdown::Shutdown;
+use ccs811::sink::ReadingSink;
+use ccs811::statsd::StatsdSink;
+
+#[derive(Deserialize)]
+struct Config {
+    mqtt: Option<Mqtt>,
+    statsd: Option<Statsd>,
+    file: Option<FileConfig>,
+    registry: Option<PathBuf>,
+    sensor: Option<String>,
+    control: Option<ControlConfig>,
+    health: Option<HealthConfig>,
+    #[cfg(feature = "mdns")]
+    mdns: Option<MdnsConfig>,
+    mode: Option<String>,
+    #[serde(default = "default_interval")]
+    interval: u64,
+    baseline_file: Option<PathBuf>,
+    #[serde(default)]
+    log_readings: bool,
+    #[serde(default)]
+    schedule: Vec<ScheduleRule>,
+    #[serde(default)]
+    filter: Vec<FilterConfig>
+}
+
+#[derive(Deserialize)]
+struct Mqtt {
+    host: String,
+    port: Option<u16>,
+    topic: String
+}
+
+#[derive(Deserialize)]
+struct Statsd {
+    server: String,
+    #[serde(default)]
+    tags: BTreeMap<String, String>
+}
+
+#[derive(Deserialize)]
+struct ControlConfig {
+    listen: String,
+    token: String
+}
+
+#[derive(Deserialize)]
+struct HealthConfig {
+    listen: String
+}
+
+#[cfg(feature = "mdns")]
+#[derive(Deserialize)]
+struct MdnsConfig {
+    port: u16,
+    instance: Option<String>,
+    #[serde(default)]
+    txt: BTreeMap<String, String>
+}
+
+#[derive(Deserialize)]
+struct FileConfig {
+    path: PathBuf,
+    #[serde(default = "default_file_format")]
+    format: String,
+    max_size: Option<u64>,
+    max_age: Option<u64>,
+    #[serde(default = "default_keep")]
+    keep: usize,
+    #[serde(default)]
+    gzip: bool
+}
+
+#[derive(Deserialize)]
+struct ScheduleRule {
+    from: Option<String>,
+    to: Option<String>,
+    mode: Option<String>,
+    interval: Option<u64>,
+    at: Option<String>,
+    action: Option<String>
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum FilterConfig {
+    Validity,
+    Outlier { window: usize, max_e_co2: u16, max_t_voc: u16 },
+    MovingAverage { window: usize },
+    Ewma { alpha: f32 }
+}
+
+fn default_interval() -> u64 {
+    10
+}
+
+fn default_file_format() -> String {
+    "json".to_string()
+}
+
+fn default_keep() -> usize {
+    5
+}
+
+fn parse_time(time: &Option<String>, field: &str) -> Result<TimeOfDay, String> {
+    time.as_ref()
+        .ok_or_else(|| format!("Schedule rule misses {}", field))?
+        .parse()
+}
+
+fn schedule(config: &Config, sensor: &SensorConfig) -> Result<Schedule, String> {
+    let mode = config.mode.as_deref().map(str::parse).transpose()?.unwrap_or(sensor.mode);
+    let default = Sampling { mode, interval: Duration::from_secs(config.interval) };
+
+    config.schedule.iter().try_fold(Schedule::new(default), |schedule, rule| {
+        let rule = match (&rule.at, &rule.action) {
+            (Some(_), Some(action)) if action == "save-baseline" => {
+                Rule::At { time: parse_time(&rule.at, "at")?, action: Action::SaveBaseline }
+            },
+            (Some(_), Some(action)) => return Err(format!("Unknown action {}, use save-baseline", action)),
+            _ => Rule::Sample {
+                from: parse_time(&rule.from, "from")?,
+                to: parse_time(&rule.to, "to")?,
+                sampling: Sampling {
+                    mode: rule.mode.as_deref().map(str::parse).transpose()?.unwrap_or(mode),
+                    interval: Duration::from_secs(rule.interval.unwrap_or(config.interval))
+                }
+            }
+        };
+
+        Ok(schedule.rule(rule))
+    })
+}
+
+fn sensor(config: &Config) -> Result<(SensorRegistry, SensorConfig), String> {
+    match (&config.registry, &config.sensor) {
+        (Some(path), Some(label)) => {
+            let registry = SensorRegistry::load(path)
+                .map_err(|error| format!("Could not load registry {}: {}", path.display(), error))?;
+            let sensor = registry.sensor(label).cloned()
+                .ok_or_else(|| format!("No sensor {} in registry {}", label, path.display()))?;
+            Ok((registry, sensor))
+        },
+        (None, None) => Ok((SensorRegistry::new(), SensorConfig::new("ccs811"))),
+        _ => Err("Configure both registry and sensor or neither of them".to_string())
+    }
+}
+
+fn load_config(path: &str) -> Result<(Config, SensorRegistry, SensorConfig, Schedule), String> {
+    let content = fs::read_to_string(path)
+        .map_err(|error| format!("Could not read {}: {}", path, error))?;
+    let config: Config = toml::from_str(&content)
+        .map_err(|error| format!("Invalid config {}: {}", path, error))?;
+    let (registry, sensor) = sensor(&config)?;
+    let schedule = schedule(&config, &sensor)?;
+
+    Ok((config, registry, sensor, schedule))
+}
+
+fn now() -> TimeOfDay {
+    let now = Local::now();
+    TimeOfDay::new(now.hour() as u8, now.minute() as u8).expect("Time of day out of range")
+}
+
+// Attempts to re-initialize a chip found reset, reads failing in a row before probing whether the chip
+// left the bus and how often to look for it while it's gone
+const RECOVERY_ATTEMPTS: u8 = 3;
+const HOT_SWAP_FAILURES: u8 = 3;
+const HOT_SWAP_PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+fn log_recovery(journal: &Journal, label: &str, event: &RecoveryEvent) {
+    match event {
+        RecoveryEvent::Lost(reason) => journal.log(Priority::Warning, "Lost the chip", &[
+            ("SENSOR", &label),
+            ("REASON", reason)
+        ]),
+        RecoveryEvent::Recovered { attempts } => journal.log(Priority::Info, "Recovered the chip", &[
+            ("SENSOR", &label),
+            ("ATTEMPTS", attempts)
+        ]),
+        RecoveryEvent::Failed { attempts, error } => journal.log(Priority::Error, "Could not recover the chip", &[
+            ("SENSOR", &label),
+            ("ATTEMPTS", attempts),
+            ("ERROR", error),
+            ("ERROR_KIND", &error.kind())
+        ]),
+        RecoveryEvent::Disconnected => journal.log(Priority::Warning, "Sensor disconnected", &[("SENSOR", &label)]),
+        RecoveryEvent::Reconnected { outage } => journal.log(Priority::Info, "Sensor reconnected", &[
+            ("SENSOR", &label),
+            ("OUTAGE", &outage.as_secs())
+        ])
+    }
+}
+
+// How often the sampling loop looks for control commands and shutdown while waiting for the next sample
+const CONTROL_POLL: Duration = Duration::from_millis(100);
+
+// A mode set via the control server, kept until the schedule changes the mode
+struct ModeOverride {
+    mode: MODE,
+    // Mode of the schedule when the override was set
+    scheduled: MODE,
+    // Until when the chip idles before lowering the sampling rate
+    idle_until: Option<Instant>
+}
+
+impl ModeOverride {
+
+    fn mode(&self) -> MODE {
+        match self.idle_until {
+            Some(until) if Instant::now() < until => MODE::Idle,
+            _ => self.mode
+        }
+    }
+}
+
+fn change_mode(journal: &Journal, ccs811: &mut CCS811<I2c, RppalPin>, mode: MODE) -> Result<(), Ccs811Error> {
+    if mode == ccs811.mode() {
+        return Ok(());
+    }
+
+    let result = ccs811.start(mode);
+    match &result {
+        Ok(()) => journal.log(Priority::Info, "Changed mode", &[("MODE", &mode)]),
+        Err(error) => journal.log(Priority::Error, "Could not change mode", &[
+            ("MODE", &mode),
+            ("ERROR", error),
+            ("ERROR_KIND", &error.kind())
+        ])
+    }
+    result
+}
+
+fn serve_control(config: &Config, journal: &Journal) -> Option<ControlServer> {
+    let control = config.control.as_ref()?;
+
+    match ControlServer::bind(control.listen.as_str(), &control.token) {
+        Ok(server) => {
+            journal.log(Priority::Info, "Serving control endpoints", &[("LISTEN", &control.listen)]);
+            Some(server)
+        },
+        Err(error) => {
+            journal.log(Priority::Error, "Could not serve control endpoints", &[
+                ("LISTEN", &control.listen),
+                ("ERROR", &error)
+            ]);
+            None
+        }
+    }
+}
+
+// Time the health reported after a sample is valid for on top of three intervals, for slow recoveries
+const HEALTH_MARGIN: Duration = Duration::from_secs(30);
+
+fn serve_health(config: &Config, probes: &HealthProbes, journal: &Journal) -> Option<ProbeServer> {
+    let health = config.health.as_ref()?;
+
+    match probes.serve(health.listen.as_str()) {
+        Ok(server) => {
+            journal.log(Priority::Info, "Serving health probes", &[("LISTEN", &health.listen)]);
+            Some(server)
+        },
+        Err(error) => {
+            journal.log(Priority::Error, "Could not serve health probes", &[
+                ("LISTEN", &health.listen),
+                ("ERROR", &error)
+            ]);
+            None
+        }
+    }
+}
+
+// Waits for the next sample, handling control commands in the meantime
+fn wait(shutdown: &Shutdown, control: Option<&ControlServer>, duration: Duration, mut handle: impl FnMut(ControlRequest)) {
+    let deadline = Instant::now() + duration;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || shutdown.is_requested() {
+            return;
+        }
+        match control {
+            Some(control) => if let Some(request) = control.recv_timeout(remaining.min(CONTROL_POLL)) {
+                handle(request);
+            },
+            None => {
+                shutdown.sleep(remaining);
+                return;
+            }
+        }
+    }
+}
+
+fn set_mode(journal: &Journal, ccs811: &mut CCS811<I2c, RppalPin>, mode_override: &mut Option<ModeOverride>,
+            scheduled: MODE, mode: MODE) -> Result<Option<Instant>, String> {
+    let now = Instant::now();
+    let idle_until = if ccs811.mode().slows_down_to(mode) {
+        Some(now + CCS811_IDLE_BEFORE_SLOWER_MODE)
+    } else if ccs811.mode() == MODE::Idle && mode != MODE::Idle {
+        // Still idling before an earlier requested switch to a lower sampling rate
+        mode_override.as_ref().and_then(|mode_override| mode_override.idle_until).filter(|until| now < *until)
+    } else {
+        None
+    };
+
+    let mode_override = mode_override.insert(ModeOverride { mode, scheduled, idle_until });
+    change_mode(journal, ccs811, mode_override.mode())
+        .map(|()| idle_until)
+        .map_err(|error| format!("Could not change mode: {}", error))
+}
+
+fn handle_command(journal: &Journal, ccs811: &mut CCS811<I2c, RppalPin>, mode_override: &mut Option<ModeOverride>,
+                  scheduled: MODE, baseline_file: &Option<PathBuf>, request: ControlRequest) {
+    let command = request.command();
+    let no_baseline_file = || "No baseline_file configured".to_string();
+
+    let result = match command {
+        Command::SetMode(mode) => match set_mode(journal, ccs811, mode_override, scheduled, mode) {
+            Ok(Some(until)) => {
+                let minutes = until.saturating_duration_since(Instant::now()).as_secs().div_ceil(60);
+                journal.log(Priority::Info, "Idling before lowering the sampling rate", &[("MODE", &mode), ("MINUTES", &minutes)]);
+                return request.defer(format!("Idling for {} minutes before switching to {}", minutes, mode));
+            },
+            Ok(None) => Ok(format!("Sampling in mode {}", mode)),
+            Err(error) => Err(error)
+        },
+        Command::SaveBaseline => baseline_file.as_ref().ok_or_else(no_baseline_file).and_then(|file| {
+            let baseline = ccs811.get_baseline().map_err(|error| format!("Could not read baseline: {}", error))?;
+            baseline::save(file, baseline).map_err(|error| format!("Could not save baseline: {}", error))?;
+            Ok(format!("Saved baseline {}", baseline))
+        }),
+        Command::RestoreBaseline => baseline_file.as_ref().ok_or_else(no_baseline_file).and_then(|file| {
+            let baseline = baseline::load(file).map_err(|error| format!("Could not load baseline: {}", error))?;
+            ccs811.set_baseline(baseline).map_err(|error| format!("Could not restore baseline: {}", error))?;
+            Ok(format!("Restored baseline {}", baseline))
+        }),
+        Command::SetEnv { humidity, temperature } => ccs811.set_env_data(humidity, temperature)
+            .map(|()| format!("Environment set to {}% and {}°C", humidity, temperature))
+            .map_err(|error| format!("Could not set environment: {}", error))
+    };
+
+    match &result {
+        Ok(message) => journal.log(Priority::Info, "Handled control command", &[("COMMAND", &command), ("RESULT", message)]),
+        Err(error) => journal.log(Priority::Error, "Control command failed", &[("COMMAND", &command), ("ERROR", error)])
+    }
+    request.respond(result);
+}
+
+fn save_baseline(journal: &Journal, file: &Option<PathBuf>, baseline: u16) {
+    match file {
+        Some(file) => match baseline::save(file, baseline) {
+            Ok(()) => journal.log(Priority::Info, "Saved baseline", &[("BASELINE", &baseline)]),
+            Err(error) => journal.log(Priority::Error, "Could not save baseline", &[
+                ("BASELINE_FILE", &file.display()),
+                ("ERROR", &error)
+            ])
+        },
+        None => journal.log(Priority::Warning, "No baseline_file configured to save the baseline to", &[])
+    }
+}
+
+fn log_pipeline_error(journal: &Journal, error: &PipelineError) {
+    match error {
+        // Logged once when the sensor disconnects and when it's back
+        PipelineError::Source(Ccs811Error::Disconnected { .. }) => {},
+        PipelineError::Source(error) => journal.log(Priority::Error, "Could not read from the chip", &[
+            ("ERROR", error),
+            ("ERROR_KIND", &error.kind())
+        ]),
+        PipelineError::Sink(error) => journal.log(Priority::Warning, "Could not publish reading", &[
+            ("SINK_ERROR", error)
+        ])
+    }
+}
+
+fn sinks(config: &Config, journal: &Journal) -> Result<Vec<Box<dyn ReadingSink + Send>>, String> {
+    let mut sinks: Vec<Box<dyn ReadingSink + Send>> = Vec::new();
+
+    if let Some(mqtt) = &config.mqtt {
+        let mut mqtt_config = MqttConfig::new(&mqtt.host, &mqtt.topic);
+        if let Some(port) = mqtt.port {
+            mqtt_config.port = port;
+        }
+        sinks.push(Box::new(MqttPublisher::connect(mqtt_config)));
+    }
+
+    if let Some(statsd) = &config.statsd {
+        let sink = StatsdSink::new(statsd.server.as_str())
+            .map_err(|error| format!("Could not connect to StatsD server {}: {}", statsd.server, error))?;
+        let sink = statsd.tags.iter().fold(sink, |sink, (key, value)| sink.with_tag(key, value));
+        sinks.push(Box::new(sink));
+    }
+
+    if let Some(file) = &config.file {
+        let format: Format = file.format.parse()?;
+        let sink = FileSink::new(&file.path, format)
+            .map_err(|error| format!("Could not open {}: {}", file.path.display(), error))?
+            .with_keep(file.keep);
+        let sink = file.max_size.into_iter().fold(sink, FileSink::with_max_size);
+        let sink = file.max_age.into_iter().fold(sink, |sink, seconds| sink.with_max_age(Duration::from_secs(seconds)));
+        let sink = if file.gzip { sink.with_gzip() } else { sink };
+        sinks.push(Box::new(sink));
+    }
+
+    if sinks.is_empty() {
+        return Err("Configure at least one of [mqtt], [statsd] or [file]".to_string());
+    }
+    if config.log_readings {
+        sinks.push(Box::new(JournalSink::new(journal.clone())));
+    }
+
+    Ok(sinks)
+}
+
+#[cfg(feature = "mdns")]
+fn advertise(config: &Config, sensor: &SensorConfig, journal: &Journal) -> Option<Advertisement> {
+    let mdns = config.mdns.as_ref()?;
+    let instance = mdns.instance.clone().unwrap_or_else(|| format!("{}@{}", sensor.label, mdns::host_name()));
+    let properties: Vec<(&str, &str)> = std::iter::once(("sensor", sensor.label.as_str()))
+        .chain(mdns.txt.iter().map(|(key, value)| (key.as_str(), value.as_str())))
+        .collect();
+
+    match Advertisement::announce(&instance, mdns.port, &properties) {
+        Ok(advertisement) => {
+            journal.log(Priority::Info, "Announced via mDNS", &[("SERVICE", &advertisement.fullname())]);
+            Some(advertisement)
+        },
+        Err(error) => {
+            journal.log(Priority::Error, "Could not announce via mDNS", &[("ERROR", &error)]);
+            None
+        }
+    }
+}
+
+fn stage(filter: &FilterConfig) -> Box<dyn Stage + Send> {
+    match *filter {
+        FilterConfig::Validity => Box::new(Validity),
+        FilterConfig::Outlier { window, max_e_co2, max_t_voc } => Box::new(OutlierRejection::new(window, max_e_co2, max_t_voc)),
+        FilterConfig::MovingAverage { window } => Box::new(MovingAverage::new(window)),
+        FilterConfig::Ewma { alpha } => Box::new(Ewma::new(alpha))
+    }
+}
+
+fn pipeline<S: ReadingSource>(source: S, config: &Config, sensor: &SensorConfig, sinks: Vec<Box<dyn ReadingSink + Send>>) -> Pipeline<S> {
+    // The validation of the sensor comes first so the filters don't average invalid readings
+    let pipeline = sensor.validation.stage().into_iter()
+        .fold(Pipeline::new(source), Pipeline::boxed_stage);
+    let pipeline = config.filter.iter()
+        .fold(pipeline, |pipeline, filter| pipeline.boxed_stage(stage(filter)));
+
+    sinks.into_iter().fold(pipeline, |pipeline, sink| pipeline.boxed_sink(sink))
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: ccs811d <config file>");
+            process::exit(2);
+        }
+    };
+
+    let (config, registry, sensor, schedule) = load_config(&path).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(2);
+    });
+
+    let journal = Journal::open("ccs811d");
+    let shutdown = Shutdown::register().expect("Could not register signal handlers");
+
+    let sinks = sinks(&config, &journal).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(2);
+    });
+
+    let recovery_journal = journal.clone();
+    let label = sensor.label.clone();
+    let mut ccs811 = sensor.open().expect("Could not init the chip")
+        .with_auto_recovery(RECOVERY_ATTEMPTS)
+        .with_hot_swap(HOT_SWAP_FAILURES, HOT_SWAP_PROBE_INTERVAL)
+        .on_recovery(move |event| log_recovery(&recovery_journal, &label, event));
+
+    match registry.profile(&sensor) {
+        Ok(Some(profile)) => match profile.apply(&mut ccs811) {
+            Ok(()) => journal.log(Priority::Info, "Applied calibration profile", &[("PROFILE", &profile.name)]),
+            Err(error) => journal.log(Priority::Error, "Could not apply calibration profile", &[
+                ("PROFILE", &profile.name),
+                ("ERROR", &error),
+                ("ERROR_KIND", &error.kind())
+            ])
+        },
+        Ok(None) => {},
+        Err(error) => journal.log(Priority::Error, "Could not load calibration profile", &[
+            ("SENSOR", &sensor.label),
+            ("ERROR", &error)
+        ])
+    }
+    if let Some(file) = config.baseline_file.as_ref().filter(|file| file.exists()) {
+        match baseline::load(file).map(|baseline| (baseline, ccs811.set_baseline(baseline))) {
+            Ok((baseline, Ok(()))) => journal.log(Priority::Info, "Restored baseline", &[("BASELINE", &baseline)]),
+            Ok((_, Err(error))) => journal.log(Priority::Error, "Could not restore baseline", &[
+                ("ERROR", &error),
+                ("ERROR_KIND", &error.kind())
+            ]),
+            Err(error) => journal.log(Priority::Error, "Could not load baseline", &[
+                ("BASELINE_FILE", &file.display()),
+                ("ERROR", &error)
+            ])
+        }
+    }
+
+    let mut pipeline = pipeline(ccs811, &config, &sensor, sinks);
+    let control = serve_control(&config, &journal);
+    let probes = HealthProbes::new();
+    let probe_server = serve_health(&config, &probes, &journal);
+    let mut ready = false;
+    #[cfg(feature = "mdns")]
+    let advertisement = advertise(&config, &sensor, &journal);
+    let mut mode_override: Option<ModeOverride> = None;
+    let mut last_check = now();
+    while !shutdown.is_requested() {
+        let time = now();
+        let sampling = schedule.sampling_at(time);
+        let ccs811 = pipeline.source_mut();
+
+        if mode_override.as_ref().is_some_and(|mode_override| mode_override.scheduled != sampling.mode) {
+            mode_override = None;
+        }
+        let mode = mode_override.as_ref().map_or(sampling.mode, ModeOverride::mode);
+        // Logged by change_mode(), the next iteration tries again. A disconnected chip gets it once it's back.
+        if !ccs811.is_disconnected() {
+            let _ = change_mode(&journal, ccs811, mode);
+        }
+
+        for action in schedule.actions_between(last_check, time) {
+            match action {
+                Action::SaveBaseline => match ccs811.get_baseline() {
+                    Ok(baseline) => save_baseline(&journal, &config.baseline_file, baseline),
+                    Err(error) => journal.log(Priority::Error, "Could not read baseline", &[
+                        ("ERROR", &error),
+                        ("ERROR_KIND", &error.kind())
+                    ])
+                }
+            }
+        }
+        last_check = time;
+
+        if mode != MODE::Idle {
+            let disconnected = pipeline.source_mut().is_disconnected();
+            match pipeline.run_once() {
+                Ok(Some(data)) => ready = data.is_valid() && !data.is_warming_up(),
+                Ok(None) => {},
+                Err(error) => {
+                    ready &= !matches!(error, PipelineError::Source(Ccs811Error::Disconnected { .. }));
+                    log_pipeline_error(&journal, &error);
+                }
+            }
+            // The failure latched during the outage would restart a daemon which recovered already
+            if disconnected && !pipeline.source_mut().is_disconnected() {
+                pipeline.source_mut().clear_health();
+            }
+        }
+        let valid_for = sampling.interval.saturating_mul(3).saturating_add(HEALTH_MARGIN);
+        probes.report(pipeline.source_mut().health(), ready, valid_for);
+
+        wait(&shutdown, control.as_ref(), sampling.interval, |request| handle_command(
+            &journal, pipeline.source_mut(), &mut mode_override, sampling.mode, &config.baseline_file, request));
+    }
+    drop(control);
+    drop(probe_server);
+
+    if let Err(error) = pipeline.flush() {
+        log_pipeline_error(&journal, &error);
+    }
+    match pipeline.source_mut().shutdown() {
+        Ok(Some(baseline)) => save_baseline(&journal, &config.baseline_file, baseline),
+        Ok(None) => journal.log(Priority::Info, "Baseline not saved as the chip was still warming up", &[]),
+        Err(error) => journal.log(Priority::Error, "Could not shut down the chip", &[
+            ("ERROR", &error),
+            ("ERROR_KIND", &error.kind())
+        ])
+    }
+    // The MQTT publisher announces the shutdown on the availability topic and disconnects
+    drop(pipeline);
+    #[cfg(feature = "mdns")]
+    drop(advertisement);
+}
@@ -0,0 +1,174 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::chip::CCS811;
+use crate::error::{Ccs811Error, SourceError};
+use crate::firmware::FirmwareVersion;
+
+const EXTENSION: &str = "profile";
+
+/// Calibration of a sensor in one environment, e.g. the office or the greenhouse. A sensor moved
+/// between them switches profiles instead of learning the baseline again for days.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalibrationProfile {
+    pub name: String,
+    pub baseline: u16,
+    /// Correction of eCO2 determined with `calibrate_against()`
+    pub e_co2_offset: i16,
+    /// Application firmware the baseline was captured with, baselines don't carry over to others
+    pub firmware: FirmwareVersion,
+    pub captured: SystemTime
+}
+
+impl CalibrationProfile {
+
+    /// Captures the baseline, eCO2 correction and firmware of the running chip. The baseline is only
+    /// meaningful once the chip ran in the environment for a while, at least after the warm-up.
+    pub fn capture<I2C, WAKE, RESET, E>(name: &str, chip: &mut CCS811<I2C, WAKE, RESET>) -> Result<Self, Ccs811Error>
+        where I2C: Write<Error = E> + WriteRead<Error = E>,
+              E: SourceError,
+              WAKE: OutputPin,
+              <WAKE as OutputPin>::Error: SourceError,
+              RESET: OutputPin,
+              <RESET as OutputPin>::Error: SourceError {
+        Ok(CalibrationProfile {
+            name: name.to_string(),
+            baseline: chip.get_baseline()?,
+            e_co2_offset: chip.e_co2_offset(),
+            firmware: FirmwareVersion::from_bytes(chip.application_version()?),
+            captured: SystemTime::now()
+        })
+    }
+
+    /// Restores the baseline and eCO2 correction on the chip. Fails with
+    /// `Ccs811Error::ProfileFirmware` if the chip runs another firmware than the profile was captured
+    /// with.
+    pub fn apply<I2C, WAKE, RESET, E>(&self, chip: &mut CCS811<I2C, WAKE, RESET>) -> Result<(), Ccs811Error>
+        where I2C: Write<Error = E> + WriteRead<Error = E>,
+              E: SourceError,
+              WAKE: OutputPin,
+              <WAKE as OutputPin>::Error: SourceError,
+              RESET: OutputPin,
+              <RESET as OutputPin>::Error: SourceError {
+        let firmware = FirmwareVersion::from_bytes(chip.application_version()?);
+        if firmware != self.firmware {
+            return Err(Ccs811Error::ProfileFirmware { profile: self.firmware, chip: firmware });
+        }
+
+        chip.set_baseline(self.baseline)?;
+        chip.set_e_co2_offset(self.e_co2_offset);
+
+        Ok(())
+    }
+
+    fn to_file(&self) -> String {
+        let captured = self.captured.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let mut file = String::new();
+        let _ = writeln!(file, "baseline={}", self.baseline);
+        let _ = writeln!(file, "e_co2_offset={}", self.e_co2_offset);
+        let _ = writeln!(file, "firmware={}", self.firmware);
+        let _ = writeln!(file, "captured={}", captured);
+
+        file
+    }
+
+    fn from_file(name: &str, file: &str) -> Result<Self, String> {
+        let value = |key: &str| file.lines()
+            .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+            .ok_or_else(|| format!("Profile {} has no {}", name, key));
+        let invalid = |key: &str| format!("Profile {} has an invalid {}", name, key);
+
+        let captured = value("captured")?.parse().map_err(|_| invalid("captured"))?;
+        Ok(CalibrationProfile {
+            name: name.to_string(),
+            baseline: value("baseline")?.parse().map_err(|_| invalid("baseline"))?,
+            e_co2_offset: value("e_co2_offset")?.parse().map_err(|_| invalid("e_co2_offset"))?,
+            firmware: value("firmware")?.parse()?,
+            captured: UNIX_EPOCH.checked_add(Duration::from_secs(captured)).ok_or_else(|| invalid("captured"))?
+        })
+    }
+}
+
+/// Directory of calibration profiles, one `<name>.profile` file each with lines like `baseline=41234`
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::calibration::{CalibrationProfile, ProfileStore};
+///
+/// let store = ProfileStore::new("/var/lib/ccs811/profiles");
+///
+/// // After a week in the office
+/// store.save(&CalibrationProfile::capture("office", &mut ccs811).expect("Could not capture"))
+///     .expect("Could not save profile");
+///
+/// // Back in the greenhouse
+/// store.load("greenhouse").expect("Could not load profile")
+///     .apply(&mut ccs811).expect("Could not apply profile");
+/// ```
+pub struct ProfileStore {
+    directory: PathBuf
+}
+
+impl ProfileStore {
+
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        ProfileStore { directory: directory.as_ref().to_path_buf() }
+    }
+
+    /// Saves the profile, replacing one of the same name at once. Creates the directory if it doesn't
+    /// exist.
+    pub fn save(&self, profile: &CalibrationProfile) -> io::Result<()> {
+        let path = self.path(&profile.name)?;
+        let temporary = path.with_extension("tmp");
+
+        fs::create_dir_all(&self.directory)?;
+        fs::write(&temporary, profile.to_file())?;
+        fs::rename(&temporary, path)
+    }
+
+    /// Loads the profile, a file not containing a profile is reported as `io::ErrorKind::InvalidData`
+    pub fn load(&self, name: &str) -> io::Result<CalibrationProfile> {
+        let file = fs::read_to_string(self.path(name)?)?;
+
+        CalibrationProfile::from_file(name, &file).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn remove(&self, name: &str) -> io::Result<()> {
+        fs::remove_file(self.path(name)?)
+    }
+
+    /// Names of the saved profiles in alphabetical order, empty if the directory doesn't exist
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error)
+        };
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == EXTENSION) {
+                names.extend(path.file_stem().and_then(|name| name.to_str()).map(str::to_string));
+            }
+        }
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Path of the profile, names are used as file names so they can't contain paths
+    fn path(&self, name: &str) -> io::Result<PathBuf> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid profile name {:?}", name)));
+        }
+
+        Ok(self.directory.join(format!("{}.{}", name, EXTENSION)))
+    }
+}
@@ -0,0 +1,48 @@
+use embedded_hal::digital::v2::OutputPin;
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use crate::error::{pin_error, Ccs811Error};
+
+/// Output pin driven through the Linux GPIO character device (`/dev/gpiochipN`) which can be used as
+/// wake or reset pin. In contrast to rppal's memory mapped GPIO this doesn't need `/dev/gpiomem` and
+/// also works for pins of GPIO expanders exposed as gpiochip.
+pub struct CdevPin {
+    handle: LineHandle
+}
+
+impl CdevPin {
+
+    /// Requests the line of the gpiochip as output. The pin starts high which means the chip is
+    /// asleep respectively not held in reset.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let i2c = ccs811::sim::SimCcs811::new();
+    /// use ccs811::cdev::CdevPin;
+    ///
+    /// let wake_pin = CdevPin::new("/dev/gpiochip0", 17, "ccs811-wake")
+    ///     .expect("Could not attach to wake pin");
+    ///
+    /// let mut ccs811 = ccs811::chip::CCS811::new(i2c, Some(wake_pin));
+    /// ```
+    pub fn new(chip: &str, line: u32, consumer: &str) -> Result<CdevPin, Ccs811Error> {
+        let handle = Chip::new(chip)
+            .and_then(|mut chip| chip.get_line(line))
+            .and_then(|line| line.request(LineRequestFlags::OUTPUT, 1, consumer))
+            .map_err(pin_error("request the line of the gpiochip"))?;
+
+        Ok(CdevPin { handle })
+    }
+}
+
+impl OutputPin for CdevPin {
+    type Error = gpio_cdev::Error;
+
+    fn set_low(&mut self) -> Result<(), gpio_cdev::Error> {
+        self.handle.set_value(0)
+    }
+
+    fn set_high(&mut self) -> Result<(), gpio_cdev::Error> {
+        self.handle.set_value(1)
+    }
+}
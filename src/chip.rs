@@ -1,107 +1,934 @@
-use rppal::i2c::I2c;
-use rppal::gpio::{OutputPin};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::convert::{Infallible, TryFrom};
+use std::fmt::{self, Debug, Display};
+#[cfg(feature = "flash")]
+use std::io::{self, Read};
 use std::thread::sleep;
-use std::cmp::min;
+use std::time::{Duration, Instant};
 use super::constants::{*};
 use std::result::Result::Err;
+#[cfg(feature = "nb")]
+use crate::nonblocking::BeginState;
+use crate::firmware::{BaselineQuirks, FirmwareVersion};
+#[cfg(feature = "flash")]
+use crate::firmware::FirmwareImage;
+use crate::history::History;
+use crate::recovery::AutoRecovery;
+use crate::health::HealthTracker;
+use crate::profile::Profiler;
+use crate::retry::{RetryConfig, RetryPolicy};
+use crate::diagnostics::{Counters, TransactionKind, TransactionLog};
+use crate::codec::{baseline_from_bytes, baseline_to_bytes, bytes_to_float, checked_float_to_bytes, parse_alg_result, TemperatureEncoding};
+use crate::error::{Ccs811Error, SourceError, i2c_error, pin_error, is_clock_stretching, is_nack};
+#[cfg(feature = "flash")]
+use crate::error::flash_error;
 
-/// Bytes are calculated by taking the value without fraction and put it's 7 bits to the first byte.
-/// The fraction is multiplied by 512 as described in the CCS811 specs. To ensure
-/// The value can not be higher than 127 but humidity and temperature, this function is used for, will never
-/// exceed this.
-fn float_to_bytes(value: f32) -> [u8; 2] {
-    let base = value.floor();
-    // We only have 9 bits. 512 are already 10. So we ensure with min() that max 511 is used for fraction
-    let fraction = min(((value - base) * 512.0 - 1.0) as u16, 511);
-    // Take 7 bits of base and 1 bit of fraction
-    let hi = ((base as u8 & 0b1111111) << 1) | ((&fraction & 0b100000000) >> 8) as u8;
-    // Take 8 bits of fraction (the missing one is in the high byte
-    let lo = (&fraction & 0xFF) as u8;
-
-    [hi, lo]
+/// Fills the chunk as far as possible. Only the last chunk of a stream can be shorter than 8 bytes, even
+/// if the reader returns less bytes per read like network streams do. Returns the filled part of the
+/// chunk, which is empty at the end of the stream.
+#[cfg(feature = "flash")]
+fn read_chunk<'a, R: Read>(reader: &mut R, chunk: &'a mut [u8]) -> io::Result<&'a [u8]> {
+    let mut length = 0;
+    while let Some(rest) = chunk.get_mut(length..).filter(|rest| !rest.is_empty()) {
+        match reader.read(rest) {
+            Ok(0) => break,
+            Ok(read) if read <= rest.len() => length = length.saturating_add(read),
+            Ok(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "reader returned more bytes than requested")),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error)
+        }
+    }
+
+    Ok(chunk.get(..length).unwrap_or_default())
+}
+
+/// What `set_env_data()` does with values outside of the supported range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvValidation {
+    /// Write the nearest supported value instead, e.g. 100% for a humidity of 100.4%
+    Clamp,
+    /// Return an `InvalidHumidity` or `InvalidTemperature` error
+    Reject
+}
+
+impl EnvValidation {
+
+    /// Returns the value to write or None if it can't be written. NaN is never written.
+    fn apply(self, value: f32, min: f32, max: f32) -> Option<f32> {
+        if value.is_nan() {
+            return None;
+        }
+
+        match self {
+            EnvValidation::Clamp => Some(value.max(min).min(max)),
+            EnvValidation::Reject if value >= min && value <= max => Some(value),
+            EnvValidation::Reject => None
+        }
+    }
+}
+
+/// I2C address of the chip, selected with its ADDR pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Address {
+    /// ADDR pulled low, `CCS811_SLAVEADDR_0` (0x5A)
+    #[default]
+    Low,
+    /// ADDR pulled high, `CCS811_SLAVEADDR_1` (0x5B)
+    High
 }
 
+impl From<Address> for u8 {
+    fn from(address: Address) -> u8 {
+        match address {
+            Address::Low => CCS811_SLAVEADDR_0,
+            Address::High => CCS811_SLAVEADDR_1
+        }
+    }
+}
+
+/// Other addresses than 0x5A and 0x5B are returned as error
+impl TryFrom<u8> for Address {
+    type Error = u8;
+
+    fn try_from(address: u8) -> Result<Self, u8> {
+        match address {
+            CCS811_SLAVEADDR_0 => Ok(Address::Low),
+            CCS811_SLAVEADDR_1 => Ok(Address::High),
+            _ => Err(address)
+        }
+    }
+}
+
+/// Level of the wake pin which wakes the chip up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WakePolarity {
+    /// Low wakes the chip, like nWAKE when the pin is wired directly
+    #[default]
+    ActiveLow,
+    /// High wakes the chip, e.g. for boards with an inverting transistor in front of nWAKE
+    ActiveHigh
+}
+
+/// Conditions signaled by pulling the nINT pin low, see `CCS811::set_interrupts()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Interrupts {
+    /// Signal when a new sample is ready
+    pub data_ready: bool,
+    /// Only signal new samples if eCO2 crossed one of the THRESHOLDS. Requires `data_ready`.
+    pub threshold: bool
+}
+
+impl Interrupts {
+    fn from_meas_mode(meas_mode: u8) -> Self {
+        Interrupts {
+            data_ready: meas_mode & CCS811_MEAS_MODE_INT_DATARDY != 0,
+            threshold: meas_mode & CCS811_MEAS_MODE_INT_THRESH != 0
+        }
+    }
+
+    fn meas_mode(&self) -> u8 {
+        let mut meas_mode = 0;
+        if self.data_ready { meas_mode |= CCS811_MEAS_MODE_INT_DATARDY; }
+        if self.threshold { meas_mode |= CCS811_MEAS_MODE_INT_THRESH; }
+
+        meas_mode
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Ccs811Data {
     pub t_voc: u16,
     pub e_co2: u16,
-    pub raw: Vec<u8>
+    pub raw: RawData,
+    /// STATUS byte read with the sample, e.g. `status.is_data_ready()` tells whether it's a new sample
+    /// and `status.has_error()` whether the chip flagged an error
+    pub status: Status,
+    /// Mode the chip was sampling in when the data was read
+    pub mode: Ccs811Mode,
+    /// Time since sampling was started with `start()`, `None` in Idle mode. Tells whether the
+    /// reading falls into the warm-up of the chip.
+    pub since_start: Option<Duration>
 }
 
-pub struct CCS811 {
-    pub i2c: I2c,
-    pub wake: Option<OutputPin>
+impl Ccs811Data {
+
+    /// Whether the reading was taken during the conditioning of the chip in the first 20 minutes
+    /// after `start()`. Such readings are unreliable and should e.g. be greyed out in dashboards.
+    pub fn is_warming_up(&self) -> bool {
+        self.since_start.is_some_and(|since_start| since_start < CCS811_WARM_UP)
+    }
+
+    /// Whether the reading is at least 400ppm eCO2. The chip reports less before the first sample or
+    /// while it isn't warmed up yet.
+    pub fn is_valid(&self) -> bool {
+        self.e_co2 >= 400
+    }
+
+    /// The worse of the levels of eCO2 and tVOC
+    pub fn air_quality(&self) -> AirQuality {
+        AirQuality::from_e_co2(self.e_co2).max(AirQuality::from_t_voc(self.t_voc))
+    }
+}
+
+/// Prints like `eCO2 812 ppm, tVOC 63 ppb (Good)` or `eCO2 812 ppm, tVOC 63 ppb (Good, warming up)`
+impl Display for Ccs811Data {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "eCO2 {} ppm, tVOC {} ppb ({}", self.e_co2, self.t_voc, self.air_quality())?;
+        if self.is_warming_up() {
+            write!(f, ", warming up")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Rough classification of indoor air, ordered from best to worst. The eCO2 levels follow the usual
+/// ventilation recommendations, the tVOC levels the guide values of the German Federal Environment
+/// Agency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AirQuality {
+    /// Below 600 ppm eCO2 or 65 ppb tVOC
+    Excellent,
+    /// Below 800 ppm eCO2 or 220 ppb tVOC
+    Good,
+    /// Below 1000 ppm eCO2 or 660 ppb tVOC
+    Moderate,
+    /// Below 1500 ppm eCO2 or 2200 ppb tVOC, time to ventilate
+    Poor,
+    Unhealthy
 }
 
-impl CCS811 {
+impl AirQuality {
+
+    pub fn from_e_co2(e_co2: u16) -> Self {
+        match e_co2 {
+            0..=599 => AirQuality::Excellent,
+            600..=799 => AirQuality::Good,
+            800..=999 => AirQuality::Moderate,
+            1000..=1499 => AirQuality::Poor,
+            _ => AirQuality::Unhealthy
+        }
+    }
+
+    pub fn from_t_voc(t_voc: u16) -> Self {
+        match t_voc {
+            0..=64 => AirQuality::Excellent,
+            65..=219 => AirQuality::Good,
+            220..=659 => AirQuality::Moderate,
+            660..=2199 => AirQuality::Poor,
+            _ => AirQuality::Unhealthy
+        }
+    }
+}
+
+impl Display for AirQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            AirQuality::Excellent => "Excellent",
+            AirQuality::Good => "Good",
+            AirQuality::Moderate => "Moderate",
+            AirQuality::Poor => "Poor",
+            AirQuality::Unhealthy => "Unhealthy"
+        })
+    }
+}
+
+/// Content of the STATUS register. Prints like `0b10011000 FW_MODE APP_VALID DATA_READY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Status(pub u8);
+
+impl From<u8> for Status {
+    fn from(status: u8) -> Self {
+        Status(status)
+    }
+}
+
+impl From<Status> for u8 {
+    fn from(status: Status) -> u8 {
+        status.0
+    }
+}
+
+const STATUS_FLAGS: [(u8, &str); 6] = [
+    (0b10000000, "FW_MODE"),
+    (0b01000000, "APP_ERASE"),
+    (0b00100000, "APP_VERIFY"),
+    (0b00010000, "APP_VALID"),
+    (0b00001000, "DATA_READY"),
+    (0b00000001, "ERROR")
+];
+
+impl Status {
+
+    /// Whether the application runs, else the boot loader
+    pub fn is_app_mode(&self) -> bool {
+        self.0 & CCS811_STATUS_APP_MODE != 0
+    }
+
+    pub fn is_app_valid(&self) -> bool {
+        self.0 & CCS811_STATUS_APP_VALID != 0
+    }
+
+    pub fn is_data_ready(&self) -> bool {
+        self.0 & CCS811_STATUS_DATA_READY != 0
+    }
+
+    /// Whether ERROR_ID contains an error
+    pub fn has_error(&self) -> bool {
+        self.0 & CCS811_STATUS_ERROR != 0
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#010b} {}", self.0, crate::diagnostics::flags(self.0, &STATUS_FLAGS))
+    }
+}
+
+/// The bytes read from ALG_RESULT_DATA. Dereferences to the byte slice, the last two bytes contain
+/// RAW_DATA with the current through the sensor and the voltage across it. It's a fixed size array,
+/// so reading doesn't allocate.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawData(pub [u8; 8]);
+
+impl From<[u8; 8]> for RawData {
+    fn from(bytes: [u8; 8]) -> Self {
+        RawData(bytes)
+    }
+}
+
+impl RawData {
+
+    /// Current through the sensor in µA (0 to 63)
+    pub fn current_ua(&self) -> u8 {
+        self.0[6] >> 2
+    }
+
+    /// Reading of the 10 bit ADC, 1023 equals 1.65V
+    pub fn adc(&self) -> u16 {
+        u16::from(self.0[6] & 0b11) << 8 | u16::from(self.0[7])
+    }
+
+    /// Voltage across the sensor in volts
+    pub fn voltage(&self) -> f32 {
+        f32::from(self.adc()) * 1.65 / 1023.0
+    }
+
+    /// Resistance of the sensor in ohms calculated from voltage and current. Returns `None` if no
+    /// current was applied, e.g. before the first sample.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// let data = ccs811.read().expect("Could not read data");
+    /// if let Some(resistance) = data.raw.resistance_ohms() {
+    ///   println!("Sensor resistance: {:.0}Ω", resistance);
+    /// }
+    /// ```
+    pub fn resistance_ohms(&self) -> Option<f32> {
+        match self.current_ua() {
+            0 => None,
+            current => Some(self.voltage() / (f32::from(current) / 1_000_000.0))
+        }
+    }
+}
+
+impl std::ops::Deref for RawData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Debug for RawData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+/// Placeholder for the pin type of optional pins which are not connected. E.g. the WAK pin of the chip
+/// tied to ground or nRESET tied to VCC.
+pub struct NoPin;
+
+impl OutputPin for NoPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+pub struct CCS811<I2C, WAKE = NoPin, RESET = NoPin> {
+    pub i2c: I2C,
+    pub wake: Option<WAKE>,
+    pub reset: Option<RESET>,
+    address: u8,
+    wake_polarity: WakePolarity,
+    always_awake: bool,
+    // Whether the wake pin is held at the awake level by with_always_awake()
+    wake_held: bool,
+    // Whether the chip was just woken up and the next transaction is the first one since
+    woken: bool,
+    env_validation: EnvValidation,
+    // Encoding of the temperature chosen with set_temperature_encoding() instead of the datasheet one
+    temperature_encoding: Option<TemperatureEncoding>,
+    // Last written ENV_DATA and the encoding of its temperature
+    env_data: Option<([u8; 4], TemperatureEncoding)>,
+    retry: RetryConfig,
+    pub(crate) bus_clock_hz: Option<u32>,
+    pub(crate) transactions: TransactionLog,
+    pub(crate) counters: Counters,
+    mode: Ccs811Mode,
+    // Last time DATA_READY was seen or the mode was started
+    sample_reference: Option<Instant>,
+    pub(crate) started: Option<Instant>,
+    pub(crate) discarded_samples: u8,
+    pending_discards: u8,
+    // Version of the application read by begin() for the firmware specific baseline rules
+    app_version: Option<FirmwareVersion>,
+    // Baseline set with set_baseline() and whether it still has to be written
+    pub(crate) baseline: Option<u16>,
+    baseline_pending: bool,
+    history: History,
+    // Correction of eCO2 determined with calibrate_against()
+    e_co2_offset: i16,
+    pub(crate) auto_recovery: AutoRecovery,
+    pub(crate) health: HealthTracker,
+    pub(crate) profiler: Option<Profiler>,
+    #[cfg(feature = "nb")]
+    pub(crate) begin_state: BeginState
+}
+
+impl<I2C, WAKE> CCS811<I2C, WAKE, NoPin> {
+
+    /// Creates a new instance of the chip on any I2C bus implementing the blocking embedded-hal
+    /// traits, e.g. the I2C of a FT232H. The wake pin can be any embedded-hal output pin.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let i2c = ccs811::sim::SimCcs811::new();
+    /// use ccs811::chip::{CCS811, NoPin};
+    ///
+    /// let mut ccs811 = CCS811::new(i2c, None::<NoPin>);
+    /// ```
+    ///
+    /// To share the bus with other chips like a BME280, hand each driver a proxy of the `shared-bus`
+    /// crate. Every transaction locks the bus, so the drivers can live in different threads.
+    ///
+    /// ```no_run
+    /// use ccs811::chip::{CCS811, NoPin};
+    /// use rppal::i2c::I2c;
+    ///
+    /// let bus = shared_bus::BusManagerStd::new(I2c::with_bus(1).expect("Could not open I2C bus"));
+    /// let mut ccs811 = CCS811::new(bus.acquire_i2c(), None::<NoPin>);
+    /// ccs811.begin().expect("Could not init the chip");
+    /// ```
+    pub fn new(i2c: I2C, wake: Option<WAKE>) -> Self {
+        CCS811 {
+            i2c,
+            wake,
+            reset: None,
+            address: CCS811_SLAVEADDR_0,
+            wake_polarity: WakePolarity::ActiveLow,
+            always_awake: false,
+            wake_held: false,
+            woken: false,
+            env_validation: EnvValidation::Clamp,
+            temperature_encoding: None,
+            env_data: None,
+            retry: RetryConfig::default(),
+            bus_clock_hz: None,
+            transactions: TransactionLog::default(),
+            counters: Counters::default(),
+            mode: Ccs811Mode::Idle,
+            sample_reference: None,
+            started: None,
+            discarded_samples: 1,
+            pending_discards: 0,
+            app_version: None,
+            baseline: None,
+            baseline_pending: false,
+            history: History::new(0),
+            e_co2_offset: 0,
+            auto_recovery: AutoRecovery::default(),
+            health: HealthTracker::default(),
+            profiler: None,
+            #[cfg(feature = "nb")]
+            begin_state: BeginState::Idle
+        }
+    }
+}
+
+impl<I2C, WAKE, RESET> CCS811<I2C, WAKE, RESET> {
+
+    /// Use a pin connected to nRESET of the chip to be able to `hard_reset()` it
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use rppal::gpio::Gpio;
+    /// # use ccs811::rppal::RppalPin;
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+    /// let reset_pin = Gpio::new().expect("Can not init gpio")
+    ///                    .get(27).expect("Could not attach to reset pin");
+    ///
+    /// let mut ccs811 = ccs811::new(i2c, None).with_reset(RppalPin::from(reset_pin));
+    /// ```
+    pub fn with_reset<PIN>(self, reset: PIN) -> CCS811<I2C, WAKE, PIN> {
+        CCS811 {
+            i2c: self.i2c,
+            wake: self.wake,
+            reset: Some(reset),
+            address: self.address,
+            wake_polarity: self.wake_polarity,
+            always_awake: self.always_awake,
+            wake_held: self.wake_held,
+            woken: self.woken,
+            env_validation: self.env_validation,
+            temperature_encoding: self.temperature_encoding,
+            env_data: self.env_data,
+            retry: self.retry,
+            bus_clock_hz: self.bus_clock_hz,
+            transactions: self.transactions,
+            counters: self.counters,
+            mode: self.mode,
+            sample_reference: self.sample_reference,
+            started: self.started,
+            discarded_samples: self.discarded_samples,
+            pending_discards: self.pending_discards,
+            app_version: self.app_version,
+            baseline: self.baseline,
+            baseline_pending: self.baseline_pending,
+            history: self.history,
+            e_co2_offset: self.e_co2_offset,
+            auto_recovery: self.auto_recovery,
+            health: self.health,
+            profiler: self.profiler,
+            #[cfg(feature = "nb")]
+            begin_state: self.begin_state
+        }
+    }
+
+    /// Use another I2C address than the default `CCS811_SLAVEADDR_0` (0x5A), e.g. `Address::High` or
+    /// `CCS811_SLAVEADDR_1` (0x5B) for chips with the ADDR pin pulled high. The address is passed with
+    /// every transaction, so the bus can be shared with other devices and other chips.
+    pub fn with_address(self, address: impl Into<u8>) -> Self {
+        CCS811 { address: address.into(), ..self }
+    }
+
+    /// Level of the wake pin which wakes the chip up, `WakePolarity::ActiveLow` by default
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+    /// # let wake_pin = rppal::gpio::Gpio::new().unwrap().get(17).unwrap();
+    /// use ccs811::chip::WakePolarity;
+    ///
+    /// let mut ccs811 = ccs811::new(i2c, Some(wake_pin)).with_wake_polarity(WakePolarity::ActiveHigh);
+    /// ```
+    pub fn with_wake_polarity(self, wake_polarity: WakePolarity) -> Self {
+        CCS811 { wake_polarity, ..self }
+    }
+
+    /// Keeps the chip awake instead of waking it up before and sending it to sleep after every
+    /// transaction. The wake pin is set once and only released by `shutdown()`, so reads skip the GPIO
+    /// writes and the 50µs wait after waking. Use it if power consumption doesn't matter or nWAKE is tied
+    /// to ground, which is the same as passing `None` as wake pin.
+    pub fn with_always_awake(self) -> Self {
+        CCS811 { always_awake: true, ..self }
+    }
+
+    /// Keep the last `capacity` new samples read in the `history()`, e.g. for trends or sparklines
+    pub fn with_history(self, capacity: usize) -> Self {
+        CCS811 { history: History::new(capacity), ..self }
+    }
+
+    /// The last samples read, empty unless enabled with `with_history()`
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Restores an eCO2 correction determined earlier with `calibrate_against()`
+    pub fn with_e_co2_offset(self, e_co2_offset: i16) -> Self {
+        CCS811 { e_co2_offset, ..self }
+    }
+
+    /// Correction added to the eCO2 of every reading, see `calibrate_against()`
+    pub fn e_co2_offset(&self) -> i16 {
+        self.e_co2_offset
+    }
+
+    /// Replaces the eCO2 correction, e.g. when switching calibration profiles
+    pub fn set_e_co2_offset(&mut self, e_co2_offset: i16) {
+        self.e_co2_offset = e_co2_offset;
+    }
+
+    /// Number of samples discarded after every `start()`, as the first sample after a mode change is
+    /// frequently bogus. Defaults to 1, 0 disables discarding. `read()` blocks while waiting for the
+    /// sample after the discarded ones.
+    pub fn with_discarded_samples(self, discarded_samples: u8) -> Self {
+        CCS811 { discarded_samples, ..self }
+    }
+
+    /// Retry failed I2C transactions according to the config. Reads, writes and flashing can be
+    /// configured separately.
+    pub fn with_retry(self, retry: RetryConfig) -> Self {
+        CCS811 { retry, ..self }
+    }
+
+    /// Tell the driver the clock speed of the I2C bus, so `begin()` can fail early with
+    /// `Ccs811Error::BusClock` if it's too fast for the chip's clock stretching. `ccs811::new()` queries
+    /// it from the Raspberry Pi.
+    pub fn with_bus_clock(self, bus_clock_hz: u32) -> Self {
+        CCS811 { bus_clock_hz: Some(bus_clock_hz), ..self }
+    }
+
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// The mode the chip was started in, Idle after a reset
+    pub fn mode(&self) -> Ccs811Mode {
+        self.mode
+    }
+
+    /// Estimates when the next fresh sample will be ready, based on the last time new data was read
+    /// or the mode was started. Returns `None` in Idle mode.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::thread::sleep;
+    /// # use std::time::Instant;
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// if let Some(eta) = ccs811.next_sample_eta() {
+    ///   sleep(eta.saturating_duration_since(Instant::now()));
+    /// }
+    /// let data = ccs811.read().expect("Could not read data");
+    /// ```
+    pub fn next_sample_eta(&self) -> Option<Instant> {
+        let period = self.mode.period()?;
+        let reference = self.sample_reference?;
+        let periods = reference.elapsed().as_nanos().checked_div(period.as_nanos())
+            .map_or(0, |periods| u32::try_from(periods).unwrap_or(u32::MAX));
+
+        reference.checked_add(period.saturating_mul(periods.saturating_add(1)))
+    }
+
+    /// Time since sampling was started with `start()`, `None` in Idle mode
+    pub fn since_start(&self) -> Option<Duration> {
+        self.started.map(|started| started.elapsed())
+    }
+
+    pub(crate) fn set_sampling(&mut self, mode: Ccs811Mode) {
+        if mode != self.mode && self.baseline.is_some() && self.baseline_quirks().cleared_on_mode_change {
+            self.baseline_pending = true;
+        }
+        self.mode = mode;
+        self.sample_reference = mode.period().map(|_| Instant::now());
+        self.started = self.sample_reference;
+        self.pending_discards = if mode.period().is_some() { self.discarded_samples } else { 0 };
+    }
+
+    fn baseline_quirks(&self) -> BaselineQuirks {
+        self.app_version.map(|version| version.baseline_quirks()).unwrap_or_default()
+    }
+
+    /// Time until the warm-up after `start()` is over, `None` if it's over or the chip is idle
+    fn warm_up_remaining(&self) -> Option<Duration> {
+        self.since_start()
+            .and_then(|since_start| CCS811_WARM_UP.checked_sub(since_start))
+            .filter(|remaining| *remaining > Duration::from_secs(0))
+    }
+
+    /// Choose whether `set_env_data()` clamps values outside of the supported range (default) or
+    /// rejects them with an error
+    pub fn set_env_validation(&mut self, env_validation: EnvValidation) {
+        self.env_validation = env_validation;
+    }
+
+    /// Overrides how `set_env_data()` encodes the temperature, which otherwise uses the 25°C offset of
+    /// the datasheet for every application version. `None` returns to the offset.
+    pub fn set_temperature_encoding(&mut self, encoding: Option<TemperatureEncoding>) {
+        self.temperature_encoding = encoding;
+    }
+
+    /// Encoding of the temperature written by `set_env_data()`. The one of the datasheet with the 25°C
+    /// offset unless overridden by `set_temperature_encoding()`.
+    pub fn temperature_encoding(&self) -> TemperatureEncoding {
+        self.temperature_encoding.unwrap_or(TemperatureEncoding::Offset25)
+    }
+
+    /// Humidity and temperature last written by `set_env_data()`, decoded from the written bytes, so
+    /// after clamping and rounding to 1/512
+    pub fn env_data(&self) -> Option<(f32, f32)> {
+        self.env_data.map(|([humidity_high, humidity_low, temperature_high, temperature_low], encoding)| (
+            bytes_to_float([humidity_high, humidity_low]),
+            encoding.from_bytes([temperature_high, temperature_low])
+        ))
+    }
+}
+
+impl<I2C, WAKE, RESET, E> CCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError,
+          WAKE: OutputPin,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin,
+          <RESET as OutputPin>::Error: SourceError {
+
+    /// Reads `buffer.len()` bytes starting at the register
+    pub(crate) fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), E> {
+        let started = Instant::now();
+        let woken = std::mem::replace(&mut self.woken, false);
+        let (i2c, address) = (&mut self.i2c, self.address);
+        let (result, repeated) = self.retry.read.run_after_wake(woken, CCS811_WAIT_AFTER_WAKE_NACK_US, |error| is_nack(error), || {
+            i2c.write_read(address, &[register], buffer)
+        });
+        let (result, stretch_retries) = self.retry.clock_stretch.run_backoff(result, |error| is_clock_stretching(error), || {
+            i2c.write_read(address, &[register], buffer)
+        });
+        if repeated {
+            self.counters.wake_retry();
+        }
+        self.counters.clock_stretch_retries(stretch_retries);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(TransactionKind::Read, started.elapsed());
+        }
+        self.transactions.record(TransactionKind::Read, register, &result);
+
+        result
+    }
+
+    pub(crate) fn read_register_byte(&mut self, register: u8) -> Result<u8, E> {
+        let mut buffer = [0; 1];
+        self.read_register(register, &mut buffer)?;
+
+        Ok(buffer[0])
+    }
+
+    /// Writes up to 8 bytes of data to the register, no register takes more so longer data is cut off.
+    /// Commands like APP_START are written without data.
+    pub(crate) fn write_register(&mut self, register: u8, data: &[u8]) -> Result<(), E> {
+        let (policy, clock_stretch) = (self.retry.write, self.retry.clock_stretch);
+        self.write_register_with(policy, clock_stretch, true, register, data)
+    }
+
+    /// Same as `write_register()` but retries according to the flash policy. A NACK after waking isn't
+    /// repeated, as the chip may have taken the erase or the block already.
+    #[cfg(feature = "flash")]
+    fn write_flash_register(&mut self, register: u8, data: &[u8]) -> Result<(), E> {
+        let policy = self.retry.flash;
+        self.write_register_with(policy, RetryPolicy::NONE, false, register, data)
+    }
+
+    fn write_register_with(&mut self, policy: RetryPolicy, clock_stretch: RetryPolicy, repeat_after_wake: bool, register: u8, data: &[u8]) -> Result<(), E> {
+        let mut buffer = [0; 9];
+        buffer[0] = register;
+        buffer.iter_mut().skip(1).zip(data).for_each(|(byte, value)| *byte = *value);
+        let message = buffer.get(..=data.len()).unwrap_or(&buffer);
+
+        let started = Instant::now();
+        let woken = std::mem::replace(&mut self.woken, false);
+        let (i2c, address) = (&mut self.i2c, self.address);
+        let (result, repeated) = policy.run_after_wake(woken && repeat_after_wake, CCS811_WAIT_AFTER_WAKE_NACK_US, |error| is_nack(error), || {
+            i2c.write(address, message)
+        });
+        let (result, stretch_retries) = clock_stretch.run_backoff(result, |error| is_clock_stretching(error), || {
+            i2c.write(address, message)
+        });
+        if repeated {
+            self.counters.wake_retry();
+        }
+        self.counters.clock_stretch_retries(stretch_retries);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(TransactionKind::Write, started.elapsed());
+        }
+        self.transactions.record(TransactionKind::Write, register, &result);
+
+        result
+    }
+
+    /// Sends the reset sequence without waiting for the chip to come up again
+    pub(crate) fn send_reset(&mut self) -> Result<(), Ccs811Error> {
+        // A chip which didn't get the sequence keeps sampling, recovery and hot swap restore its mode
+        self.write_register(CCS811_SW_RESET, &[0x11,0xE5,0x72,0x8A])
+            .map_err(i2c_error("reset chip"))?;
+        self.set_sampling(Ccs811Mode::Idle);
+        self.counters.reset();
+
+        Ok(())
+    }
 
-    fn reset(&mut self) -> Result<(), String> {
-        self.i2c.block_write(CCS811_SW_RESET, &[0x11,0xE5,0x72,0x8A])
-            .map_err(|error| format!("Couldn't write to I2C: {}", error))?;
+    fn reset(&mut self) -> Result<(), Ccs811Error> {
+        self.send_reset()?;
 
         sleep(CCS811_WAIT_AFTER_RESET_US);
 
         Ok(())
     }
 
-    fn app_start(&mut self) -> Result<(), String> {
-        self.i2c.write(&[CCS811_APP_START])
-            .map_err(|error| format!("Could not set App start: {}", error))?;
+    /// Sends the app start command without waiting for the application to be started. The boot loader
+    /// ignores it without a valid application, which is reported as `NoValidApplication` instead.
+    pub(crate) fn send_app_start(&mut self) -> Result<(), Ccs811Error> {
+        let status = self.read_register_byte(CCS811_STATUS)
+            .map_err(i2c_error("read chip status"))?;
+        if status & CCS811_STATUS_APP_VALID == 0 {
+            return Err(Ccs811Error::NoValidApplication(status));
+        }
+
+        self.write_register(CCS811_APP_START, &[])
+            .map_err(i2c_error("set App start"))
+    }
+
+    fn app_start(&mut self) -> Result<(), Ccs811Error> {
+        self.send_app_start()?;
 
         sleep(CCS811_WAIT_AFTER_APPSTART_US);
 
         Ok(())
     }
 
-    fn erase_app(&mut self) -> Result<(), String> {
-        self.i2c.block_write(CCS811_APP_ERASE, &[0xE7, 0xA7, 0xE6, 0x09])
-            .map_err(|error| format!("Could not erase app: {}", error))?;
+    #[cfg(feature = "flash")]
+    fn erase_app(&mut self) -> Result<(), Ccs811Error> {
+        self.write_flash_register(CCS811_APP_ERASE, &[0xE7, 0xA7, 0xE6, 0x09])
+            .map_err(i2c_error("erase app"))?;
 
         sleep(CCS811_WAIT_AFTER_APPERASE_MS);
 
         Ok(())
     }
 
-    fn check_hw_id(&mut self) -> Result<(), String> {
-        let hw_id = self.i2c.smbus_read_byte(CCS811_HW_ID)
-            .map_err(|error| format!("Couldn't read HWID: {}", error))?;
+    pub(crate) fn check_hw_id(&mut self) -> Result<(), Ccs811Error> {
+        let hw_id = self.read_register_byte(CCS811_HW_ID)
+            .map_err(i2c_error("read HWID"))?;
 
         if hw_id != 0x81 {
-            return Err(format!("HWID of chip is not 0x81 but {:x?}", hw_id));
+            return Err(Ccs811Error::HardwareId(hw_id));
         }
 
         Ok(())
     }
 
-    fn check_status(&mut self, expected: u8) -> Result<(), String> {
-        let status = self.i2c.smbus_read_byte(CCS811_STATUS)
-            .map_err(|error| format!("Could not read chip status: {}", error))?;
+    pub(crate) fn check_bus_clock(&self) -> Result<(), Ccs811Error> {
+        match self.bus_clock_hz {
+            Some(clock_hz) if clock_hz > CCS811_MAX_BUS_CLOCK_HZ => {
+                Err(Ccs811Error::BusClock { clock_hz, max_hz: CCS811_MAX_BUS_CLOCK_HZ })
+            },
+            _ => Ok(())
+        }
+    }
+
+    pub(crate) fn check_status(&mut self, expected: u8) -> Result<(), Ccs811Error> {
+        let status = self.read_register_byte(CCS811_STATUS)
+            .map_err(i2c_error("read chip status"))?;
 
         if (status & expected) == 0 {
-            return Err(format!("Chip status is not {:#010b} but {:#010b}", expected, status));
+            return Err(Ccs811Error::Status { expected, actual: status });
         }
 
         Ok(())
     }
 
-    fn awake(&mut self) {
+    pub(crate) fn awake(&mut self) -> Result<(), Ccs811Error> {
+        if self.wake_held {
+            return Ok(());
+        }
+
         if let Some(pin) = &mut self.wake {
-            pin.set_low();
+            let started = Instant::now();
+            match self.wake_polarity {
+                WakePolarity::ActiveLow => pin.set_low().map_err(pin_error("set wake pin low"))?,
+                WakePolarity::ActiveHigh => pin.set_high().map_err(pin_error("set wake pin high"))?
+            }
             sleep(CCS811_WAIT_AFTER_WAKE_US);
+            self.woken = true;
+            if let Some(profiler) = &mut self.profiler {
+                profiler.record_wake(started.elapsed());
+            }
         }
+        self.wake_held = self.always_awake;
+
+        Ok(())
     }
 
-    fn sleep(&mut self) {
+    pub(crate) fn sleep(&mut self) -> Result<(), Ccs811Error> {
+        if self.always_awake {
+            return Ok(());
+        }
+
+        self.release_wake()
+    }
+
+    /// Sends the chip to sleep, even if it's kept awake by `with_always_awake()`
+    fn release_wake(&mut self) -> Result<(), Ccs811Error> {
+        self.wake_held = false;
         if let Some(pin) = &mut self.wake {
-            pin.set_high();
+            match self.wake_polarity {
+                WakePolarity::ActiveLow => pin.set_high().map_err(pin_error("set wake pin high"))?,
+                WakePolarity::ActiveHigh => pin.set_low().map_err(pin_error("set wake pin low"))?
+            }
         }
+
+        Ok(())
     }
 
-    /// Initialize CCS811 chip with i2c bus
-    /// Sequence: set i2c slave -> Wake to low -> reset chip -> check hardware id -> start chip -> check chip status -> Wake to high -> ready
+    /// Resets the chip by pulling nRESET low. In contrast to the software reset this works even if the
+    /// I2C interface of the chip is wedged. Like after a software reset the chip is in boot mode
+    /// afterwards and needs to be initialized with `begin()` again.
     ///
     /// # Examples
     ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// if let Err(error) = ccs811.read() {
+    ///   ccs811.hard_reset().expect("Could not reset the chip");
+    ///   ccs811.begin().expect("Could not init the chip");
+    /// }
     /// ```
+    pub fn hard_reset(&mut self) -> Result<(), Ccs811Error> {
+        let pin = self.reset.as_mut()
+            .ok_or(Ccs811Error::NoResetPin)?;
+
+        pin.set_low()
+            .map_err(pin_error("set reset pin low"))?;
+        sleep(CCS811_RESET_PULSE_US);
+        pin.set_high()
+            .map_err(pin_error("set reset pin high"))?;
+        self.set_sampling(Ccs811Mode::Idle);
+        self.counters.reset();
+
+        sleep(CCS811_WAIT_AFTER_RESET_US);
+
+        Ok(())
+    }
+
+    /// Ensures the chip isn't held in reset by the reset pin
+    pub(crate) fn release_reset(&mut self) -> Result<(), Ccs811Error> {
+        if let Some(pin) = &mut self.reset {
+            pin.set_high()
+                .map_err(pin_error("set reset pin high"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Initialize CCS811 chip with i2c bus
+    /// Sequence: release reset pin -> Wake to low -> reset chip -> check hardware id -> check valid application -> start chip -> check chip status -> Wake to high -> ready
+    /// Without a valid application on the chip it fails with `Ccs811Error::NoValidApplication`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
     /// let mut ccs811 = ccs811::new(i2c, None);
     ///
     /// match ccs811.begin() {
@@ -109,20 +936,19 @@ impl CCS811 {
     ///   Err(error) => panic!("Could not init the chip: {}", error)
     /// }
     /// ```
-    pub fn begin(&mut self) -> Result<(), String> {
-        self.i2c.set_slave_address(CCS811_SLAVEADDR_0)
-            .map_err(|error| format!("Could not set slave addr: {}", error))?;
+    pub fn begin(&mut self) -> Result<(), Ccs811Error> {
+        self.check_bus_clock()?;
+        self.release_reset()?;
+        self.awake()?;
 
-        self.awake();
-
-        self.reset()
-            .and(self.check_hw_id())
-            .and(self.app_start())
-            .and(self.check_status(CCS811_STATUS_APP_MODE | CCS811_STATUS_APP_VERIFY))?;
-
-        self.sleep();
+        self.reset()?;
+        self.check_hw_id()?;
+        self.app_start()?;
+        self.check_status(CCS811_STATUS_APP_MODE | CCS811_STATUS_APP_VERIFY)?;
+        self.read_app_version()?;
+        self.counters.init();
 
-        Ok(())
+        self.sleep()
     }
 
     /// Put CCS811 chip into target mode. Be aware that the first sampled data will be available after
@@ -131,10 +957,12 @@ impl CCS811 {
     /// Also be aware that the documentation of the chip mentions to change the chip mode to a lower
     /// sampling rate like Sec1 to Sec60, the mode should be set to Idle for at least 10 minutes before
     /// the setting the new mode.
+    /// The interrupt configuration is kept, see `set_interrupts()`.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
     /// let mut ccs811 = ccs811::new(i2c, None);
     ///
     /// match ccs811.begin() {
@@ -145,123 +973,428 @@ impl CCS811 {
     ///   Err(error) => panic!("Could not init the chip: {}", error)
     /// }
     /// ```
-    pub fn start(&mut self, mode: Ccs811Mode) -> Result<(), String> {
-        self.awake();
-        self.i2c.block_write(CCS811_MEAS_MODE, &[(mode as u8) << 4])
-            .map_err(|error| format!("Could not set mode: {}", error))?;
-        self.sleep();
+    pub fn start(&mut self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.update_mode(CCS811_MEAS_MODE_DRIVE_MODE, (mode as u8) << 4)?;
+        self.set_sampling(mode);
 
         Ok(())
     }
 
+    /// Initializes the chip with `begin()` and starts sampling with `start()` in one go
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+    /// let mut ccs811 = ccs811::new(i2c, None);
+    /// ccs811.quick_start(ccs811::MODE::Sec1).expect("Could not start the chip");
+    /// ```
+    pub fn quick_start(&mut self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.begin()?;
+        self.start(mode)
+    }
+
+    /// Same as `quick_start()` but also restores a baseline saved with `get_baseline()`, e.g. before
+    /// the last shutdown. Firmware 1.x and 2.0.x overwrite a baseline written during the warm-up, so on
+    /// those it is written with the first `read()` after the warm-up.
+    pub fn quick_start_with_baseline(&mut self, mode: Ccs811Mode, baseline: u16) -> Result<(), Ccs811Error> {
+        self.quick_start(mode)?;
+        self.restore_baseline(baseline)
+    }
+
+    /// Same as `start()` but additionally enables the data ready interrupt. The chip pulls nINT low
+    /// whenever a new sample is ready until it was read.
+    pub fn start_with_interrupt(&mut self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.update_mode(
+            CCS811_MEAS_MODE_DRIVE_MODE | CCS811_MEAS_MODE_INT_DATARDY,
+            ((mode as u8) << 4) | CCS811_MEAS_MODE_INT_DATARDY
+        )?;
+        self.set_sampling(mode);
+
+        Ok(())
+    }
+
+    /// Configure when the chip pulls nINT low without changing the mode
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// use ccs811::chip::Interrupts;
+    ///
+    /// ccs811.set_interrupts(Interrupts { data_ready: true, threshold: false })
+    ///     .expect("Could not enable interrupt");
+    /// ```
+    pub fn set_interrupts(&mut self, interrupts: Interrupts) -> Result<(), Ccs811Error> {
+        self.update_mode(CCS811_MEAS_MODE_INT_DATARDY | CCS811_MEAS_MODE_INT_THRESH, interrupts.meas_mode())
+    }
+
+    /// Currently configured interrupts
+    pub fn interrupts(&mut self) -> Result<Interrupts, Ccs811Error> {
+        self.awake()?;
+        let meas_mode = self.read_register_byte(CCS811_MEAS_MODE)
+            .map_err(i2c_error("read mode"))?;
+        self.sleep()?;
+
+        Ok(Interrupts::from_meas_mode(meas_mode))
+    }
+
+    /// Replaces the bits of MEAS_MODE selected by the mask and keeps the others
+    fn update_mode(&mut self, mask: u8, bits: u8) -> Result<(), Ccs811Error> {
+        self.awake()?;
+        let meas_mode = self.read_register_byte(CCS811_MEAS_MODE)
+            .map_err(i2c_error("read mode"))?;
+        self.write_register(CCS811_MEAS_MODE, &[(meas_mode & !mask) | (bits & mask)])
+            .map_err(i2c_error("set mode"))?;
+        self.sleep()
+    }
+
     /// Version should be something like 0x1X
-    pub fn hardware_version(&mut self) -> Result<u8, String> {
-        self.i2c.smbus_read_byte(CCS811_HW_VERSION)
-            .map_err(|error| format!("Could not read hardware version: {}", error))
+    pub fn hardware_version(&mut self) -> Result<u8, Ccs811Error> {
+        self.read_register_byte(CCS811_HW_VERSION)
+            .map_err(i2c_error("read hardware version"))
     }
 
     /// Something like 0x10 0x0
-    pub fn bootloader_version(&mut self) -> Result<[u8; 2], String> {
+    pub fn bootloader_version(&mut self) -> Result<[u8; 2], Ccs811Error> {
         let mut buffer = [0; 2];
-        self.i2c.block_read(CCS811_FW_BOOT_VERSION, &mut buffer)
-            .map_err(|error| format!("Could not read boot loader version: {}", error))?;
+        self.read_register(CCS811_FW_BOOT_VERSION, &mut buffer)
+            .map_err(i2c_error("read boot loader version"))?;
 
         Ok(buffer)
     }
 
     /// Something like 0x10 0x0 or higher. You can flash a newer firmware (2.0.0) using the flash method
     /// and a firmware binary. See examples for more details
-    pub fn application_version(&mut self) -> Result<[u8; 2], String> {
+    pub fn application_version(&mut self) -> Result<[u8; 2], Ccs811Error> {
         let mut buffer = [0; 2];
-        self.i2c.block_read(CCS811_FW_APP_VERSION, &mut buffer)
-            .map_err(|error| format!("Could not read application version: {}", error))?;
+        self.read_register(CCS811_FW_APP_VERSION, &mut buffer)
+            .map_err(i2c_error("read application version"))?;
 
         Ok(buffer)
     }
 
-    /// Get the currently used baseline
-    pub fn get_baseline(&mut self) -> Result<u16, String> {
-        self.i2c.smbus_read_word(CCS811_BASELINE)
-            .map_err(|error| format!("Could not read baseline: {}", error))
+    /// Remembers the version of the application for the firmware specific baseline rules
+    pub(crate) fn read_app_version(&mut self) -> Result<(), Ccs811Error> {
+        self.app_version = Some(FirmwareVersion::from_bytes(self.application_version()?));
+
+        Ok(())
+    }
+
+    /// Get the currently used baseline. Firmware 2.0.x doesn't report a meaningful baseline during the
+    /// warm-up after `start()`, in this case `Ccs811Error::BaselineNotReady` tells how long to wait.
+    pub fn get_baseline(&mut self) -> Result<u16, Ccs811Error> {
+        if let Some(remaining) = self.warm_up_remaining().filter(|_| self.baseline_quirks().read_after_warm_up) {
+            return Err(Ccs811Error::BaselineNotReady { remaining });
+        }
+
+        let mut buffer = [0; 2];
+        self.awake()?;
+        self.read_register(CCS811_BASELINE, &mut buffer)
+            .map_err(i2c_error("read baseline"))?;
+        self.sleep()?;
+
+        Ok(baseline_from_bytes(buffer))
+    }
+
+    /// Corrects eCO2 against a reading of a calibrated reference, e.g. a NDIR CO2 sensor placed next to
+    /// the chip. The difference between the reference and the current sample is stored as offset and
+    /// added to the eCO2 of all following readings. Returns the offset, so it can be saved and restored
+    /// with `with_e_co2_offset()`. Calibrate after the warm-up and in stable air, the baseline isn't
+    /// changed as its relation to ppm isn't documented.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// # struct Reference;
+    /// # impl Reference { fn co2_ppm(&self) -> u16 { 400 } }
+    /// # let reference = Reference;
+    /// let offset = ccs811.calibrate_against(reference.co2_ppm()).expect("Could not calibrate");
+    /// println!("eCO2 is corrected by {} ppm", offset);
+    /// ```
+    pub fn calibrate_against(&mut self, reference_ppm: u16) -> Result<i16, Ccs811Error> {
+        let data = self.read()?;
+        if !data.is_valid() {
+            return Err(Ccs811Error::InvalidSample(data.e_co2));
+        }
+
+        // The raw data still has the eCO2 without the previous offset
+        let e_co2 = u16::from_be_bytes([data.raw.0[0], data.raw.0[1]]);
+        let offset = i32::from(reference_ppm).saturating_sub(i32::from(e_co2));
+        self.e_co2_offset = offset.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16;
+
+        Ok(self.e_co2_offset)
     }
 
     /// The CCS811 chip has an automatic baseline correction based on a 24 hour interval but you still
     /// can set the baseline manually if you want.
-    pub fn set_baseline(&mut self, baseline: u16) -> Result<(), String> {
-        self.i2c.smbus_write_word(CCS811_BASELINE, baseline)
-            .map_err(|error| format!("Could not set baseline: {}", error))
+    /// Firmware 1.x and 2.0.x overwrite a baseline written during the warm-up after `start()`, in this
+    /// case `Ccs811Error::BaselineNotReady` tells how long to wait. Firmware 2.0.x also clears the
+    /// baseline when the mode changes, so the driver writes it again after the next warm-up.
+    pub fn set_baseline(&mut self, baseline: u16) -> Result<(), Ccs811Error> {
+        if let Some(remaining) = self.warm_up_remaining().filter(|_| self.baseline_quirks().write_after_warm_up) {
+            return Err(Ccs811Error::BaselineNotReady { remaining });
+        }
+
+        self.restore_baseline(baseline)
+    }
+
+    /// Keeps the baseline and writes it right away or, if the firmware would overwrite it in the
+    /// warm-up, with the first `read()` after it
+    pub(crate) fn restore_baseline(&mut self, baseline: u16) -> Result<(), Ccs811Error> {
+        self.baseline = Some(baseline);
+        self.baseline_pending = true;
+
+        self.write_pending_baseline()
+    }
+
+    /// Writes the baseline of `set_baseline()` unless the firmware would overwrite it in the warm-up
+    pub(crate) fn write_pending_baseline(&mut self) -> Result<(), Ccs811Error> {
+        let baseline = match self.baseline {
+            Some(baseline) if self.baseline_pending => baseline,
+            _ => return Ok(())
+        };
+        if self.baseline_quirks().write_after_warm_up && self.warm_up_remaining().is_some() {
+            return Ok(());
+        }
+
+        self.awake()?;
+        self.write_register(CCS811_BASELINE, &baseline_to_bytes(baseline))
+            .map_err(i2c_error("set baseline"))?;
+        self.sleep()?;
+        self.baseline_pending = false;
+
+        Ok(())
     }
 
     /// Set environmental data measured by external sensors to the chip to include those in
     /// calculations. E.g. humidity 48.5% and 23.3°C
+    /// Supported are humidities from `CCS811_HUMIDITY_MIN` to `CCS811_HUMIDITY_MAX` (0% to 100%) and
+    /// temperatures in the range of the `temperature_encoding()`, -25°C to just below 103°C with the
+    /// offset of the datasheet, 0°C to just below 128°C with `Absolute`. Values outside of these
+    /// ranges are clamped or rejected depending on `set_env_validation()`. NaN is always rejected.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
     /// match ccs811.set_env_data(48.5, 23.3) {
     ///   Ok(()) => println!("Updated environmental data on chip"),
     ///   Err(error) => panic!("Failed to set environmental data on chip because {}", error)
     /// }
     /// ```
-    pub fn set_env_data(&mut self, humidity: f32, temperature: f32) -> Result<(), String> {
-        let data = [
-            float_to_bytes(humidity),
-            float_to_bytes(temperature)
-        ].concat();
+    pub fn set_env_data(&mut self, humidity: f32, temperature: f32) -> Result<(), Ccs811Error> {
+        let humidity = self.env_validation.apply(humidity, CCS811_HUMIDITY_MIN, CCS811_HUMIDITY_MAX)
+            .and_then(checked_float_to_bytes)
+            .ok_or(Ccs811Error::InvalidHumidity(humidity))?;
+        let encoding = self.temperature_encoding();
+        let (temperature_min, temperature_max) = encoding.range();
+        let temperature = self.env_validation.apply(temperature, temperature_min, temperature_max)
+            .and_then(|temperature| encoding.to_bytes(temperature))
+            .ok_or(Ccs811Error::InvalidTemperature(temperature))?;
+
+        let [humidity_high, humidity_low] = humidity;
+        let [temperature_high, temperature_low] = temperature;
+        let data = [humidity_high, humidity_low, temperature_high, temperature_low];
 
-        self.i2c.block_write(CCS811_ENV_DATA, &data)
-            .map_err(|error| format!("Could npt write env data: {}", error))?;
+        self.awake()?;
+        self.write_register(CCS811_ENV_DATA, &data)
+            .map_err(i2c_error("write env data"))?;
+        self.sleep()?;
+        self.env_data = Some((data, encoding));
 
         Ok(())
     }
 
+    /// Writes the environment of the last `set_env_data()` again, e.g. after the chip was reset
+    pub(crate) fn restore_env_data(&mut self) -> Result<(), Ccs811Error> {
+        let data = match self.env_data {
+            Some((data, _)) => data,
+            None => return Ok(())
+        };
+
+        self.awake()?;
+        self.write_register(CCS811_ENV_DATA, &data)
+            .map_err(i2c_error("write env data"))?;
+        self.sleep()
+    }
+
     /// Read last sampled eCO2, tVOC and the corresponding status, error and raw data from the
-    /// chip register
+    /// chip register. If the sample is one of the first ones after `start()` which are discarded (see
+    /// `with_discarded_samples()`), it blocks until the next sample and returns that one instead. With
+    /// the default of one discarded sample the first `read()` after `start()` can take up to two
+    /// sample periods, e.g. 2 minutes in `Sec60` mode. `with_discarded_samples(0)` turns this off.
+    /// With `with_auto_recovery()` a chip which got lost is re-initialized before reading again, with
+    /// `with_hot_swap()` a chip which left the bus is reported as `Ccs811Error::Disconnected`.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
     /// match ccs811.read() {
     ///   Ok(data) => {
-    ///     println!("t_voc: {}, e_co2: {}, raw: {:x?}", data.t_voc, data.e_co2, data.raw);
+    ///     println!("{}, raw: {:x?}", data, data.raw);
     ///   },
     ///   Err(error) => println!("Could not read data: {}", error)
     /// };
     /// ```
-    pub fn read(&mut self) -> Result<Ccs811Data, String> {
+    pub fn read(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        let result = self.read_sample();
+        self.health.record(result.is_ok());
+
+        result
+    }
+
+    fn read_sample(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        self.check_reconnected()?;
+        let result = self.read_present_sample();
+
+        self.track_presence(result)
+    }
+
+    fn read_present_sample(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        let mut buffer = self.read_recovered_result()?;
+        while self.discard_sample(&buffer) {
+            buffer = self.read_next_result()?;
+        }
+
+        self.parse_result(buffer)
+    }
+
+    /// Reads ALG_RESULT_DATA after writing a pending baseline. With `with_auto_recovery()` a chip which
+    /// got lost is re-initialized before reading again.
+    pub(crate) fn read_recovered_result(&mut self) -> Result<[u8; 8], Ccs811Error> {
+        self.write_pending_baseline()?;
+
+        let result = self.read_result();
+        match self.check_lost(&result) {
+            Some(reason) => {
+                self.recover(reason)?;
+                self.read_result()
+            },
+            None => result
+        }
+    }
+
+    /// Whether the buffer holds a new sample which has to be discarded. Counts it as discarded.
+    pub(crate) fn discard_sample(&mut self, buffer: &[u8; 8]) -> bool {
+        let discard = self.pending_discards > 0
+            && buffer[5] == 0
+            && buffer[4] & CCS811_STATUS_DATA_READY != 0
+            && self.mode.period().is_some();
+
+        if discard {
+            self.pending_discards = self.pending_discards.saturating_sub(1);
+            self.sample_reference = Some(Instant::now());
+        }
+
+        discard
+    }
+
+    /// Sleeps until the next sample and reads it. Polls for DATA_READY for at most another period
+    /// in case the chip is late, then gives up and returns what the chip has.
+    fn read_next_result(&mut self) -> Result<[u8; 8], Ccs811Error> {
+        let (eta, period) = match (self.next_sample_eta(), self.mode.period()) {
+            (Some(eta), Some(period)) => (eta, period),
+            _ => return self.read_result()
+        };
+        sleep(eta.saturating_duration_since(Instant::now()));
+
+        let deadline = Instant::now().checked_add(period);
+        loop {
+            let buffer = self.read_result()?;
+            if buffer[4] & CCS811_STATUS_DATA_READY != 0 || buffer[5] != 0 || deadline.is_none_or(|deadline| Instant::now() >= deadline) {
+                return Ok(buffer);
+            }
+            sleep(CCS811_DATA_READY_POLL_MS);
+        }
+    }
+
+    /// Reads ALG_RESULT_DATA which also contains STATUS and ERROR_ID
+    pub(crate) fn read_result(&mut self) -> Result<[u8; 8], Ccs811Error> {
         let mut buffer = [0; 8];
-        self.awake();
+        self.awake()?;
+
+        self.read_register(CCS811_ALG_RESULT_DATA, &mut buffer)
+            .map_err(i2c_error("read chip data"))?;
 
-        self.i2c.block_read(CCS811_ALG_RESULT_DATA, &mut buffer)
-            .map_err(|error| format!("Could not read chip data: {}", error))?;
+        self.sleep()?;
 
-        self.sleep();
+        Ok(buffer)
+    }
 
+    pub(crate) fn parse_result(&mut self, buffer: [u8; 8]) -> Result<Ccs811Data, Ccs811Error> {
         if buffer[5] != 0 {
-            return Err(format!("Some error while reading data {:x?}", buffer[5]));
+            return Err(Ccs811Error::Chip(buffer[5]));
         }
 
-        let data = Ccs811Data {
-            e_co2: buffer[0] as u16 * 256 + buffer[1] as u16,
-            t_voc: buffer[2] as u16 * 256 + buffer[3] as u16,
-            raw: buffer.to_vec()
-        };
+        if buffer[4] & CCS811_STATUS_DATA_READY != 0 && self.mode.period().is_some() {
+            self.sample_reference = Some(Instant::now());
+        }
+
+        let mut data = parse_alg_result(buffer, self.mode, self.since_start())?;
+        // Readings below 400ppm aren't valid and have to stay recognizable as such
+        if data.is_valid() && self.e_co2_offset != 0 {
+            data.e_co2 = i32::from(data.e_co2).saturating_add(i32::from(self.e_co2_offset)).clamp(400, 8192) as u16;
+        }
 
-        if data.t_voc > 1187 || data.e_co2 > 8192 {
-            return Err(format!("The data is above max {}ppb, {}ppm", data.t_voc, data.e_co2));
+        if buffer[4] & CCS811_STATUS_DATA_READY != 0 {
+            self.history.record(data);
         }
 
         Ok(data)
     }
 
+    /// Leaves the chip in a defined state before the application exits: puts it into Idle mode and
+    /// raises the wake pin. Returns the current baseline, so it can be persisted and restored with
+    /// `set_baseline()` after the next start. It's `None` if the firmware doesn't report a meaningful
+    /// baseline yet, see `get_baseline()`.
+    pub fn shutdown(&mut self) -> Result<Option<u16>, Ccs811Error> {
+        self.awake()?;
+        let baseline = match self.get_baseline() {
+            Ok(baseline) => Some(baseline),
+            Err(Ccs811Error::BaselineNotReady { .. }) => None,
+            Err(error) => return Err(error)
+        };
+        self.start(Ccs811Mode::Idle)?;
+        self.release_wake()?;
+
+        Ok(baseline)
+    }
+
+    /// Sleeps until the next sample should be ready according to the mode and reads it. Replaces
+    /// loops sleeping for the period of the mode, which drift apart from the sampling of the chip.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// ccs811.start(ccs811::MODE::Sec60).expect("Could not start");
+    ///
+    /// loop {
+    ///   let data = ccs811.sleep_until_next_sample().expect("Could not read data");
+    ///   println!("t_voc: {}, e_co2: {}", data.t_voc, data.e_co2);
+    /// }
+    /// ```
+    pub fn sleep_until_next_sample(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        let eta = self.next_sample_eta()
+            .ok_or(Ccs811Error::NotSampling)?;
+        sleep(eta.saturating_duration_since(Instant::now()));
+
+        self.read()
+    }
+
     /// Flash another firmware to the CCS811 chip. The firmware can be found in the world wide web in
     /// form of an binary file which must be read and passed as byte array to this function.
     /// If flashing fails the chip still got a working boot loader which makes it possible to write
     /// another firmware to the chip and fix the issue.
+    /// After flashing, the version reported by the new application is returned.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
     /// use std::fs::File;
     /// use std::io::Read;
     ///
@@ -275,50 +1408,134 @@ impl CCS811 {
     ///
     /// println!("Firmware has size of {} bytes", read);
     ///
-    /// ccs811.flash(data)
+    /// let version = ccs811.flash(data)
     /// .expect("Failed to flash firmware");
     ///
-    /// println!("Flashed :)");
+    /// println!("Flashed version {} :)", version);
+    /// ```
+    #[cfg(feature = "flash")]
+    pub fn flash(&mut self, data: Vec<u8>) -> Result<FirmwareVersion, Ccs811Error> {
+        self.flash_from_reader(data.as_slice())
+    }
+
+    /// Verifies the firmware image against the expected SHA-256 hash before flashing it. In contrast
+    /// to `flash()` a corrupted download is detected before the application on the chip is erased.
+    /// Without a hash the image is verified against `FirmwareImage::KNOWN_HASHES` with `verify_known()`.
+    /// If the version of the image is known, it's compared with the version the chip reports after
+    /// flashing to ensure the upgrade actually took effect.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// use std::fs::File;
+    /// use ccs811::firmware::FirmwareImage;
+    ///
+    /// let file = File::open("./CCS811_FW_App_v2-0-1.bin").expect("No firmware found");
+    /// let image = FirmwareImage::from_reader(file).expect("Could not load firmware");
+    ///
+    /// ccs811.flash_image(&image, Some("<sha256 of the firmware>"))
+    ///     .expect("Failed to flash firmware");
     /// ```
-    pub fn flash(&mut self, data: Vec<u8>) -> Result<(), String> {
-        self.i2c.set_slave_address(CCS811_SLAVEADDR_0)
-            .map_err(|error| format!("Could not set slave addr: {}", error))?;
+    #[cfg(feature = "flash")]
+    pub fn flash_image(&mut self, image: &FirmwareImage, expected_sha256: Option<&str>) -> Result<FirmwareVersion, Ccs811Error> {
+        self.flash_image_with_progress(image, expected_sha256, |_| ())
+    }
 
+    /// Same as `flash_image()` but reports the number of bytes written so far to `progress`, e.g. to
+    /// update a progress bar with `image.len()` as total
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// let image = ccs811::firmware::FirmwareImage::open("./CCS811_FW_App_v2-0-1.bin")
+    ///     .expect("Could not load firmware");
+    ///
+    /// ccs811.flash_image_with_progress(&image, Some("<sha256 of the firmware>"), |written| {
+    ///   println!("{}/{} bytes", written, image.len());
+    /// }).expect("Failed to flash firmware");
+    /// ```
+    #[cfg(feature = "flash")]
+    pub fn flash_image_with_progress<F: FnMut(usize)>(&mut self, image: &FirmwareImage, expected_sha256: Option<&str>, progress: F) -> Result<FirmwareVersion, Ccs811Error> {
+        match expected_sha256 {
+            Some(expected_sha256) => image.verify(expected_sha256)?,
+            None => image.verify_known()?
+        }
+
+        let version = self.flash_from_reader_with_progress(image.data(), progress)?;
+
+        match image.version() {
+            Some(expected) if expected != version => {
+                Err(Ccs811Error::Flash(format!("Chip reports version {} after flashing but image is {}", version, expected)))
+            },
+            _ => Ok(version)
+        }
+    }
+
+    /// Same as `flash()` but streams the firmware from any reader in chunks of 8 bytes, so the image
+    /// doesn't need to be buffered and can come straight from a file or network stream.
+    /// Be aware that the application on the chip is erased before the firmware is read. If reading
+    /// fails afterwards the chip stays in boot loader mode and flashing has to be repeated.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// use std::fs::File;
+    ///
+    /// let file = File::open("./CCS811_FW_App_v2-0-1.bin")
+    ///     .expect("No firmware found");
+    ///
+    /// ccs811.flash_from_reader(file)
+    ///     .expect("Failed to flash firmware");
+    /// ```
+    #[cfg(feature = "flash")]
+    pub fn flash_from_reader<R: Read>(&mut self, reader: R) -> Result<FirmwareVersion, Ccs811Error> {
+        self.flash_from_reader_with_progress(reader, |_| ())
+    }
+
+    /// Same as `flash_from_reader()` but reports the number of bytes written so far to `progress`
+    /// after every chunk
+    #[cfg(feature = "flash")]
+    pub fn flash_from_reader_with_progress<R: Read, F: FnMut(usize)>(&mut self, mut reader: R, mut progress: F) -> Result<FirmwareVersion, Ccs811Error> {
         self.reset()?;
         self.check_status(CCS811_STATUS_APP_VALID)
-            .map_err(|error| format!("Not valid: {}", error))?; //status!=0x00 && status!=0x10
+            .map_err(flash_error("application not valid"))?; //status!=0x00 && status!=0x10
         self.erase_app()?;
         self.check_status(CCS811_STATUS_APP_ERASE)
-            .map_err(|error| format!("Not erased: {}", error))?; // status!=0x40
+            .map_err(flash_error("application not erased"))?; // status!=0x40
 
-        let mut i = 0;
+        let mut chunk = [0; 8];
+        let mut written = 0;
         loop {
-            println!("Flashing {} of {}\r", i, data.len());
-            if i >= data.len() {
+            progress(written);
+            let data = read_chunk(&mut reader, &mut chunk)
+                .map_err(flash_error("could not read firmware"))?;
+            if data.is_empty() {
                 break;
             }
-            let end = match i + 8 {
-                v if v > data.len() => data.len(),
-                v => v
-            };
-            self.i2c.block_write(CCS811_APP_DATA, &data[i..end])
-                .map_err(|error| format!("Could not write firmware: {}", error))?;
+            self.write_flash_register(CCS811_APP_DATA, data)
+                .map_err(i2c_error("write firmware"))?;
 
-            i += 8;
+            written = written.saturating_add(data.len());
         }
         sleep(CCS811_WAIT_AFTER_APPDATA_MS);
 
-        self.i2c.write(&[CCS811_APP_VERIFY])
-            .map_err(|error| format!("Could not reset verify bit: {}", error))?;
+        self.write_flash_register(CCS811_APP_VERIFY, &[])
+            .map_err(i2c_error("reset verify bit"))?;
         sleep(CCS811_WAIT_AFTER_APPVERIFY_MS);
 
         self.check_status(CCS811_STATUS_APP_ERASE | CCS811_STATUS_APP_VERIFY | CCS811_STATUS_APP_VALID)
-            .map_err(|error| format!("Not verified: {}", error))?;
+            .map_err(flash_error("application not verified"))?;
 
         self.reset()?;
 
         self.check_status(CCS811_STATUS_APP_VALID)
-            .map_err(|error| format!("Unexpected status after flashing: {}", error))
+            .map_err(flash_error("unexpected status after flashing"))?;
+
+        self.application_version()
+            .map(FirmwareVersion::from_bytes)
     }
 }
 
@@ -0,0 +1,107 @@
+use std::time::Duration;
+use crate::chip::{Ccs811Data, RawData, Status};
+use crate::constants::{Ccs811Mode, CCS811_TEMPERATURE_MAX, CCS811_TEMPERATURE_MIN};
+use crate::error::Ccs811Error;
+
+/// Offset added to temperatures by `TemperatureEncoding::Offset25`
+pub const TEMPERATURE_OFFSET: f32 = 25.0;
+
+/// How the temperature is written to ENV_DATA, see `CCS811::set_temperature_encoding()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureEncoding {
+    /// The temperature plus 25°C as the datasheet specifies, so -25°C to just below 103°C can be
+    /// written. Used for all application versions.
+    Offset25,
+    /// The temperature as it is, only 0°C to just below 128°C can be written. Only for firmware known
+    /// to ignore the offset of the datasheet.
+    Absolute
+}
+
+impl TemperatureEncoding {
+
+    /// Lowest and highest temperature which can be written
+    pub fn range(self) -> (f32, f32) {
+        match self {
+            TemperatureEncoding::Offset25 => (CCS811_TEMPERATURE_MIN - TEMPERATURE_OFFSET, CCS811_TEMPERATURE_MAX - TEMPERATURE_OFFSET),
+            TemperatureEncoding::Absolute => (CCS811_TEMPERATURE_MIN, CCS811_TEMPERATURE_MAX)
+        }
+    }
+
+    /// Encodes the temperature, `None` for NaN and temperatures outside of `range()`
+    pub fn to_bytes(self, temperature: f32) -> Option<[u8; 2]> {
+        match self {
+            TemperatureEncoding::Offset25 => checked_float_to_bytes(temperature + TEMPERATURE_OFFSET),
+            TemperatureEncoding::Absolute => checked_float_to_bytes(temperature)
+        }
+    }
+
+    /// Decodes a temperature encoded by `to_bytes()`
+    pub fn from_bytes(self, bytes: [u8; 2]) -> f32 {
+        match self {
+            TemperatureEncoding::Offset25 => bytes_to_float(bytes) - TEMPERATURE_OFFSET,
+            TemperatureEncoding::Absolute => bytes_to_float(bytes)
+        }
+    }
+}
+
+/// Encodes humidity or temperature for ENV_DATA. The upper 7 bits hold the integer part and the lower
+/// 9 bits the fraction in 1/512 steps, so the value is multiplied by 512 and rounded to the nearest
+/// step. Values outside of 0 to 127.998 saturate and NaN is encoded as 0, `set_env_data()` validates
+/// the values before.
+pub fn float_to_bytes(value: f32) -> [u8; 2] {
+    ((value * 512.0).round().clamp(0.0, f32::from(u16::MAX)) as u16).to_be_bytes()
+}
+
+/// Same as `float_to_bytes()` but returns `None` for NaN and values outside of 0 to 127.998 instead of
+/// saturating
+pub fn checked_float_to_bytes(value: f32) -> Option<[u8; 2]> {
+    let steps = (value * 512.0).round();
+    match (0.0..=f32::from(u16::MAX)).contains(&steps) {
+        true => Some((steps as u16).to_be_bytes()),
+        false => None
+    }
+}
+
+/// Decodes humidity or temperature encoded by `float_to_bytes()`
+pub fn bytes_to_float(bytes: [u8; 2]) -> f32 {
+    f32::from(u16::from_be_bytes(bytes)) / 512.0
+}
+
+/// Encodes the baseline for the BASELINE register
+pub fn baseline_to_bytes(baseline: u16) -> [u8; 2] {
+    // Byte order of a SMBus word to stay compatible with baselines saved by earlier versions
+    baseline.to_le_bytes()
+}
+
+/// Decodes the BASELINE register, inverse of `baseline_to_bytes()`
+pub fn baseline_from_bytes(bytes: [u8; 2]) -> u16 {
+    u16::from_le_bytes(bytes)
+}
+
+/// Builds the reading from ALG_RESULT_DATA, checking the range the chip specifies
+///
+/// # Examples
+///
+/// ```
+/// use ccs811::codec::parse_alg_result;
+///
+/// let data = parse_alg_result([0x01, 0x90, 0x00, 0x00, 0x98, 0x00, 0x50, 0x00], ccs811::MODE::Sec1, None)
+///     .expect("Reading out of range");
+/// assert_eq!(data.e_co2, 400);
+/// ```
+pub fn parse_alg_result(buffer: [u8; 8], mode: Ccs811Mode, since_start: Option<Duration>) -> Result<Ccs811Data, Ccs811Error> {
+    let data = Ccs811Data {
+        e_co2: u16::from_be_bytes([buffer[0], buffer[1]]),
+        t_voc: u16::from_be_bytes([buffer[2], buffer[3]]),
+        raw: RawData(buffer),
+        status: Status(buffer[4]),
+        mode,
+        since_start
+    };
+
+    if data.t_voc > 1187 || data.e_co2 > 8192 {
+        return Err(Ccs811Error::OutOfRange { t_voc: data.t_voc, e_co2: data.e_co2 });
+    }
+
+    Ok(data)
+}
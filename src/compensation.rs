@@ -0,0 +1,260 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use crate::error::{BoxError, SourceError};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Sender, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::chip::CCS811;
+
+/// Humidity in % and temperature in °C measured by an external sensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Environment {
+    pub humidity: f32,
+    pub temperature: f32
+}
+
+/// Anything providing the current environment, e.g. a BME280 or SHT31 driver. Closures returning
+/// an `Environment` can be used directly.
+///
+/// # Examples
+///
+/// A BME280 read through the `bme280` crate, sharing the bus with the CCS811 through `shared-bus`:
+///
+/// ```ignore
+/// use ccs811::compensation::{Compensation, CompensationConfig, Environment};
+/// use ccs811::error::BoxError;
+///
+/// let mut bme280 = bme280::i2c::BME280::new_primary(bus.acquire_i2c());
+/// bme280.init(&mut Delay).expect("Could not init BME280");
+///
+/// let compensation = Compensation::spawn(chip.clone(), move || -> Result<Environment, BoxError> {
+///     let measurements = bme280.measure(&mut Delay).map_err(|error| format!("{:?}", error))?;
+///     Ok(Environment { humidity: measurements.humidity, temperature: measurements.temperature })
+/// }, CompensationConfig::default());
+/// ```
+pub trait EnvironmentSource: Send {
+    fn read_environment(&mut self) -> Result<Environment, BoxError>;
+}
+
+impl<F> EnvironmentSource for F where F: FnMut() -> Result<Environment, BoxError> + Send {
+    fn read_environment(&mut self) -> Result<Environment, BoxError> {
+        self()
+    }
+}
+
+pub struct CompensationConfig {
+    /// How often the environment source is sampled
+    pub interval: Duration,
+    /// Minimum time between two writes of ENV_DATA to the chip
+    pub min_write_interval: Duration,
+    /// Change in % which causes a write of ENV_DATA
+    pub humidity_threshold: f32,
+    /// Change in °C which causes a write of ENV_DATA
+    pub temperature_threshold: f32,
+    /// ENV_DATA is written after this time even if the environment didn't change, e.g. to restore
+    /// the data after the chip was reset
+    pub refresh_interval: Duration
+}
+
+impl Default for CompensationConfig {
+    fn default() -> CompensationConfig {
+        CompensationConfig {
+            interval: Duration::from_secs(10),
+            min_write_interval: Duration::from_secs(60),
+            humidity_threshold: 1.0,
+            temperature_threshold: 0.5,
+            refresh_interval: Duration::from_secs(600)
+        }
+    }
+}
+
+impl CompensationConfig {
+
+    /// Whether the environment differs enough from the last written one to write it to the chip
+    fn should_write(&self, last: Option<(Environment, Instant)>, current: Environment, now: Instant) -> bool {
+        let (written, at) = match last {
+            Some(last) => last,
+            None => return true
+        };
+        let age = now.duration_since(at);
+
+        if age < self.min_write_interval {
+            return false;
+        }
+
+        age >= self.refresh_interval
+            || (current.humidity - written.humidity).abs() >= self.humidity_threshold
+            || (current.temperature - written.temperature).abs() >= self.temperature_threshold
+    }
+}
+
+/// Background thread which keeps the environmental data on the chip up to date. The chip is shared
+/// with the application through a mutex which is only locked while ENV_DATA gets written.
+/// The thread is stopped when the handle is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// # struct Measurement { humidity: f32, temperature: f32 }
+/// # struct Bme280;
+/// # impl Bme280 { fn measure(&mut self) -> Result<Measurement, ()> { Err(()) } }
+/// # let mut bme280 = Bme280;
+/// use std::sync::{Arc, Mutex};
+/// use ccs811::compensation::{Compensation, CompensationConfig, Environment};
+/// use ccs811::error::BoxError;
+///
+/// let chip = Arc::new(Mutex::new(ccs811));
+/// let compensation = Compensation::spawn(chip.clone(), move || -> Result<Environment, BoxError> {
+///     let measurement = bme280.measure().map_err(|error| format!("{:?}", error))?;
+///     Ok(Environment { humidity: measurement.humidity, temperature: measurement.temperature })
+/// }, CompensationConfig::default());
+///
+/// let data = chip.lock().unwrap().read();
+/// ```
+pub struct Compensation {
+    stop: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+    last_error: Arc<Mutex<Option<String>>>
+}
+
+impl Compensation {
+
+    pub fn spawn<I2C, WAKE, RESET, E, S>(chip: Arc<Mutex<CCS811<I2C, WAKE, RESET>>>, mut source: S, config: CompensationConfig) -> Compensation
+        where I2C: Write<Error = E> + WriteRead<Error = E> + Send + 'static,
+              E: SourceError,
+              WAKE: OutputPin + Send + 'static,
+              <WAKE as OutputPin>::Error: SourceError,
+              RESET: OutputPin + Send + 'static,
+              <RESET as OutputPin>::Error: SourceError,
+              S: EnvironmentSource + 'static {
+        let (stop, stopped) = channel();
+        let last_error = Arc::new(Mutex::new(None));
+        let thread_error = last_error.clone();
+
+        let thread = thread::spawn(move || {
+            let mut last_written = None;
+
+            loop {
+                let result = source.read_environment().and_then(|environment| {
+                    let now = Instant::now();
+                    if !config.should_write(last_written, environment, now) {
+                        return Ok(());
+                    }
+
+                    chip.lock()
+                        .map_err(|_| "Chip lock is poisoned")?
+                        .set_env_data(environment.humidity, environment.temperature)?;
+                    last_written = Some((environment, now));
+
+                    Ok(())
+                });
+
+                if let Ok(mut error) = thread_error.lock() {
+                    *error = result.err().map(|error| error.to_string());
+                }
+
+                match stopped.recv_timeout(config.interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break
+                }
+            }
+        });
+
+        Compensation { stop, thread: Some(thread), last_error }
+    }
+
+    /// The error of the last cycle if reading the source or writing the chip failed
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().ok().and_then(|error| error.clone())
+    }
+
+    /// Stops the thread and waits for it to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Compensation {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip::NoPin;
+    use crate::sim::SimCcs811;
+    use super::*;
+
+    const ENVIRONMENT: Environment = Environment { humidity: 50.0, temperature: 22.0 };
+
+    fn after(at: Instant, seconds: u64) -> Instant {
+        at.checked_add(Duration::from_secs(seconds)).unwrap()
+    }
+
+    #[test]
+    fn writes_the_first_environment() {
+        assert!(CompensationConfig::default().should_write(None, ENVIRONMENT, Instant::now()));
+    }
+
+    #[test]
+    fn rate_limits_writes() {
+        let config = CompensationConfig::default();
+        let written = Instant::now();
+        let changed = Environment { humidity: 60.0, temperature: 30.0 };
+
+        assert!(!config.should_write(Some((ENVIRONMENT, written)), changed, after(written, 59)));
+        assert!(config.should_write(Some((ENVIRONMENT, written)), changed, after(written, 60)));
+    }
+
+    #[test]
+    fn writes_changes_beyond_the_thresholds() {
+        let config = CompensationConfig::default();
+        let last = Some((ENVIRONMENT, Instant::now()));
+        let now = after(Instant::now(), 120);
+
+        let humidity = |humidity| Environment { humidity, ..ENVIRONMENT };
+        let temperature = |temperature| Environment { temperature, ..ENVIRONMENT };
+        assert!(!config.should_write(last, humidity(50.9), now));
+        assert!(config.should_write(last, humidity(49.0), now));
+        assert!(!config.should_write(last, temperature(22.4), now));
+        assert!(config.should_write(last, temperature(22.5), now));
+    }
+
+    #[test]
+    fn refreshes_an_unchanged_environment() {
+        let config = CompensationConfig::default();
+        let written = Instant::now();
+
+        assert!(!config.should_write(Some((ENVIRONMENT, written)), ENVIRONMENT, after(written, 599)));
+        assert!(config.should_write(Some((ENVIRONMENT, written)), ENVIRONMENT, after(written, 600)));
+    }
+
+    #[test]
+    fn keeps_the_error_of_the_source() {
+        let mut ccs811 = CCS811::new(SimCcs811::new(), None::<NoPin>);
+        ccs811.begin().unwrap();
+        let chip = Arc::new(Mutex::new(ccs811));
+        let config = CompensationConfig { interval: Duration::from_millis(1), ..CompensationConfig::default() };
+
+        let compensation = Compensation::spawn(chip.clone(), || Err("Sensor unplugged".into()), config);
+        let deadline = after(Instant::now(), 5);
+        while compensation.last_error().is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let error = compensation.last_error();
+        compensation.stop();
+
+        assert_eq!(error.as_deref(), Some("Sensor unplugged"));
+        assert_eq!(chip.lock().unwrap().i2c.env_data(), [0x64, 0x00, 0x64, 0x00]);
+    }
+}
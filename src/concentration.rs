@@ -0,0 +1,81 @@
+use crate::chip::Ccs811Data;
+
+/// Mean molar mass in g/mol of the VOC mixture of Mølhave, the usual reference of indoor air
+/// guidelines for tVOC
+pub const MOLAR_MASS_MOLHAVE: f32 = 110.0;
+/// Molar mass in g/mol of isobutylene, which photoionization detectors are calibrated with
+pub const MOLAR_MASS_ISOBUTYLENE: f32 = 56.1;
+/// Molar mass in g/mol of ethanol, which the CCS811 tVOC is roughly equivalent to
+pub const MOLAR_MASS_ETHANOL: f32 = 46.07;
+
+// J/(mol·K)
+const GAS_CONSTANT: f32 = 8.314_463;
+const ZERO_CELSIUS: f32 = 273.15;
+
+/// Converts tVOC between ppb as the chip reports it and µg/m³, the mass concentration thresholds of
+/// building standards like WELL or the guide values of the German Federal Environment Agency are
+/// given in. The mass depends on the mean molar mass assumed for the unknown mixture of VOCs and on
+/// the density of the air, so readings converted with different molar masses aren't comparable.
+///
+/// By default the Mølhave mixture at 25°C and 1013.25 hPa is assumed, where 1 ppb is 4.5 µg/m³.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::concentration::{MOLAR_MASS_ISOBUTYLENE, TvocConversion};
+///
+/// let conversion = TvocConversion::new(MOLAR_MASS_ISOBUTYLENE).with_ambient(21.5, 965.0);
+///
+/// let data = ccs811.read().expect("Could not read data");
+/// println!("tVOC {:.0} µg/m³", conversion.t_voc_mass(&data));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TvocConversion {
+    /// Mean molar mass of the VOCs in g/mol
+    pub molar_mass: f32,
+    /// Ambient temperature in °C
+    pub temperature: f32,
+    /// Ambient pressure in hPa
+    pub pressure: f32
+}
+
+impl Default for TvocConversion {
+    fn default() -> Self {
+        TvocConversion { molar_mass: MOLAR_MASS_MOLHAVE, temperature: 25.0, pressure: 1013.25 }
+    }
+}
+
+impl TvocConversion {
+
+    /// Converts with the molar mass in g/mol at 25°C and 1013.25 hPa
+    pub fn new(molar_mass: f32) -> Self {
+        TvocConversion { molar_mass, ..TvocConversion::default() }
+    }
+
+    /// Ambient temperature in °C and pressure in hPa, e.g. from a BME280 next to the chip
+    pub fn with_ambient(self, temperature: f32, pressure: f32) -> Self {
+        TvocConversion { temperature, pressure, ..self }
+    }
+
+    /// µg/m³ of one ppb, the molar mass divided by the molar volume of the air in l/mol
+    pub fn factor(&self) -> f32 {
+        let molar_volume = GAS_CONSTANT * (self.temperature + ZERO_CELSIUS) / (self.pressure / 10.0);
+
+        self.molar_mass / molar_volume
+    }
+
+    pub fn to_mass(&self, ppb: f32) -> f32 {
+        ppb * self.factor()
+    }
+
+    /// ppb of a mass concentration in µg/m³, e.g. to compare a guide value with the readings
+    pub fn to_ppb(&self, mass: f32) -> f32 {
+        mass / self.factor()
+    }
+
+    /// tVOC of the reading in µg/m³
+    pub fn t_voc_mass(&self, data: &Ccs811Data) -> f32 {
+        self.to_mass(f32::from(data.t_voc))
+    }
+}
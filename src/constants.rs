@@ -1,14 +1,98 @@
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::str::FromStr;
 use std::time::Duration;
 
+/// Drive mode of the chip. Parses from and displays as `idle`, `1s`, `10s`, `60s` and `250ms`, the
+/// names of the variants like `Sec60` are accepted as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Ccs811Mode {
     Idle = 0,
     Sec1 = 1,
     Sec10 = 2,
-    Sec60 = 3
+    Sec60 = 3,
+    /// Samples every 250ms but only updates the raw data, eCO2 and tVOC keep their last values
+    Ms250 = 4
 }
 
-pub const CCS811_SLAVEADDR_0: u16 = 0x5A;
-// pub const CCS811_SLAVEADDR_1: u16 = 0x5B;
+impl Ccs811Mode {
+
+    /// Interval between two samples or `None` if the chip doesn't sample in Idle mode
+    pub fn period(&self) -> Option<Duration> {
+        match self {
+            Ccs811Mode::Idle => None,
+            Ccs811Mode::Sec1 => Some(Duration::from_secs(1)),
+            Ccs811Mode::Sec10 => Some(Duration::from_secs(10)),
+            Ccs811Mode::Sec60 => Some(Duration::from_secs(60)),
+            Ccs811Mode::Ms250 => Some(Duration::from_millis(250))
+        }
+    }
+
+    /// Whether switching from this mode to `next` lowers the sampling rate, e.g. 1s to 60s. The chip
+    /// should be Idle for `CCS811_IDLE_BEFORE_SLOWER_MODE` before such a switch.
+    pub fn slows_down_to(&self, next: Ccs811Mode) -> bool {
+        match (self.period(), next.period()) {
+            (Some(period), Some(next_period)) => next_period > period,
+            _ => false
+        }
+    }
+}
+
+impl FromStr for Ccs811Mode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "idle" => Ok(Ccs811Mode::Idle),
+            "1s" | "sec1" => Ok(Ccs811Mode::Sec1),
+            "10s" | "sec10" => Ok(Ccs811Mode::Sec10),
+            "60s" | "sec60" => Ok(Ccs811Mode::Sec60),
+            "250ms" | "ms250" => Ok(Ccs811Mode::Ms250),
+            _ => Err(format!("Unknown mode {}, use idle, 1s, 10s, 60s or 250ms", value))
+        }
+    }
+}
+
+impl Display for Ccs811Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Ccs811Mode::Idle => "idle",
+            Ccs811Mode::Sec1 => "1s",
+            Ccs811Mode::Sec10 => "10s",
+            Ccs811Mode::Sec60 => "60s",
+            Ccs811Mode::Ms250 => "250ms"
+        })
+    }
+}
+
+/// The drive mode as in the MEAS_MODE register
+impl From<Ccs811Mode> for u8 {
+    fn from(mode: Ccs811Mode) -> u8 {
+        mode as u8
+    }
+}
+
+/// Converts the drive mode of the MEAS_MODE register, unknown modes are returned as error
+impl TryFrom<u8> for Ccs811Mode {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, u8> {
+        match value {
+            0 => Ok(Ccs811Mode::Idle),
+            1 => Ok(Ccs811Mode::Sec1),
+            2 => Ok(Ccs811Mode::Sec10),
+            3 => Ok(Ccs811Mode::Sec60),
+            4 => Ok(Ccs811Mode::Ms250),
+            _ => Err(value)
+        }
+    }
+}
+
+pub const CCS811_SLAVEADDR_0: u8 = 0x5A;
+pub const CCS811_SLAVEADDR_1: u8 = 0x5B; // ADDR pin high
+
+// The Raspberry Pi doesn't handle the clock stretching of the chip at higher speeds
+pub const CCS811_MAX_BUS_CLOCK_HZ: u32 = 100_000;
 
 // CCS811 registers/mailboxes, all 1 byte except when stated otherwise
 pub const CCS811_STATUS          : u8 = 0x00;
@@ -22,23 +106,45 @@ pub const CCS811_HW_ID           : u8 = 0x20;
 pub const CCS811_HW_VERSION      : u8 = 0x21;
 pub const CCS811_FW_BOOT_VERSION : u8 = 0x23; // 2 bytes
 pub const CCS811_FW_APP_VERSION  : u8 = 0x24; // 2 bytes
-// pub const CCS811_ERROR_ID        : u8 = 0xE0;
+pub const CCS811_ERROR_ID        : u8 = 0xE0;
+#[cfg(any(feature = "flash", feature = "testing", test))]
 pub const CCS811_APP_ERASE       : u8 = 0xF1; // 4 bytes
+#[cfg(any(feature = "flash", feature = "testing", test))]
 pub const CCS811_APP_DATA        : u8 = 0xF2; // 9 bytes
+#[cfg(any(feature = "flash", feature = "testing", test))]
 pub const CCS811_APP_VERIFY      : u8 = 0xF3; // 0 bytes
 pub const CCS811_APP_START       : u8 = 0xF4; // 0 bytes
 pub const CCS811_SW_RESET        : u8 = 0xFF; // 4 bytes
 
+pub const CCS811_MEAS_MODE_DRIVE_MODE  : u8 = 0b01110000; // Bits of the Ccs811Mode
+pub const CCS811_MEAS_MODE_INT_DATARDY : u8 = 0b00001000; // Else nINT not asserted on new data
+pub const CCS811_MEAS_MODE_INT_THRESH  : u8 = 0b00000100; // Else nINT asserted on every new data instead of crossed thresholds
+
 pub const CCS811_STATUS_APP_MODE   : u8 = 0b10000000; // Else boot mode
+#[cfg(any(feature = "flash", feature = "testing", test))]
 pub const CCS811_STATUS_APP_ERASE  : u8 = 0b01000000; // Else no erase completed
 pub const CCS811_STATUS_APP_VERIFY : u8 = 0b00100000; // Else no verify completed
 pub const CCS811_STATUS_APP_VALID  : u8 = 0b00010000; // Else no valid app firmware loaded
-// pub const CCS811_STATUS_DATA_READY : u8 = 0b00001000; // Else no new data samples ready
-// pub const CCS811_STATUS_ERROR      : u8 = 0b00000001; // Else no error
+pub const CCS811_STATUS_DATA_READY : u8 = 0b00001000; // Else no new data samples ready
+pub const CCS811_STATUS_ERROR      : u8 = 0b00000001; // Else no error
+
+// Range of the environmental data which can be written to ENV_DATA
+pub const CCS811_HUMIDITY_MIN: f32 = 0.0;
+pub const CCS811_HUMIDITY_MAX: f32 = 100.0;
+pub const CCS811_TEMPERATURE_MIN: f32 = 0.0;
+pub const CCS811_TEMPERATURE_MAX: f32 = 127.0 + 511.0 / 512.0; // 7 bits integer and 9 bits fraction
 
 pub const CCS811_WAIT_AFTER_RESET_US: Duration = Duration::from_micros(2000); // The CCS811 needs a wait after reset
 pub const CCS811_WAIT_AFTER_APPSTART_US: Duration = Duration::from_micros(1000); // The CCS811 needs a wait after app start
+pub const CCS811_RESET_PULSE_US: Duration = Duration::from_micros(20); // nRESET has to be low for at least 20µs
 pub const CCS811_WAIT_AFTER_WAKE_US: Duration = Duration::from_micros(50); // The CCS811 needs a wait after WAKE signal
+pub const CCS811_WAIT_AFTER_WAKE_NACK_US: Duration = Duration::from_micros(200); // Settle time when the first transaction after WAKE is NACKed
+#[cfg(feature = "flash")]
 pub const CCS811_WAIT_AFTER_APPERASE_MS: Duration = Duration::from_millis(500); // The CCS811 needs a wait after app erase (300ms from spec not enough)
+#[cfg(feature = "flash")]
 pub const CCS811_WAIT_AFTER_APPVERIFY_MS: Duration = Duration::from_millis(70); // The CCS811 needs a wait after app verify
-pub const CCS811_WAIT_AFTER_APPDATA_MS: Duration = Duration::from_millis(50); // The CCS811 needs a wait after writing app data
\ No newline at end of file
+#[cfg(feature = "flash")]
+pub const CCS811_WAIT_AFTER_APPDATA_MS: Duration = Duration::from_millis(50); // The CCS811 needs a wait after writing app data
+pub const CCS811_DATA_READY_POLL_MS: Duration = Duration::from_millis(10); // Polling interval when a sample is late
+pub const CCS811_WARM_UP: Duration = Duration::from_secs(20 * 60); // Readings are unreliable for 20 minutes after starting
+pub const CCS811_IDLE_BEFORE_SLOWER_MODE: Duration = Duration::from_secs(10 * 60); // Idle time before lowering the sampling rate
\ No newline at end of file
@@ -0,0 +1,238 @@
+use ::tiny_http::{Header, Method, Request, Response, Server};
+use std::fmt::{self, Display};
+use std::io::{self, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::MODE;
+
+// How long a client waits for the sampling loop to handle its command and the largest body read
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_BODY: u64 = 1024;
+
+/// What a client of the `ControlServer` asks for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// `PUT /mode` with the mode like `60s` as body
+    SetMode(MODE),
+    /// `POST /baseline/save`
+    SaveBaseline,
+    /// `POST /baseline/restore`
+    RestoreBaseline,
+    /// `PUT /env` with `humidity=48.5&temperature=23.3` as body
+    SetEnv { humidity: f32, temperature: f32 }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::SetMode(mode) => write!(f, "set mode {}", mode),
+            Command::SaveBaseline => write!(f, "save baseline"),
+            Command::RestoreBaseline => write!(f, "restore baseline"),
+            Command::SetEnv { humidity, temperature } => write!(f, "set environment {}% {}°C", humidity, temperature)
+        }
+    }
+}
+
+enum Reply {
+    Done(String),
+    Deferred(String),
+    Failed(String)
+}
+
+/// A command waiting to be handled. Dropping it without answer tells the client the command was
+/// dropped.
+pub struct ControlRequest {
+    command: Command,
+    reply: Sender<Reply>
+}
+
+impl ControlRequest {
+
+    pub fn command(&self) -> Command {
+        self.command
+    }
+
+    /// Answers with `200 OK` or `500 Internal Server Error` and the message
+    pub fn respond(self, result: Result<String, String>) {
+        // The client might have given up waiting
+        let _ = self.reply.send(match result {
+            Ok(message) => Reply::Done(message),
+            Err(message) => Reply::Failed(message)
+        });
+    }
+
+    /// Answers with `202 Accepted`, e.g. for a mode change waiting for the chip to idle long enough
+    pub fn defer(self, message: String) {
+        let _ = self.reply.send(Reply::Deferred(message));
+    }
+}
+
+/// HTTP endpoints to manage a sensor remotely: change the mode, save or restore the baseline and
+/// write the environment. Clients authenticate with `Authorization: Bearer <token>`.
+///
+/// The server runs in its own thread and hands the commands to the loop owning the chip, which gets
+/// them with `recv_timeout()`, e.g. while waiting for the next sample, and answers them. The client
+/// waits up to 30 seconds for the answer. The token is sent in plain text, so expose the server on
+/// trusted networks only or behind a TLS proxy.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::control::{Command, ControlServer};
+///
+/// let control = ControlServer::bind("0.0.0.0:8081", "secret").expect("Could not start control server");
+/// loop {
+///   if let Some(request) = control.recv_timeout(Duration::from_secs(10)) {
+///     let result = match request.command() {
+///       Command::SetEnv { humidity, temperature } => ccs811.set_env_data(humidity, temperature)
+///         .map(|()| "Environment set".to_string())
+///         .map_err(|error| error.to_string()),
+///       _ => Err("Not supported".to_string())
+///     };
+///     request.respond(result);
+///   }
+///   println!("{:?}", ccs811.read());
+/// }
+/// ```
+///
+/// ```sh
+/// curl -X PUT -H 'Authorization: Bearer secret' -d 'humidity=48.5&temperature=23.3' http://sensor:8081/env
+/// ```
+pub struct ControlServer {
+    server: Arc<Server>,
+    requests: Receiver<ControlRequest>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl ControlServer {
+
+    /// Listens on the address, e.g. `0.0.0.0:8081`, for clients with the token, which must not be empty
+    pub fn bind<A: ToSocketAddrs>(address: A, token: &str) -> io::Result<Self> {
+        if token.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "The token of the control server is empty"));
+        }
+        let server = Arc::new(Server::http(address).map_err(io::Error::other)?);
+        let (sender, requests) = mpsc::channel();
+
+        let listener = server.clone();
+        let token = token.to_string();
+        let thread = thread::Builder::new().name("ccs811-control".to_string()).spawn(move || {
+            // Ends once the server is unblocked on drop
+            for request in listener.incoming_requests() {
+                serve(request, &token, &sender);
+            }
+        })?;
+
+        Ok(ControlServer { server, requests, thread: Some(thread) })
+    }
+
+    /// Address the server listens on, e.g. to find the port when binding to port 0
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Waits up to `timeout` for the next command
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<ControlRequest> {
+        self.requests.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(mut request: Request, token: &str, requests: &Sender<ControlRequest>) {
+    let (status, message) = match authorize(&request, token).and_then(|()| command(&mut request)) {
+        Ok(command) => dispatch(command, requests),
+        Err(rejection) => rejection
+    };
+
+    let mut response = Response::from_string(format!("{}\n", message)).with_status_code(status);
+    if status == 401 {
+        if let Ok(header) = Header::from_bytes("WWW-Authenticate", "Bearer") {
+            response = response.with_header(header);
+        }
+    }
+    // The client might have hung up already
+    let _ = request.respond(response);
+}
+
+fn authorize(request: &Request, token: &str) -> Result<(), (u16, String)> {
+    let authorized = request.headers().iter()
+        .filter(|header| header.field.equiv("Authorization"))
+        .filter_map(|header| header.value.as_str().strip_prefix("Bearer "))
+        .any(|given| same(given.trim().as_bytes(), token.as_bytes()));
+
+    if authorized {
+        Ok(())
+    } else {
+        Err((401, "Missing or wrong bearer token".to_string()))
+    }
+}
+
+// Compares in constant time, so the time of a rejection doesn't tell how much of the token was right
+fn same(given: &[u8], token: &[u8]) -> bool {
+    given.len() == token.len() && given.iter().zip(token).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+fn command(request: &mut Request) -> Result<Command, (u16, String)> {
+    let mut body = String::new();
+    request.as_reader().take(MAX_BODY).read_to_string(&mut body)
+        .map_err(|error| (400, format!("Could not read the body: {}", error)))?;
+    let bad_request = |message: String| (400, message);
+
+    match (request.method(), request.url().trim_end_matches('/')) {
+        (Method::Put, "/mode") => body.trim().parse().map(Command::SetMode).map_err(bad_request),
+        (Method::Post, "/baseline/save") => Ok(Command::SaveBaseline),
+        (Method::Post, "/baseline/restore") => Ok(Command::RestoreBaseline),
+        (Method::Put, "/env") => parse_env(&body).map_err(bad_request),
+        (_, "/mode") | (_, "/env") => Err((405, "Use PUT".to_string())),
+        (_, "/baseline/save") | (_, "/baseline/restore") => Err((405, "Use POST".to_string())),
+        (_, path) => Err((404, format!("Unknown endpoint {}, use /mode, /baseline/save, /baseline/restore or /env", path)))
+    }
+}
+
+/// `humidity=48.5&temperature=23.3` in any order
+fn parse_env(body: &str) -> Result<Command, String> {
+    let (mut humidity, mut temperature) = (None, None);
+
+    for pair in body.trim().split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').ok_or_else(|| format!("{} is no key=value pair", pair))?;
+        let value: f32 = value.parse().map_err(|_| format!("{} of {} is no number", value, key))?;
+        match key {
+            "humidity" => humidity = Some(value),
+            "temperature" => temperature = Some(value),
+            _ => return Err(format!("Unknown key {}, use humidity and temperature", key))
+        }
+    }
+
+    match (humidity, temperature) {
+        (Some(humidity), Some(temperature)) => Ok(Command::SetEnv { humidity, temperature }),
+        _ => Err("Give both humidity and temperature, e.g. humidity=48.5&temperature=23.3".to_string())
+    }
+}
+
+fn dispatch(command: Command, requests: &Sender<ControlRequest>) -> (u16, String) {
+    let (reply, answer) = mpsc::channel();
+    if requests.send(ControlRequest { command, reply }).is_err() {
+        return (503, "The sensor is shutting down".to_string());
+    }
+
+    match answer.recv_timeout(REPLY_TIMEOUT) {
+        Ok(Reply::Done(message)) => (200, message),
+        Ok(Reply::Deferred(message)) => (202, message),
+        Ok(Reply::Failed(message)) => (500, message),
+        Err(RecvTimeoutError::Timeout) => (504, "The sensor didn't handle the command in time".to_string()),
+        Err(RecvTimeoutError::Disconnected) => (503, "The sensor dropped the command".to_string())
+    }
+}
@@ -0,0 +1,284 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+use crate::chip::{CCS811, Status};
+use crate::constants::*;
+use crate::error::SourceError;
+use crate::firmware::FirmwareVersion;
+
+/// Number of transactions kept for `diagnose()`
+pub const TRANSACTION_LOG_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionKind {
+    Read,
+    Write
+}
+
+/// A past transaction on the I2C bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub kind: TransactionKind,
+    pub register: u8,
+    /// The error of the bus if the transaction failed
+    pub error: Option<String>
+}
+
+/// Keeps the last `TRANSACTION_LOG_SIZE` transactions of a chip
+#[derive(Default)]
+pub(crate) struct TransactionLog(VecDeque<Transaction>);
+
+impl TransactionLog {
+
+    pub(crate) fn record<E: Display>(&mut self, kind: TransactionKind, register: u8, result: &Result<(), E>) {
+        if self.0.len() == TRANSACTION_LOG_SIZE {
+            self.0.pop_front();
+        }
+
+        self.0.push_back(Transaction {
+            kind,
+            register,
+            error: result.as_ref().err().map(|error| error.to_string())
+        });
+    }
+}
+
+/// Counters of the driver, see `metrics()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metrics {
+    /// Time since the driver last reset the chip, which restarts it. `None` before the first reset.
+    pub uptime: Option<Duration>,
+    /// Software and hard resets since the driver was created
+    pub resets: u32,
+    /// Successful initializations with `begin()` since the driver was created
+    pub inits: u32,
+    /// Transactions the chip NACKed right after waking up, which were repeated after a settle delay
+    pub wake_retries: u32,
+    /// Transactions retried because the chip stretched the clock too long
+    pub clock_stretch_retries: u32
+}
+
+#[derive(Default)]
+pub(crate) struct Counters {
+    last_reset: Option<Instant>,
+    resets: u32,
+    inits: u32,
+    wake_retries: u32,
+    clock_stretch_retries: u32
+}
+
+impl Counters {
+
+    pub(crate) fn reset(&mut self) {
+        self.last_reset = Some(Instant::now());
+        self.resets = self.resets.saturating_add(1);
+    }
+
+    pub(crate) fn init(&mut self) {
+        self.inits = self.inits.saturating_add(1);
+    }
+
+    pub(crate) fn wake_retry(&mut self) {
+        self.wake_retries = self.wake_retries.saturating_add(1);
+    }
+
+    pub(crate) fn clock_stretch_retries(&mut self, retries: u8) {
+        self.clock_stretch_retries = self.clock_stretch_retries.saturating_add(u32::from(retries));
+    }
+}
+
+/// Whether a chip answers on an address and which HWID it reports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressProbe {
+    pub address: u8,
+    pub hardware_id: Result<u8, String>
+}
+
+/// Report of `diagnose()` with everything worth knowing when the chip doesn't work. Print it with
+/// `{}` to get a human readable report.
+#[derive(Debug, Clone)]
+pub struct Diagnosis {
+    /// Address the driver talks to
+    pub address: u8,
+    /// Clock of the I2C bus if known, see `with_bus_clock()`
+    pub bus_clock_hz: Option<u32>,
+    /// Result of waking up the chip with the wake pin
+    pub wake: Result<(), String>,
+    /// Result of reading the HWID on both possible addresses
+    pub probes: Vec<AddressProbe>,
+    pub hardware_version: Result<u8, String>,
+    pub bootloader_version: Result<FirmwareVersion, String>,
+    pub application_version: Result<FirmwareVersion, String>,
+    pub status: Result<u8, String>,
+    /// Content of the ERROR_ID register. Reading it clears the error bit in the status.
+    pub error_id: Result<u8, String>,
+    pub metrics: Metrics,
+    /// The transactions before the diagnosis, oldest first
+    pub transactions: Vec<Transaction>
+}
+
+const ERROR_FLAGS: [(u8, &str); 6] = [
+    (0b00000001, "WRITE_REG_INVALID"),
+    (0b00000010, "READ_REG_INVALID"),
+    (0b00000100, "MEASMODE_INVALID"),
+    (0b00001000, "MAX_RESISTANCE"),
+    (0b00010000, "HEATER_FAULT"),
+    (0b00100000, "HEATER_SUPPLY")
+];
+
+pub(crate) fn flags(value: u8, names: &[(u8, &str)]) -> String {
+    let set: Vec<&str> = names.iter()
+        .filter(|(bit, _)| value & bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+
+    if set.is_empty() { "-".to_string() } else { set.join(" ") }
+}
+
+fn write_result<T: Display>(f: &mut fmt::Formatter, label: &str, result: &Result<T, String>) -> fmt::Result {
+    match result {
+        Ok(value) => writeln!(f, "{:<20}{}", label, value),
+        Err(error) => writeln!(f, "{:<20}failed: {}", label, error)
+    }
+}
+
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "CCS811 diagnosis")?;
+        writeln!(f, "{:<20}{:#04x}", "Address", self.address)?;
+        match self.bus_clock_hz {
+            Some(clock_hz) if clock_hz > CCS811_MAX_BUS_CLOCK_HZ => {
+                writeln!(f, "{:<20}{}Hz (too fast, max {}Hz)", "Bus clock", clock_hz, CCS811_MAX_BUS_CLOCK_HZ)?
+            },
+            Some(clock_hz) => writeln!(f, "{:<20}{}Hz", "Bus clock", clock_hz)?,
+            None => writeln!(f, "{:<20}unknown", "Bus clock")?
+        }
+        match &self.wake {
+            Ok(()) => writeln!(f, "{:<20}ok", "Wake")?,
+            Err(error) => writeln!(f, "{:<20}failed: {}", "Wake", error)?
+        }
+        for probe in &self.probes {
+            let label = format!("HWID at {:#04x}", probe.address);
+            match probe.hardware_id {
+                Ok(0x81) => writeln!(f, "{:<20}0x81 (CCS811)", label)?,
+                Ok(hw_id) => writeln!(f, "{:<20}{:#04x} (not a CCS811)", label, hw_id)?,
+                Err(ref error) => writeln!(f, "{:<20}no answer: {}", label, error)?
+            }
+        }
+        write_result(f, "Hardware version", &self.hardware_version.clone().map(|version| format!("{:#04x}", version)))?;
+        write_result(f, "Boot loader", &self.bootloader_version)?;
+        write_result(f, "Application", &self.application_version)?;
+        write_result(f, "Status", &self.status.clone()
+            .map(Status))?;
+        write_result(f, "Error ID", &self.error_id.clone()
+            .map(|error_id| format!("{:#04x} {}", error_id, flags(error_id, &ERROR_FLAGS))))?;
+
+        match self.metrics.uptime {
+            Some(uptime) => writeln!(f, "{:<20}{}s", "Uptime", uptime.as_secs())?,
+            None => writeln!(f, "{:<20}unknown", "Uptime")?
+        }
+        writeln!(f, "{:<20}{}", "Resets", self.metrics.resets)?;
+        writeln!(f, "{:<20}{}", "Inits", self.metrics.inits)?;
+        writeln!(f, "{:<20}{}", "Wake retries", self.metrics.wake_retries)?;
+        writeln!(f, "{:<20}{}", "Stretch retries", self.metrics.clock_stretch_retries)?;
+
+        writeln!(f, "Last transactions")?;
+        if self.transactions.is_empty() {
+            writeln!(f, "  -")?;
+        }
+        for transaction in &self.transactions {
+            let kind = match transaction.kind {
+                TransactionKind::Read => "read ",
+                TransactionKind::Write => "write"
+            };
+            match &transaction.error {
+                Some(error) => writeln!(f, "  {} {:#04x} failed: {}", kind, transaction.register, error)?,
+                None => writeln!(f, "  {} {:#04x} ok", kind, transaction.register)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<I2C, WAKE, RESET> CCS811<I2C, WAKE, RESET> {
+
+    /// Uptime of the chip and how often it was reset and initialized as observed by the driver.
+    /// Frequent resets in a long running deployment hint at flaky power or wiring.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// let metrics = ccs811.metrics();
+    /// println!("{} resets, up for {:?}", metrics.resets, metrics.uptime);
+    /// ```
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            uptime: self.counters.last_reset.map(|last_reset| last_reset.elapsed()),
+            resets: self.counters.resets,
+            inits: self.counters.inits,
+            wake_retries: self.counters.wake_retries,
+            clock_stretch_retries: self.counters.clock_stretch_retries
+        }
+    }
+}
+
+impl<I2C, WAKE, RESET, E> CCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError,
+          WAKE: OutputPin,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin,
+          <RESET as OutputPin>::Error: SourceError {
+
+    /// Collects bus parameters, the answers on both addresses, versions, status, error ID and the
+    /// last transactions into a report. Nothing fails, every failure is part of the report. Please
+    /// attach it when reporting issues.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// println!("{}", ccs811.diagnose());
+    /// ```
+    pub fn diagnose(&mut self) -> Diagnosis {
+        let transactions = self.transactions.0.iter().cloned().collect();
+        let wake = self.awake().map_err(|error| error.to_string());
+
+        let probes = [CCS811_SLAVEADDR_0, CCS811_SLAVEADDR_1].iter()
+            .map(|&address| {
+                let mut buffer = [0; 1];
+                let hardware_id = self.i2c.write_read(address, &[CCS811_HW_ID], &mut buffer)
+                    .map(|_| buffer[0])
+                    .map_err(|error| error.to_string());
+
+                AddressProbe { address, hardware_id }
+            })
+            .collect();
+
+        let diagnosis = Diagnosis {
+            address: self.address(),
+            bus_clock_hz: self.bus_clock_hz,
+            wake,
+            probes,
+            hardware_version: self.hardware_version().map_err(|error| error.to_string()),
+            bootloader_version: self.bootloader_version()
+                .map(FirmwareVersion::from_bytes)
+                .map_err(|error| error.to_string()),
+            application_version: self.application_version()
+                .map(FirmwareVersion::from_bytes)
+                .map_err(|error| error.to_string()),
+            status: self.read_register_byte(CCS811_STATUS).map_err(|error| error.to_string()),
+            error_id: self.read_register_byte(CCS811_ERROR_ID).map_err(|error| error.to_string()),
+            metrics: self.metrics(),
+            transactions
+        };
+
+        let _ = self.sleep();
+
+        diagnosis
+    }
+}
@@ -0,0 +1,250 @@
+use ::embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use ::embedded_graphics::mono_font::{MonoFont, MonoTextStyle};
+use ::embedded_graphics::pixelcolor::{BinaryColor, PixelColor, Rgb888};
+use ::embedded_graphics::prelude::*;
+use ::embedded_graphics::primitives::{PrimitiveStyle, Rectangle, Triangle};
+use ::embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+use crate::chip::{AirQuality, Ccs811Data};
+
+// Gap between the lines and to the edges in pixels
+const MARGIN: i32 = 2;
+
+/// Colors of the layout of an `AirDisplay`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette<C> {
+    pub foreground: C,
+    pub background: C,
+    pub excellent: C,
+    pub good: C,
+    pub moderate: C,
+    pub poor: C,
+    pub unhealthy: C
+}
+
+/// White on black with the quality going from green to red, for color displays like the ILI9341
+impl<C: PixelColor + From<Rgb888>> Default for Palette<C> {
+    fn default() -> Self {
+        Palette {
+            foreground: Rgb888::WHITE.into(),
+            background: Rgb888::BLACK.into(),
+            excellent: Rgb888::new(0, 200, 80).into(),
+            good: Rgb888::new(150, 210, 0).into(),
+            moderate: Rgb888::new(255, 200, 0).into(),
+            poor: Rgb888::new(255, 110, 0).into(),
+            unhealthy: Rgb888::new(230, 0, 0).into()
+        }
+    }
+}
+
+impl Palette<BinaryColor> {
+
+    /// Lit pixels on a dark display like the SSD1306, the quality is only told by its name
+    pub fn monochrome() -> Self {
+        Palette {
+            foreground: BinaryColor::On,
+            background: BinaryColor::Off,
+            excellent: BinaryColor::On,
+            good: BinaryColor::On,
+            moderate: BinaryColor::On,
+            poor: BinaryColor::On,
+            unhealthy: BinaryColor::On
+        }
+    }
+}
+
+impl<C: Copy> Palette<C> {
+
+    pub fn quality(&self, quality: AirQuality) -> C {
+        match quality {
+            AirQuality::Excellent => self.excellent,
+            AirQuality::Good => self.good,
+            AirQuality::Moderate => self.moderate,
+            AirQuality::Poor => self.poor,
+            AirQuality::Unhealthy => self.unhealthy
+        }
+    }
+}
+
+/// Direction a value took over the trend window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trend {
+    Rising,
+    Steady,
+    Falling
+}
+
+impl Trend {
+
+    // Changes within 5% of the older value or 10 units are steady, as the readings fluctuate
+    fn between(older: u16, newer: u16) -> Self {
+        let tolerance = (older / 20).max(10);
+        if newer > older.saturating_add(tolerance) {
+            Trend::Rising
+        } else if newer < older.saturating_sub(tolerance) {
+            Trend::Falling
+        } else {
+            Trend::Steady
+        }
+    }
+}
+
+/// Renders the latest reading to any `embedded-graphics` `DrawTarget`, e.g. an SSD1306 or ILI9341 on
+/// the same Pi as the chip. eCO2 and tVOC are drawn as large numbers with an arrow of their trend over
+/// the last 5 minutes, below them a bar in the color of the air quality with its name. The layout
+/// fills the width of the target and needs about 128x64 pixels with the default fonts.
+///
+/// Invalid readings are drawn as `--` and the bar tells when the chip is still warming up.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use embedded_graphics::{mock_display::MockDisplay, pixelcolor::BinaryColor};
+/// # trait Flush { fn flush(&mut self) -> Result<(), ()> { Ok(()) } }
+/// # impl Flush for MockDisplay<BinaryColor> {}
+/// # fn main() -> Result<(), ccs811::Ccs811Error> {
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// # let mut ssd1306 = MockDisplay::<BinaryColor>::new();
+/// use ccs811::display::{AirDisplay, Palette};
+///
+/// let mut display = AirDisplay::new(Palette::monochrome());
+/// loop {
+///   display.update(&ccs811.sleep_until_next_sample()?);
+///
+///   display.draw(&mut ssd1306).expect("Could not draw");
+///   ssd1306.flush().expect("Could not flush");
+/// }
+/// # }
+/// ```
+pub struct AirDisplay<C> {
+    palette: Palette<C>,
+    large_font: &'static MonoFont<'static>,
+    small_font: &'static MonoFont<'static>,
+    trend_window: Duration,
+    latest: Option<Ccs811Data>,
+    // Valid readings within the trend window as time, eCO2 and tVOC
+    recent: VecDeque<(Instant, u16, u16)>
+}
+
+impl<C: PixelColor> AirDisplay<C> {
+
+    pub fn new(palette: Palette<C>) -> Self {
+        AirDisplay {
+            palette,
+            large_font: &FONT_10X20,
+            small_font: &FONT_6X10,
+            trend_window: Duration::from_secs(5 * 60),
+            latest: None,
+            recent: VecDeque::new()
+        }
+    }
+
+    /// Fonts of the numbers and of the units and labels, e.g. larger ones for a 320x240 display
+    pub fn with_fonts(self, large_font: &'static MonoFont<'static>, small_font: &'static MonoFont<'static>) -> Self {
+        AirDisplay { large_font, small_font, ..self }
+    }
+
+    /// Time span the trend arrows are calculated over
+    pub fn with_trend_window(self, trend_window: Duration) -> Self {
+        AirDisplay { trend_window, ..self }
+    }
+
+    /// Shows the reading on the next `draw()`
+    pub fn update(&mut self, data: &Ccs811Data) {
+        self.update_at(data, Instant::now())
+    }
+
+    /// Shows the reading taken at the given time on the next `draw()`
+    pub fn update_at(&mut self, data: &Ccs811Data, at: Instant) {
+        self.latest = Some(*data);
+        if data.is_valid() {
+            self.recent.push_back((at, data.e_co2, data.t_voc));
+        }
+        while self.recent.front().is_some_and(|(time, _, _)| at.saturating_duration_since(*time) > self.trend_window) {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Trends of eCO2 and tVOC over the trend window, `None` without two valid readings in it
+    pub fn trend(&self) -> Option<(Trend, Trend)> {
+        let (&(oldest, e_co2_then, t_voc_then), &(newest, e_co2, t_voc)) = (self.recent.front()?, self.recent.back()?);
+        if newest == oldest {
+            return None;
+        }
+
+        Some((Trend::between(e_co2_then, e_co2), Trend::between(t_voc_then, t_voc)))
+    }
+
+    /// Clears the target and draws the latest reading, or `waiting for data` before the first one
+    pub fn draw<D: DrawTarget<Color = C>>(&self, target: &mut D) -> Result<(), D::Error> {
+        target.clear(self.palette.background)?;
+        let area = target.bounding_box();
+        let left = area.top_left.x.saturating_add(MARGIN);
+        let top = area.top_left.y.saturating_add(MARGIN);
+        let small = MonoTextStyle::new(self.small_font, self.palette.foreground);
+
+        let Some(data) = self.latest else {
+            Text::with_baseline("waiting for data", Point::new(left, top), small, Baseline::Top).draw(target)?;
+            return Ok(());
+        };
+
+        let trend = self.trend();
+        let line_height = i32::try_from(self.large_font.character_size.height).unwrap_or(i32::MAX);
+        let second_line = top.saturating_add(line_height).saturating_add(MARGIN);
+        self.draw_value(target, Point::new(left, top), data.e_co2, "ppm eCO2", trend.map(|(e_co2, _)| e_co2), data.is_valid())?;
+        self.draw_value(target, Point::new(left, second_line), data.t_voc, "ppb tVOC", trend.map(|(_, t_voc)| t_voc), data.is_valid())?;
+
+        // The bar takes the rest of the height
+        let bar_top = second_line.saturating_add(line_height).saturating_add(MARGIN);
+        let bar = Rectangle::with_corners(Point::new(area.top_left.x, bar_top), area.bottom_right().unwrap_or(area.top_left));
+        let label = match (data.is_valid(), data.is_warming_up()) {
+            (false, _) => "no valid reading".to_string(),
+            (true, true) => format!("{}, warming up", data.air_quality()),
+            (true, false) => data.air_quality().to_string()
+        };
+        let (fill, text) = if data.is_valid() {
+            (self.palette.quality(data.air_quality()), self.palette.background)
+        } else {
+            (self.palette.background, self.palette.foreground)
+        };
+        bar.into_styled(PrimitiveStyle::with_fill(fill)).draw(target)?;
+        let centered = TextStyleBuilder::new().alignment(Alignment::Center).baseline(Baseline::Middle).build();
+        Text::with_text_style(&label, bar.center(), MonoTextStyle::new(self.small_font, text), centered).draw(target)?;
+
+        Ok(())
+    }
+
+    // Draws a number with its unit and trend arrow at the right edge
+    fn draw_value<D: DrawTarget<Color = C>>(&self, target: &mut D, position: Point, value: u16, unit: &str,
+                                            trend: Option<Trend>, valid: bool) -> Result<(), D::Error> {
+        let large = MonoTextStyle::new(self.large_font, self.palette.foreground);
+        let small = MonoTextStyle::new(self.small_font, self.palette.foreground);
+        let number = if valid { value.to_string() } else { "--".to_string() };
+
+        let after = Text::with_baseline(&number, position, large, Baseline::Top).draw(target)?;
+        let height = i32::try_from(self.large_font.character_size.height).unwrap_or(i32::MAX);
+        let unit_position = Point::new(after.x.saturating_add(MARGIN), position.y.saturating_add(height).saturating_sub(MARGIN));
+        Text::with_baseline(unit, unit_position, small, Baseline::Bottom).draw(target)?;
+
+        let Some(trend) = trend.filter(|_| valid) else {
+            return Ok(());
+        };
+        // A triangle as large as a digit in the line, pointing up, right or down
+        let size = height.saturating_sub(MARGIN.saturating_mul(2)) / 2;
+        let right = target.bounding_box().bottom_right().map_or(position.x, |corner| corner.x).saturating_sub(MARGIN);
+        let left = right.saturating_sub(size.saturating_mul(2));
+        let middle = position.y.saturating_add(height / 2);
+        let (top, bottom) = (middle.saturating_sub(size), middle.saturating_add(size));
+        let center = right.saturating_sub(size);
+        let arrow = match trend {
+            Trend::Rising => Triangle::new(Point::new(center, top), Point::new(left, bottom), Point::new(right, bottom)),
+            Trend::Steady => Triangle::new(Point::new(left, top), Point::new(left, bottom), Point::new(right, middle)),
+            Trend::Falling => Triangle::new(Point::new(left, top), Point::new(right, top), Point::new(center, bottom))
+        };
+        arrow.into_styled(PrimitiveStyle::with_fill(self.palette.foreground)).draw(target)?;
+
+        Ok(())
+    }
+}
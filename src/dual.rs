@@ -0,0 +1,195 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use crate::chip::{CCS811, Ccs811Data};
+use crate::constants::Ccs811Mode;
+use crate::error::{Ccs811Error, SourceError};
+
+/// Result of reading both chips of a `DualCCS811`
+pub struct CrossValidation {
+    pub primary: Ccs811Data,
+    pub secondary: Ccs811Data,
+    /// Absolute difference of the eCO2 values in ppm
+    pub e_co2_divergence: u16,
+    /// Absolute difference of the tVOC values in ppb
+    pub t_voc_divergence: u16,
+    /// Whether both divergences are within the configured thresholds
+    pub agree: bool
+}
+
+impl CrossValidation {
+
+    /// Average eCO2 of both chips in ppm
+    pub fn e_co2(&self) -> u16 {
+        average(self.primary.e_co2, self.secondary.e_co2)
+    }
+
+    /// Average tVOC of both chips in ppb
+    pub fn t_voc(&self) -> u16 {
+        average(self.primary.t_voc, self.secondary.t_voc)
+    }
+}
+
+fn average(a: u16, b: u16) -> u16 {
+    (u32::from(a).saturating_add(u32::from(b)) / 2) as u16
+}
+
+/// Two chips, usually at 0x5A and 0x5B, measuring the same air. As both should report similar
+/// values, a growing divergence reveals a degrading sensor.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rppal::i2c::I2c;
+/// use ccs811::dual::DualCCS811;
+///
+/// let primary = ccs811::new(I2c::with_bus(1).unwrap(), None);
+/// let secondary = ccs811::new(I2c::with_bus(1).unwrap(), None).with_address(ccs811::CCS811_SLAVEADDR_1);
+///
+/// let mut sensors = DualCCS811::new(primary, secondary, 100, 50);
+/// sensors.begin().expect("Could not init the chips");
+/// sensors.start(ccs811::MODE::Sec1).expect("Could not start");
+///
+/// let result = sensors.read().expect("Could not read data");
+/// if !result.agree {
+///   println!("Sensors disagree by {}ppm", result.e_co2_divergence);
+/// }
+/// ```
+pub struct DualCCS811<I2C1, WAKE1, RESET1, I2C2, WAKE2, RESET2> {
+    pub primary: CCS811<I2C1, WAKE1, RESET1>,
+    pub secondary: CCS811<I2C2, WAKE2, RESET2>,
+    max_e_co2_divergence: u16,
+    max_t_voc_divergence: u16
+}
+
+impl<I2C1, WAKE1, RESET1, I2C2, WAKE2, RESET2, E1, E2> DualCCS811<I2C1, WAKE1, RESET1, I2C2, WAKE2, RESET2>
+    where I2C1: Write<Error = E1> + WriteRead<Error = E1>,
+          E1: SourceError,
+          WAKE1: OutputPin,
+          <WAKE1 as OutputPin>::Error: SourceError,
+          RESET1: OutputPin,
+          <RESET1 as OutputPin>::Error: SourceError,
+          I2C2: Write<Error = E2> + WriteRead<Error = E2>,
+          E2: SourceError,
+          WAKE2: OutputPin,
+          <WAKE2 as OutputPin>::Error: SourceError,
+          RESET2: OutputPin,
+          <RESET2 as OutputPin>::Error: SourceError {
+
+    /// The chips are considered to disagree if eCO2 differs by more than `max_e_co2_divergence` ppm
+    /// or tVOC by more than `max_t_voc_divergence` ppb
+    pub fn new(primary: CCS811<I2C1, WAKE1, RESET1>, secondary: CCS811<I2C2, WAKE2, RESET2>,
+               max_e_co2_divergence: u16, max_t_voc_divergence: u16) -> Self {
+        DualCCS811 { primary, secondary, max_e_co2_divergence, max_t_voc_divergence }
+    }
+
+    pub fn begin(&mut self) -> Result<(), Ccs811Error> {
+        self.primary.begin()?;
+        self.secondary.begin()
+    }
+
+    pub fn start(&mut self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.primary.start(mode)?;
+        self.secondary.start(mode)
+    }
+
+    pub fn set_env_data(&mut self, humidity: f32, temperature: f32) -> Result<(), Ccs811Error> {
+        self.primary.set_env_data(humidity, temperature)?;
+        self.secondary.set_env_data(humidity, temperature)
+    }
+
+    /// Reads both chips and compares their values
+    pub fn read(&mut self) -> Result<CrossValidation, Ccs811Error> {
+        let primary = self.primary.read()?;
+        let secondary = self.secondary.read()?;
+
+        let e_co2_divergence = primary.e_co2.abs_diff(secondary.e_co2);
+        let t_voc_divergence = primary.t_voc.abs_diff(secondary.t_voc);
+
+        Ok(CrossValidation {
+            primary,
+            secondary,
+            e_co2_divergence,
+            t_voc_divergence,
+            agree: e_co2_divergence <= self.max_e_co2_divergence && t_voc_divergence <= self.max_t_voc_divergence
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::chip::NoPin;
+    use crate::constants::CCS811_SLAVEADDR_1;
+    use crate::sim::SimCcs811;
+    use super::*;
+
+    fn begin() -> DualCCS811<SimCcs811, NoPin, NoPin, SimCcs811, NoPin, NoPin> {
+        let primary = CCS811::new(SimCcs811::new(), None::<NoPin>).with_discarded_samples(0);
+        let secondary = CCS811::new(SimCcs811::new().with_address(CCS811_SLAVEADDR_1), None::<NoPin>)
+            .with_address(CCS811_SLAVEADDR_1)
+            .with_discarded_samples(0);
+        let mut sensors = DualCCS811::new(primary, secondary, 100, 50);
+        sensors.begin().unwrap();
+        sensors.start(Ccs811Mode::Sec1).unwrap();
+
+        sensors
+    }
+
+    fn read(sensors: &mut DualCCS811<SimCcs811, NoPin, NoPin, SimCcs811, NoPin, NoPin>,
+            primary: (u16, u16), secondary: (u16, u16)) -> CrossValidation {
+        sensors.primary.i2c.set_air(primary.0, primary.1);
+        sensors.primary.i2c.elapse(Duration::from_secs(1));
+        sensors.secondary.i2c.set_air(secondary.0, secondary.1);
+        sensors.secondary.i2c.elapse(Duration::from_secs(1));
+
+        sensors.read().unwrap()
+    }
+
+    #[test]
+    fn chips_within_the_thresholds_agree() {
+        let mut sensors = begin();
+
+        let result = read(&mut sensors, (800, 60), (900, 10));
+        assert_eq!((result.e_co2_divergence, result.t_voc_divergence), (100, 50));
+        assert!(result.agree);
+        assert_eq!((result.e_co2(), result.t_voc()), (850, 35));
+    }
+
+    #[test]
+    fn chips_beyond_a_threshold_disagree() {
+        let mut sensors = begin();
+
+        let result = read(&mut sensors, (1000, 60), (899, 60));
+        assert_eq!(result.e_co2_divergence, 101);
+        assert!(!result.agree);
+
+        let result = read(&mut sensors, (800, 0), (800, 51));
+        assert_eq!(result.t_voc_divergence, 51);
+        assert!(!result.agree);
+    }
+
+    #[test]
+    fn average_does_not_overflow() {
+        assert_eq!(average(u16::MAX, u16::MAX), u16::MAX);
+        assert_eq!(average(401, 400), 400);
+    }
+
+    #[test]
+    fn both_chips_get_the_environment() {
+        let mut sensors = begin();
+
+        sensors.set_env_data(48.5, 23.3).unwrap();
+        assert_eq!(sensors.primary.i2c.env_data(), [0x61, 0x00, 0x60, 0x9A]);
+        assert_eq!(sensors.secondary.i2c.env_data(), [0x61, 0x00, 0x60, 0x9A]);
+    }
+
+    #[test]
+    fn begin_fails_if_the_secondary_chip_fails() {
+        let primary = CCS811::new(SimCcs811::new(), None::<NoPin>);
+        let secondary = CCS811::new(SimCcs811::new().without_application(), None::<NoPin>);
+        let mut sensors = DualCCS811::new(primary, secondary, 100, 50);
+
+        assert!(matches!(sensors.begin(), Err(Ccs811Error::NoValidApplication(_))));
+        assert!(sensors.primary.i2c.is_app_mode());
+    }
+}
@@ -0,0 +1,230 @@
+use std::io;
+use thiserror::Error;
+use crate::firmware::FirmwareVersion;
+
+/// Errors of the I2C bus, the pins or other sources kept as `source()` of a `Ccs811Error`
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Bound of the I2C bus and pin errors. They're preserved as `source()` of the `Ccs811Error`, so
+/// they have to be proper errors which can be sent between threads.
+pub trait SourceError: std::error::Error + Send + Sync + 'static {}
+
+impl<T: std::error::Error + Send + Sync + 'static> SourceError for T {}
+
+/// Errors of the CCS811 driver
+#[derive(Debug, Error)]
+pub enum Ccs811Error {
+    /// A transaction on the I2C bus failed
+    #[error("Could not {operation}")]
+    I2c { operation: &'static str, #[source] source: BoxError },
+    /// Setting the wake or reset pin failed
+    #[error("Could not {operation}")]
+    Pin { operation: &'static str, #[source] source: BoxError },
+    /// The device doesn't identify itself as CCS811
+    #[error("HWID of chip is not 0x81 but {0:#x}")]
+    HardwareId(u8),
+    /// The status register doesn't have the expected bits set
+    #[error("Chip status is not {expected:#010b} but {actual:#010b}")]
+    Status { expected: u8, actual: u8 },
+    /// APP_VALID is clear, the boot loader has no application to start. Flash one with `flash()` or
+    /// `ccs811ctl flash`.
+    #[error("No valid application on the chip (status {0:#010b}), flash a firmware e.g. with ccs811ctl flash")]
+    NoValidApplication(u8),
+    /// The chip reported an error in the ERROR_ID byte of the result data
+    #[error("Some error while reading data {0:#x}")]
+    Chip(u8),
+    /// The measured values exceed the maximum specified for the chip
+    #[error("The data is above max {t_voc}ppb, {e_co2}ppm")]
+    OutOfRange { t_voc: u16, e_co2: u16 },
+    /// The humidity can't be written to the chip, see `CCS811::set_env_data`
+    #[error("Humidity of {0}% is not supported")]
+    InvalidHumidity(f32),
+    /// The temperature can't be written to the chip, see `CCS811::set_env_data`
+    #[error("Temperature of {0}°C is not supported")]
+    InvalidTemperature(f32),
+    /// The chip kept stretching the clock longer than the I2C controller tolerates, even after the
+    /// clock stretching retries of the `RetryConfig`
+    #[error("Could not {operation}, the chip stretched the clock too long. Lower the I2C clock e.g. with dtparam=i2c_arm_baudrate=10000 in /boot/config.txt and check the pull-ups")]
+    ClockStretching { operation: &'static str, #[source] source: BoxError },
+    /// The I2C bus is too fast for the clock stretching of the chip
+    #[error("I2C clock of {clock_hz}Hz exceeds the {max_hz}Hz the chip's clock stretching tolerates, lower it e.g. with dtparam=i2c_arm_baudrate=10000 in /boot/config.txt")]
+    BusClock { clock_hz: u32, max_hz: u32 },
+    /// The chip is in Idle mode, so no new sample will become ready
+    #[error("The chip doesn't sample in Idle mode")]
+    NotSampling,
+    /// The firmware doesn't report or keep a meaningful baseline during the warm-up after `start()`
+    #[error("The baseline is not ready during the warm-up, {} seconds remaining", remaining.as_secs())]
+    BaselineNotReady { remaining: std::time::Duration },
+    /// `calibrate_against()` needs a valid sample, the chip reports less than 400ppm before the first one
+    #[error("No valid sample to calibrate against, eCO2 is {0}ppm")]
+    InvalidSample(u16),
+    /// The chip left the bus, e.g. it was unplugged, see `CCS811::with_hot_swap()`
+    #[error("The chip is disconnected for {}s", outage.as_secs())]
+    Disconnected { outage: std::time::Duration },
+    /// `hard_reset()` was called without a reset pin
+    #[error("No reset pin configured")]
+    NoResetPin,
+    /// A calibration profile was captured with another firmware, whose baseline doesn't apply
+    #[error("Calibration profile is for firmware {profile}, but the chip runs {chip}")]
+    ProfileFirmware { profile: FirmwareVersion, chip: FirmwareVersion },
+    /// The firmware image or the flashed firmware isn't the expected one
+    #[error("Could not flash firmware: {0}")]
+    Flash(String),
+    /// A step of flashing a new firmware failed
+    #[error("Could not flash firmware, {step}")]
+    FlashStep { step: &'static str, #[source] source: BoxError }
+}
+
+/// Maps an error of the I2C bus to a `Ccs811Error::I2c` describing the failed operation
+pub(crate) fn i2c_error<E: SourceError>(operation: &'static str) -> impl FnOnce(E) -> Ccs811Error {
+    move |error| match is_clock_stretching(&error) {
+        true => Ccs811Error::ClockStretching { operation, source: Box::new(error) },
+        false => Ccs811Error::I2c { operation, source: Box::new(error) }
+    }
+}
+
+/// Whether the I2C error is the timeout the controller reports when the chip stretches the clock
+/// longer than it tolerates, like the Raspberry Pi does with ETIMEDOUT
+pub(crate) fn is_clock_stretching(error: &(dyn std::error::Error + 'static)) -> bool {
+    let is_timeout = |error: &io::Error| error.kind() == io::ErrorKind::TimedOut;
+
+    let mut source = Some(error);
+    while let Some(error) = source {
+        // rppal doesn't expose the io::Error as source
+        #[cfg(feature = "rppal")]
+        if let Some(rppal::i2c::Error::Io(error)) = error.downcast_ref::<rppal::i2c::Error>() {
+            return is_timeout(error);
+        }
+        if let Some(error) = error.downcast_ref::<io::Error>() {
+            return is_timeout(error);
+        }
+        source = error.source();
+    }
+
+    false
+}
+
+/// Whether the I2C error is a missing acknowledge of the chip, which Linux reports as ENXIO or
+/// EREMOTEIO depending on the I2C controller
+pub(crate) fn is_nack(error: &(dyn std::error::Error + 'static)) -> bool {
+    const ENXIO: i32 = 6;
+    const EREMOTEIO: i32 = 121;
+    let is_nack = |error: &io::Error| matches!(error.raw_os_error(), Some(ENXIO) | Some(EREMOTEIO));
+
+    let mut source = Some(error);
+    while let Some(error) = source {
+        #[cfg(feature = "rppal")]
+        if let Some(rppal::i2c::Error::Io(error)) = error.downcast_ref::<rppal::i2c::Error>() {
+            return is_nack(error);
+        }
+        if let Some(error) = error.downcast_ref::<io::Error>() {
+            return is_nack(error);
+        }
+        #[cfg(any(test, feature = "testing"))]
+        if let Some(crate::sim::SimError::AddressNack(_)) = error.downcast_ref::<crate::sim::SimError>() {
+            return true;
+        }
+        source = error.source();
+    }
+
+    false
+}
+
+/// Maps an error of the wake or reset pin to a `Ccs811Error::Pin` describing the failed operation
+pub(crate) fn pin_error<E: SourceError>(operation: &'static str) -> impl FnOnce(E) -> Ccs811Error {
+    move |error| Ccs811Error::Pin { operation, source: Box::new(error) }
+}
+
+/// Maps an error during flashing to a `Ccs811Error::FlashStep` describing the failed step
+#[cfg(feature = "flash")]
+pub(crate) fn flash_error<E: Into<BoxError>>(step: &'static str) -> impl FnOnce(E) -> Ccs811Error {
+    move |error| Ccs811Error::FlashStep { step, source: error.into() }
+}
+
+/// How a supervision loop should react to a `Ccs811Error`, see `Ccs811Error::recovery_hint()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// Retry the failed transaction, e.g. after a NACK on the bus
+    Retry,
+    /// Reinitialize the chip with `begin()` or `hard_reset()`, it might have been reset or lost its mode
+    Reinitialize,
+    /// Flash the firmware again, the chip is stuck in boot loader mode
+    Reflash,
+    /// Retrying won't help, check the wiring, the chip or its power supply, or slow down the bus clock
+    Hardware,
+    /// The driver was used with invalid arguments or configuration
+    Usage
+}
+
+impl Ccs811Error {
+
+    /// Short name of the error without details, e.g. to label metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Ccs811Error::I2c { .. } => "i2c",
+            Ccs811Error::Pin { .. } => "pin",
+            Ccs811Error::HardwareId(_) => "hardware_id",
+            Ccs811Error::Status { .. } => "status",
+            Ccs811Error::NoValidApplication(_) => "no_valid_application",
+            Ccs811Error::Chip(_) => "chip",
+            Ccs811Error::OutOfRange { .. } => "out_of_range",
+            Ccs811Error::InvalidHumidity(_) => "invalid_humidity",
+            Ccs811Error::InvalidTemperature(_) => "invalid_temperature",
+            Ccs811Error::ClockStretching { .. } => "clock_stretching",
+            Ccs811Error::BusClock { .. } => "bus_clock",
+            Ccs811Error::NotSampling => "not_sampling",
+            Ccs811Error::BaselineNotReady { .. } => "baseline_not_ready",
+            Ccs811Error::InvalidSample(_) => "invalid_sample",
+            Ccs811Error::Disconnected { .. } => "disconnected",
+            Ccs811Error::NoResetPin => "no_reset_pin",
+            Ccs811Error::ProfileFirmware { .. } => "profile_firmware",
+            Ccs811Error::Flash(_) | Ccs811Error::FlashStep { .. } => "flash"
+        }
+    }
+
+    /// Whether retrying the operation is likely to succeed
+    pub fn is_transient(&self) -> bool {
+        self.recovery_hint() == Recovery::Retry
+    }
+
+    /// Tells how to recover from this error without matching the error messages
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// use ccs811::error::Recovery;
+    ///
+    /// match ccs811.read() {
+    ///   Ok(data) => println!("e_co2: {}", data.e_co2),
+    ///   Err(error) => match error.recovery_hint() {
+    ///     Recovery::Retry => (),
+    ///     Recovery::Reinitialize => ccs811.begin().expect("Could not init the chip"),
+    ///     _ => panic!("Can not recover: {}", error)
+    ///   }
+    /// }
+    /// ```
+    pub fn recovery_hint(&self) -> Recovery {
+        match self {
+            Ccs811Error::I2c { .. } => Recovery::Retry,
+            Ccs811Error::OutOfRange { .. } => Recovery::Retry,
+            // Retry after the warm-up
+            Ccs811Error::BaselineNotReady { .. } => Recovery::Retry,
+            // Retry with the next sample
+            Ccs811Error::InvalidSample(_) => Recovery::Retry,
+            // The driver probes for the chip and re-initializes it when it's back
+            Ccs811Error::Disconnected { .. } => Recovery::Retry,
+            Ccs811Error::Status { .. } => Recovery::Reinitialize,
+            // HEATER_FAULT and HEATER_SUPPLY can't be fixed by the driver, the others indicate the chip
+            // lost its state
+            Ccs811Error::Chip(error_id) if error_id & 0b0011_0000 != 0 => Recovery::Hardware,
+            Ccs811Error::Chip(_) => Recovery::Reinitialize,
+            Ccs811Error::FlashStep { .. } | Ccs811Error::NoValidApplication(_) => Recovery::Reflash,
+            Ccs811Error::Pin { .. } | Ccs811Error::HardwareId(_) => Recovery::Hardware,
+            // The bus has to be slowed down, retrying at the same clock rarely helps
+            Ccs811Error::BusClock { .. } | Ccs811Error::ClockStretching { .. } => Recovery::Hardware,
+            Ccs811Error::InvalidHumidity(_) | Ccs811Error::InvalidTemperature(_) | Ccs811Error::NotSampling | Ccs811Error::NoResetPin | Ccs811Error::Flash(_)
+            | Ccs811Error::ProfileFirmware { .. } => Recovery::Usage
+        }
+    }
+}
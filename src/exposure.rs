@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use crate::chip::Ccs811Data;
+
+/// Window of the usual occupational exposure limits
+pub const EIGHT_HOURS: Duration = Duration::from_secs(8 * 60 * 60);
+/// Longest window an `ExposureTracker` keeps readings for
+pub const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Readings are summed up per minute, so a day takes 1440 buckets whatever the sampling rate
+const BUCKET: Duration = Duration::from_secs(60);
+
+/// Time-weighted average of eCO2 and tVOC over a window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Twa {
+    /// eCO2 in ppm
+    pub e_co2: f32,
+    /// tVOC in ppb
+    pub t_voc: f32,
+    pub window: Duration,
+    /// Time of the window covered by valid readings, the averages are over this time
+    pub covered: Duration
+}
+
+impl Twa {
+
+    /// Share of the window covered by valid readings, from 0 to 1. Compliance reports usually ask for
+    /// the whole window, e.g. a full shift.
+    pub fn coverage(&self) -> f32 {
+        (self.covered.as_secs_f32() / self.window.as_secs_f32()).min(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: Instant,
+    // Concentrations integrated over the covered seconds
+    e_co2: f64,
+    t_voc: f64,
+    covered: Duration
+}
+
+/// Calculates the time-weighted averages (TWA) of eCO2 and tVOC over the last 8 or 24 hours for
+/// occupational health reports. Each reading counts for the time until the next one, so readings
+/// taken at irregular intervals or in different modes are weighted correctly. A reading counts for at
+/// most `max_hold`, 5 minutes by default, so gaps like a disconnected sensor aren't filled with stale
+/// values. Invalid readings and the warm-up aren't counted.
+///
+/// The readings are summed up per minute, which bounds the memory to a few dozen kB and the window
+/// to a resolution of a minute.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), ccs811::Ccs811Error> {
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::exposure::ExposureTracker;
+///
+/// let mut exposure = ExposureTracker::new();
+/// loop {
+///   exposure.record(&ccs811.sleep_until_next_sample()?);
+///
+///   if let Some(twa) = exposure.twa_8h() {
+///     println!("8h TWA eCO2 {:.0} ppm, tVOC {:.0} ppb over {:.0}% of the window",
+///       twa.e_co2, twa.t_voc, twa.coverage() * 100.0);
+///   }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExposureTracker {
+    buckets: VecDeque<Bucket>,
+    // Last valid reading, which counts until the next one
+    last: Option<(u16, u16, Instant)>,
+    max_hold: Duration
+}
+
+impl Default for ExposureTracker {
+    fn default() -> Self {
+        ExposureTracker::new()
+    }
+}
+
+impl ExposureTracker {
+
+    pub fn new() -> Self {
+        ExposureTracker { buckets: VecDeque::new(), last: None, max_hold: Duration::from_secs(5 * 60) }
+    }
+
+    /// Longest time a reading counts for when no next one follows, set it above the sampling period
+    pub fn with_max_hold(self, max_hold: Duration) -> Self {
+        ExposureTracker { max_hold, ..self }
+    }
+
+    /// Records a reading taken just now
+    pub fn record(&mut self, data: &Ccs811Data) {
+        self.record_at(data, Instant::now())
+    }
+
+    /// Records a reading taken at the given time
+    pub fn record_at(&mut self, data: &Ccs811Data, at: Instant) {
+        if let Some((e_co2, t_voc, since)) = self.last {
+            let held = at.saturating_duration_since(since).min(self.max_hold);
+            self.add(e_co2, t_voc, since, held);
+        }
+        self.last = (data.is_valid() && !data.is_warming_up()).then_some((data.e_co2, data.t_voc, at));
+
+        while let Some(bucket) = self.buckets.front() {
+            if at.saturating_duration_since(bucket.start) < DAY {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+    }
+
+    fn add(&mut self, e_co2: u16, t_voc: u16, at: Instant, held: Duration) {
+        if self.buckets.back().is_none_or(|bucket| at.saturating_duration_since(bucket.start) >= BUCKET) {
+            self.buckets.push_back(Bucket { start: at, e_co2: 0.0, t_voc: 0.0, covered: Duration::ZERO });
+        }
+        let Some(bucket) = self.buckets.back_mut() else {
+            return;
+        };
+        bucket.e_co2 += f64::from(e_co2) * held.as_secs_f64();
+        bucket.t_voc += f64::from(t_voc) * held.as_secs_f64();
+        bucket.covered = bucket.covered.saturating_add(held);
+    }
+
+    /// TWA over the last 8 hours, `None` without valid readings in them
+    pub fn twa_8h(&self) -> Option<Twa> {
+        self.twa(EIGHT_HOURS)
+    }
+
+    /// TWA over the last 24 hours, `None` without valid readings in them
+    pub fn twa_24h(&self) -> Option<Twa> {
+        self.twa(DAY)
+    }
+
+    /// TWA over the window up to now, at most a `DAY`
+    pub fn twa(&self, window: Duration) -> Option<Twa> {
+        self.twa_at(window, Instant::now())
+    }
+
+    /// TWA over the window up to the given time, at most a `DAY`
+    pub fn twa_at(&self, window: Duration, at: Instant) -> Option<Twa> {
+        let window = window.min(DAY);
+        let (e_co2, t_voc, covered) = self.buckets.iter()
+            .filter(|bucket| at.saturating_duration_since(bucket.start) < window)
+            .fold((0.0, 0.0, Duration::ZERO), |(e_co2, t_voc, covered), bucket| {
+                (e_co2 + bucket.e_co2, t_voc + bucket.t_voc, covered.saturating_add(bucket.covered))
+            });
+        if covered.is_zero() {
+            return None;
+        }
+
+        Some(Twa {
+            e_co2: (e_co2 / covered.as_secs_f64()) as f32,
+            t_voc: (t_voc / covered.as_secs_f64()) as f32,
+            window,
+            covered
+        })
+    }
+
+    /// Forgets all readings, e.g. at the start of a shift
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::reading;
+    use super::*;
+
+    fn minutes(start: Instant, minutes: u32) -> Instant {
+        start.checked_add(BUCKET.saturating_mul(minutes)).unwrap()
+    }
+
+    #[test]
+    fn weights_readings_by_time() {
+        let start = Instant::now();
+        let mut exposure = ExposureTracker::new().with_max_hold(Duration::from_secs(3600));
+
+        exposure.record_at(&reading(400, 10), start);
+        exposure.record_at(&reading(1000, 100), minutes(start, 10));
+        exposure.record_at(&reading(1000, 100), minutes(start, 40));
+
+        let twa = exposure.twa_at(EIGHT_HOURS, minutes(start, 40)).unwrap();
+        assert_eq!((twa.e_co2, twa.t_voc), (850.0, 77.5));
+        assert_eq!(twa.covered, BUCKET.saturating_mul(40));
+        assert_eq!(twa.coverage(), 40.0 / 480.0);
+    }
+
+    #[test]
+    fn gaps_are_not_filled_beyond_max_hold() {
+        let start = Instant::now();
+        let mut exposure = ExposureTracker::new();
+
+        exposure.record_at(&reading(400, 10), start);
+        exposure.record_at(&reading(800, 10), minutes(start, 60));
+
+        let twa = exposure.twa_at(EIGHT_HOURS, minutes(start, 60)).unwrap();
+        assert_eq!((twa.e_co2, twa.covered), (400.0, BUCKET.saturating_mul(5)));
+    }
+
+    #[test]
+    fn skips_invalid_readings_and_the_warm_up() {
+        let start = Instant::now();
+        let mut exposure = ExposureTracker::new();
+
+        exposure.record_at(&Ccs811Data { since_start: Some(Duration::ZERO), ..reading(800, 10) }, start);
+        exposure.record_at(&reading(0, 0), minutes(start, 1));
+        exposure.record_at(&reading(800, 10), minutes(start, 2));
+        assert_eq!(exposure.twa_at(EIGHT_HOURS, minutes(start, 2)), None);
+    }
+
+    #[test]
+    fn readings_leave_the_window() {
+        let start = Instant::now();
+        let mut exposure = ExposureTracker::new();
+        exposure.record_at(&reading(400, 10), start);
+        exposure.record_at(&reading(400, 10), minutes(start, 1));
+
+        assert_eq!(exposure.twa_at(EIGHT_HOURS, minutes(start, 9 * 60)), None);
+        assert!(exposure.twa_at(DAY, minutes(start, 9 * 60)).is_some());
+
+        // A reading more than a day later drops them
+        exposure.record_at(&reading(400, 10), minutes(start, 25 * 60));
+        assert_eq!(exposure.twa_at(DAY, minutes(start, 25 * 60)), None);
+        assert!(exposure.buckets.is_empty());
+    }
+}
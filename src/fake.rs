@@ -0,0 +1,277 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use crate::chip::{Ccs811Data, RawData, Status};
+use crate::codec::TemperatureEncoding;
+use crate::constants::*;
+use crate::error::Ccs811Error;
+use crate::pipeline::ReadingSource;
+
+// Outdoor air, the level rooms return to when ventilated
+const OUTDOOR_E_CO2: f32 = 420.0;
+const OUTDOOR_T_VOC: f32 = 10.0;
+const CATCH_UP_LIMIT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Course of the air `FakeCcs811` generates readings for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scenario {
+    /// Office starting at 8:00, occupied from 9:00 to 17:00 with a lunch break from 12:00 to 13:00.
+    /// eCO2 rises to about 1300ppm while occupied and falls back over night.
+    OfficeDay,
+    /// Kitchen with cooking from minute 10 to 40, tVOC rises to about 700ppb and eCO2 to about
+    /// 1400ppm, both fall back within half an hour afterwards.
+    Cooking,
+    /// Stuffy room at 1600ppm eCO2 and 300ppb tVOC with the window opened after 5 minutes
+    WindowOpened,
+    /// Fixed levels, only the noise varies
+    Constant { e_co2: u16, t_voc: u16 }
+}
+
+impl Scenario {
+
+    /// Levels the air approaches at the time since the start of the scenario and how fast, as time
+    /// constant of the exponential approach
+    fn target(&self, time: Duration) -> (f32, f32, Duration) {
+        let minutes = time.as_secs_f32() / 60.0;
+
+        match *self {
+            Scenario::OfficeDay => {
+                let hour = (8.0 + minutes / 60.0) % 24.0;
+                let occupancy = match hour {
+                    hour if (12.0..13.0).contains(&hour) => 0.3,
+                    hour if (9.0..17.0).contains(&hour) => 1.0,
+                    _ => 0.0
+                };
+                (OUTDOOR_E_CO2 + 900.0 * occupancy, OUTDOOR_T_VOC + 140.0 * occupancy, Duration::from_secs(40 * 60))
+            },
+            Scenario::Cooking if (10.0..40.0).contains(&minutes) => (1400.0, 700.0, Duration::from_secs(5 * 60)),
+            Scenario::Cooking => (450.0, 40.0, Duration::from_secs(10 * 60)),
+            Scenario::WindowOpened if minutes < 5.0 => (1600.0, 300.0, Duration::from_secs(60)),
+            Scenario::WindowOpened => (OUTDOOR_E_CO2, OUTDOOR_T_VOC, Duration::from_secs(8 * 60)),
+            Scenario::Constant { e_co2, t_voc } => (f32::from(e_co2), f32::from(t_voc), Duration::from_secs(1))
+        }
+    }
+
+    /// Levels at the start of the scenario
+    fn initial(&self) -> (f32, f32) {
+        let (e_co2, t_voc, _) = self.target(Duration::from_secs(0));
+        (e_co2, t_voc)
+    }
+}
+
+/// Sensor without hardware for developing dashboards and automations. It has the same methods as
+/// `CCS811` for sampling, environment and baseline, but generates plausible readings following a
+/// `Scenario` with some noise. With `with_speed()` the scenario runs faster than real time, e.g. to
+/// watch an office day within minutes.
+///
+/// # Examples
+///
+/// ```
+/// use ccs811::fake::{FakeCcs811, Scenario};
+///
+/// let mut ccs811 = FakeCcs811::new(Scenario::Cooking).with_speed(3600.0);
+/// ccs811.begin().unwrap();
+/// ccs811.start(ccs811::MODE::Sec1).unwrap();
+///
+/// // An hour of cooking within a second
+/// for _ in 0..3600 {
+///   let data = ccs811.sleep_until_next_sample().unwrap();
+///   println!("{}", data);
+/// }
+/// ```
+pub struct FakeCcs811 {
+    scenario: Scenario,
+    speed: f32,
+    created: Instant,
+    mode: Ccs811Mode,
+    // Scenario time sampling was started at
+    started: Option<Duration>,
+    sample_reference: Option<Instant>,
+    // Levels of the air at the scenario time of the last update
+    e_co2: f32,
+    t_voc: f32,
+    updated: Duration,
+    noise: u32,
+    env_data: Option<(f32, f32)>,
+    baseline: u16
+}
+
+impl FakeCcs811 {
+
+    pub fn new(scenario: Scenario) -> Self {
+        let (e_co2, t_voc) = scenario.initial();
+
+        FakeCcs811 {
+            scenario,
+            speed: 1.0,
+            created: Instant::now(),
+            mode: Ccs811Mode::Idle,
+            started: None,
+            sample_reference: None,
+            e_co2,
+            t_voc,
+            updated: Duration::from_secs(0),
+            noise: 0x2545_F491,
+            env_data: None,
+            baseline: 0x8A3C
+        }
+    }
+
+    /// How much faster than real time the scenario and the sampling run. Values below or equal 0, NaN
+    /// and infinity are treated as 1.
+    pub fn with_speed(self, speed: f32) -> Self {
+        FakeCcs811 { speed: if speed > 0.0 && speed.is_finite() { speed } else { 1.0 }, ..self }
+    }
+
+    /// Seed of the noise, fakes with the same seed and speed generate the same readings
+    pub fn with_seed(self, seed: u32) -> Self {
+        // Xorshift never leaves 0
+        FakeCcs811 { noise: seed.max(1), ..self }
+    }
+
+    pub fn scenario(&self) -> Scenario {
+        self.scenario
+    }
+
+    /// Time elapsed in the scenario
+    pub fn scenario_time(&self) -> Duration {
+        // Saturates instead of overflowing at absurd speeds
+        Duration::try_from_secs_f64(self.created.elapsed().as_secs_f64() * f64::from(self.speed)).unwrap_or(Duration::MAX)
+    }
+
+    /// Nothing to initialize, exists to be used like `CCS811`
+    pub fn begin(&mut self) -> Result<(), Ccs811Error> {
+        Ok(())
+    }
+
+    pub fn start(&mut self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.mode = mode;
+        self.started = mode.period().map(|_| self.scenario_time());
+        self.sample_reference = mode.period().map(|_| Instant::now());
+
+        Ok(())
+    }
+
+    pub fn mode(&self) -> Ccs811Mode {
+        self.mode
+    }
+
+    /// Scenario time since sampling was started with `start()`, `None` in Idle mode
+    pub fn since_start(&self) -> Option<Duration> {
+        self.started.map(|started| self.scenario_time().saturating_sub(started))
+    }
+
+    /// When the next sample is ready, the period of the mode is shortened by the speed
+    pub fn next_sample_eta(&self) -> Option<Instant> {
+        let period = Duration::try_from_secs_f32(self.mode.period()?.as_secs_f32() / self.speed).unwrap_or(Duration::MAX);
+        let reference = self.sample_reference?;
+        let samples = ((reference.elapsed().as_secs_f32() / period.as_secs_f32()).floor() as u32).saturating_add(1);
+
+        reference.checked_add(period.saturating_mul(samples))
+    }
+
+    /// Stored for `env_data()`, the generated readings don't depend on it. Like the default of `CCS811`
+    /// with application 2.0 values outside of the supported range are clamped and NaN is rejected.
+    pub fn set_env_data(&mut self, humidity: f32, temperature: f32) -> Result<(), Ccs811Error> {
+        if humidity.is_nan() {
+            return Err(Ccs811Error::InvalidHumidity(humidity));
+        }
+        if temperature.is_nan() {
+            return Err(Ccs811Error::InvalidTemperature(temperature));
+        }
+        let (temperature_min, temperature_max) = TemperatureEncoding::Offset25.range();
+        self.env_data = Some((
+            humidity.clamp(CCS811_HUMIDITY_MIN, CCS811_HUMIDITY_MAX),
+            temperature.clamp(temperature_min, temperature_max)
+        ));
+
+        Ok(())
+    }
+
+    /// Humidity and temperature last set with `set_env_data()`
+    pub fn env_data(&self) -> Option<(f32, f32)> {
+        self.env_data
+    }
+
+    pub fn get_baseline(&mut self) -> Result<u16, Ccs811Error> {
+        Ok(self.baseline)
+    }
+
+    pub fn set_baseline(&mut self, baseline: u16) -> Result<(), Ccs811Error> {
+        self.baseline = baseline;
+
+        Ok(())
+    }
+
+    /// Stops sampling and returns the baseline like `CCS811::shutdown()`
+    pub fn shutdown(&mut self) -> Result<Option<u16>, Ccs811Error> {
+        self.start(Ccs811Mode::Idle)?;
+
+        Ok(Some(self.baseline))
+    }
+
+    /// Multiplier between 0.98 and 1.02
+    fn next_noise(&mut self) -> f32 {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+
+        0.98 + (self.noise % 4001) as f32 / 100_000.0
+    }
+
+    /// Approaches the target levels of the scenario in steps of at most a minute, so the levels
+    /// follow changes of the target within a long gap between two readings
+    fn update(&mut self, time: Duration) {
+        // The levels settle within hours, so of a longer gap only the last day is simulated
+        self.updated = self.updated.max(time.saturating_sub(CATCH_UP_LIMIT));
+        while self.updated < time {
+            let step = time.saturating_sub(self.updated).min(Duration::from_secs(60));
+            let (e_co2, t_voc, time_constant) = self.scenario.target(self.updated);
+            let approach = 1.0 - (-step.as_secs_f32() / time_constant.as_secs_f32()).exp();
+            self.e_co2 += (e_co2 - self.e_co2) * approach;
+            self.t_voc += (t_voc - self.t_voc) * approach;
+            self.updated = self.updated.saturating_add(step);
+        }
+    }
+
+    /// Reading of the air right now. In Idle mode the chip doesn't sample, so like `CCS811::read()`
+    /// the values don't change anymore.
+    pub fn read(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        if self.mode.period().is_some() {
+            self.update(self.scenario_time());
+        }
+
+        let e_co2 = (self.e_co2 * self.next_noise()).round().clamp(400.0, 8192.0) as u16;
+        let t_voc = (self.t_voc * self.next_noise()).round().clamp(0.0, 1187.0) as u16;
+        // Higher concentrations lower the resistance of the sensor and with it the ADC reading
+        let adc = 1023_u16.saturating_sub(e_co2.saturating_sub(400) / 8);
+        let raw = (20 << 10) | adc;
+        let status = CCS811_STATUS_APP_MODE | CCS811_STATUS_APP_VALID | CCS811_STATUS_DATA_READY;
+        let [e_co2_high, e_co2_low] = e_co2.to_be_bytes();
+        let [t_voc_high, t_voc_low] = t_voc.to_be_bytes();
+        let [raw_high, raw_low] = raw.to_be_bytes();
+
+        Ok(Ccs811Data {
+            e_co2,
+            t_voc,
+            raw: RawData([e_co2_high, e_co2_low, t_voc_high, t_voc_low, status, 0, raw_high, raw_low]),
+            status: Status(status),
+            mode: self.mode,
+            since_start: self.since_start()
+        })
+    }
+
+    /// Sleeps until the next sample should be ready and reads it, see `CCS811::sleep_until_next_sample()`
+    pub fn sleep_until_next_sample(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        let eta = self.next_sample_eta()
+            .ok_or(Ccs811Error::NotSampling)?;
+        sleep(eta.saturating_duration_since(Instant::now()));
+
+        self.read()
+    }
+}
+
+impl ReadingSource for FakeCcs811 {
+    fn next_reading(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        self.read()
+    }
+}
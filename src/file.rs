@@ -0,0 +1,339 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::format::{Format, LoggedReading};
+use crate::sink::ReadingSink;
+
+/// Appends readings as lines of a `Format` to a file, newline-delimited JSON or CSV with a header. The
+/// file is rotated when it exceeds a size or age: it's renamed to `<path>.1`, older files move on to
+/// `<path>.2` and so on, and the oldest beyond the number kept are deleted. With the `gzip` feature the
+/// rotated files can be compressed to `<path>.1.gz`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use ccs811::sink::ReadingSink;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use std::time::Duration;
+/// use ccs811::file::FileSink;
+/// use ccs811::format::Format;
+///
+/// let mut file = FileSink::new("/var/log/ccs811/readings.jsonl", Format::Json)
+///     .expect("Could not open file")
+///     .with_max_size(10 * 1024 * 1024)
+///     .with_max_age(Duration::from_secs(24 * 60 * 60))
+///     .with_keep(30);
+///
+/// file.publish(&ccs811.read().expect("Could not read data")).expect("Could not write reading");
+/// ```
+pub struct FileSink {
+    path: PathBuf,
+    format: Format,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    keep: usize,
+    gzip: bool,
+    file: File,
+    size: u64,
+    created: SystemTime
+}
+
+impl FileSink {
+
+    /// Appends to the file if it exists. Doesn't rotate until configured with `with_max_size()` or
+    /// `with_max_age()` and keeps 5 rotated files by default.
+    pub fn new<P: AsRef<Path>>(path: P, format: Format) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (file, size, created) = open(&path, format)?;
+
+        Ok(FileSink {
+            path,
+            format,
+            max_size: None,
+            max_age: None,
+            keep: 5,
+            gzip: false,
+            file,
+            size,
+            created
+        })
+    }
+
+    /// Rotates the file before it grows beyond this many bytes
+    pub fn with_max_size(self, bytes: u64) -> Self {
+        FileSink { max_size: Some(bytes), ..self }
+    }
+
+    /// Rotates the file when it's older than this, e.g. a day for one file per day
+    pub fn with_max_age(self, age: Duration) -> Self {
+        FileSink { max_age: Some(age), ..self }
+    }
+
+    /// Number of rotated files kept, 0 deletes the file on rotation
+    pub fn with_keep(self, keep: usize) -> Self {
+        FileSink { keep, ..self }
+    }
+
+    /// Compresses rotated files with gzip
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(self) -> Self {
+        FileSink { gzip: true, ..self }
+    }
+
+    /// Path of the file currently written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn publish(&mut self, data: &Ccs811Data) -> io::Result<()> {
+        let line = format!("{}\n", self.format.format(data, SystemTime::now()));
+        if self.needs_rotation(line.len()) {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size = self.size.saturating_add(line.len() as u64);
+
+        Ok(())
+    }
+
+    /// Rotates the file right away, independent of its size and age
+    pub fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        match self.keep {
+            0 => fs::remove_file(&self.path)?,
+            keep => {
+                remove_if_exists(&self.rotated(keep))?;
+                for index in (1..keep).rev() {
+                    rename_if_exists(&self.rotated(index), &self.rotated(index.saturating_add(1)))?;
+                }
+                self.archive()?;
+            }
+        }
+
+        let (file, size, created) = open(&self.path, self.format)?;
+        self.file = file;
+        self.size = size;
+        self.created = created;
+
+        Ok(())
+    }
+
+    fn needs_rotation(&self, length: usize) -> bool {
+        // A single line longer than the limit is written anyway instead of rotating empty files
+        let too_large = self.max_size
+            .is_some_and(|max_size| self.size > 0 && self.size.saturating_add(length as u64) > max_size);
+        let too_old = self.max_age
+            .is_some_and(|max_age| self.created.elapsed().is_ok_and(|age| age >= max_age));
+
+        too_large || too_old
+    }
+
+    /// Moves the current file to `<path>.1`, compressed if configured
+    fn archive(&self) -> io::Result<()> {
+        #[cfg(feature = "gzip")]
+        if self.gzip {
+            return self.compress();
+        }
+
+        fs::rename(&self.path, self.rotated(1))
+    }
+
+    #[cfg(feature = "gzip")]
+    fn compress(&self) -> io::Result<()> {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+
+        let target = self.rotated(1);
+        let mut encoder = GzEncoder::new(File::create(&target)?, Compression::default());
+        io::copy(&mut File::open(&self.path)?, &mut encoder)?;
+        encoder.finish()?.sync_all()?;
+
+        fs::remove_file(&self.path)
+    }
+
+    /// Path of the rotated file with the index, 1 being the newest
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        if self.gzip {
+            name.push(".gz");
+        }
+
+        PathBuf::from(name)
+    }
+}
+
+/// Opens the file for appending and writes the header of the format if it's empty. Returns the file,
+/// its size and when it was created.
+fn open(path: &Path, format: Format) -> io::Result<(File, u64, SystemTime)> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let mut size = metadata.len();
+    // Not every file system records the creation time
+    let created = metadata.created().or_else(|_| metadata.modified()).unwrap_or_else(|_| SystemTime::now());
+
+    if size == 0 {
+        if let Some(header) = format.header() {
+            let line = format!("{}\n", header);
+            file.write_all(line.as_bytes())?;
+            size = line.len() as u64;
+        }
+    }
+
+    Ok((file, size, created))
+}
+
+/// Reads a log written by `FileSink` in JSON, CSV or Influx format, which is detected from the first
+/// line. With the `gzip` feature rotated files ending in `.gz` are decompressed.
+///
+/// # Examples
+///
+/// ```no_run
+/// let readings = ccs811::file::read_log("/var/log/ccs811/readings.jsonl.1.gz").expect("Could not read log");
+/// let max = readings.iter().map(|reading| reading.e_co2).max();
+/// ```
+pub fn read_log<P: AsRef<Path>>(path: P) -> io::Result<Vec<LoggedReading>> {
+    let file = File::open(path.as_ref())?;
+    let reader: Box<dyn Read> = match path.as_ref().extension() {
+        #[cfg(feature = "gzip")]
+        Some(extension) if extension == "gz" => Box::new(flate2::read::GzDecoder::new(file)),
+        _ => Box::new(file)
+    };
+
+    let mut lines = BufReader::new(reader).lines();
+    let first = match lines.next() {
+        Some(line) => line?,
+        None => return Ok(Vec::new())
+    };
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let format = Format::detect(&first).ok_or_else(|| invalid(format!("Unknown format of line {:?}", first)))?;
+
+    let mut readings = Vec::new();
+    // The header was consumed as first line already
+    let (first, line_offset) = match format.header() {
+        Some(_) => (None, 2),
+        None => (Some(Ok(first)), 1)
+    };
+    for (index, line) in first.into_iter().chain(lines).enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reading = format.parse(&line)
+            .ok_or_else(|| invalid(format!("Line {} is no {} reading: {:?}", index.saturating_add(line_offset), format, line)))?;
+        readings.push(reading);
+    }
+
+    Ok(readings)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result
+    }
+}
+
+impl ReadingSink for FileSink {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        Ok(FileSink::publish(self, data)?)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        Ok(self.file.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+    use crate::sim::reading;
+    use super::*;
+
+    /// Empty directory for the files of a test
+    fn directory(test: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("ccs811-file-{}-{}", test, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        directory
+    }
+
+    fn rotated(path: &Path, index: usize) -> PathBuf {
+        PathBuf::from(format!("{}.{}", path.display(), index))
+    }
+
+    #[test]
+    fn rotates_by_size() {
+        let path = directory("size").join("readings.csv");
+        // The header and one reading fit
+        let mut file = FileSink::new(&path, Format::Csv).unwrap().with_max_size(60).with_keep(2);
+
+        for e_co2 in [400, 500, 600, 700] {
+            file.publish(&reading(e_co2, 0)).unwrap();
+        }
+        file.flush().unwrap();
+
+        let e_co2s = |path: &Path| read_log(path).unwrap().iter().map(|reading| reading.e_co2).collect::<Vec<_>>();
+        assert_eq!(e_co2s(&path), vec![700]);
+        assert_eq!(e_co2s(&rotated(&path, 1)), vec![600]);
+        assert_eq!(e_co2s(&rotated(&path, 2)), vec![500]);
+        assert!(!rotated(&path, 3).exists());
+    }
+
+    #[test]
+    fn keep_0_deletes_the_file() {
+        let path = directory("keep").join("readings.jsonl");
+        let mut file = FileSink::new(&path, Format::Json).unwrap().with_keep(0);
+        file.publish(&reading(400, 0)).unwrap();
+
+        file.rotate().unwrap();
+        assert!(!rotated(&path, 1).exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compresses_rotated_files() {
+        let path = directory("gzip").join("readings.jsonl");
+        let mut file = FileSink::new(&path, Format::Json).unwrap().with_gzip();
+        file.publish(&reading(400, 0)).unwrap();
+
+        file.rotate().unwrap();
+        let compressed = PathBuf::from(format!("{}.1.gz", path.display()));
+        assert_eq!(read_log(compressed).unwrap().first().map(|reading| reading.e_co2), Some(400));
+    }
+
+    #[test]
+    fn appends_to_an_existing_file() {
+        let path = directory("append").join("readings.csv");
+        FileSink::new(&path, Format::Csv).unwrap().publish(&reading(400, 0)).unwrap();
+        FileSink::new(&path, Format::Csv).unwrap().publish(&reading(500, 0)).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.matches("timestamp,e_co2,t_voc,warming_up").count(), 1);
+        assert_eq!(read_log(&path).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn read_log_reports_invalid_lines() {
+        let path = directory("invalid").join("readings.csv");
+        fs::write(&path, "timestamp,e_co2,t_voc,warming_up\n1700000000,812,63,false\n\n1700000001,oops\n").unwrap();
+
+        let error = read_log(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("Line 4 is no csv reading"), "{}", error);
+    }
+}
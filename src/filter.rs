@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use crate::chip::Ccs811Data;
+
+/// Smooths the eCO2 and tVOC values of consecutive samples. Filters are fed with every sample read
+/// from the chip in the order they were read.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::filter::{Filter, Ewma};
+///
+/// let mut filter = Ewma::new(0.3);
+///
+/// loop {
+///   let data = filter.filter(ccs811.read().expect("Could not read data"));
+///   println!("t_voc: {}, e_co2: {}", data.t_voc, data.e_co2);
+/// }
+/// ```
+pub trait Filter {
+    fn filter(&mut self, data: Ccs811Data) -> Ccs811Data;
+}
+
+/// Simple moving average over the last `window` samples. Suppresses noise well but follows step
+/// changes only with a delay of the whole window.
+pub struct MovingAverage {
+    window: usize,
+    e_co2: VecDeque<u16>,
+    t_voc: VecDeque<u16>
+}
+
+impl MovingAverage {
+
+    /// A window of 0 is treated like 1 which doesn't smooth at all
+    pub fn new(window: usize) -> MovingAverage {
+        let window = window.max(1);
+
+        MovingAverage {
+            window,
+            e_co2: VecDeque::with_capacity(window),
+            t_voc: VecDeque::with_capacity(window)
+        }
+    }
+}
+
+fn push_average(values: &mut VecDeque<u16>, window: usize, value: u16) -> u16 {
+    if values.len() == window {
+        values.pop_front();
+    }
+    values.push_back(value);
+
+    let sum: u32 = values.iter().map(|value| u32::from(*value)).sum();
+    (sum as f32 / values.len() as f32).round() as u16
+}
+
+impl Filter for MovingAverage {
+    fn filter(&mut self, data: Ccs811Data) -> Ccs811Data {
+        Ccs811Data {
+            e_co2: push_average(&mut self.e_co2, self.window, data.e_co2),
+            t_voc: push_average(&mut self.t_voc, self.window, data.t_voc),
+            ..data
+        }
+    }
+}
+
+/// Exponentially weighted moving average. Every sample is weighted with `alpha` and the previous
+/// average with `1 - alpha`. Compared to the moving average it follows step changes like someone
+/// entering the room faster while still suppressing noise. Higher alphas follow faster but smooth less.
+pub struct Ewma {
+    alpha: f32,
+    e_co2: Option<f32>,
+    t_voc: Option<f32>
+}
+
+impl Ewma {
+
+    /// Alpha has to be greater than 0 and at most 1, other values are clamped into this range.
+    /// NaN disables the smoothing like an alpha of 1.
+    pub fn new(alpha: f32) -> Ewma {
+        Ewma {
+            alpha: if alpha.is_nan() { 1.0 } else { alpha.clamp(f32::EPSILON, 1.0) },
+            e_co2: None,
+            t_voc: None
+        }
+    }
+}
+
+fn push_ewma(average: &mut Option<f32>, alpha: f32, value: u16) -> u16 {
+    let value = f32::from(value);
+    let next = match *average {
+        Some(average) => alpha * value + (1.0 - alpha) * average,
+        // The first sample is the best guess we have
+        None => value
+    };
+    *average = Some(next);
+
+    next.round() as u16
+}
+
+impl Filter for Ewma {
+    fn filter(&mut self, data: Ccs811Data) -> Ccs811Data {
+        Ccs811Data {
+            e_co2: push_ewma(&mut self.e_co2, self.alpha, data.e_co2),
+            t_voc: push_ewma(&mut self.t_voc, self.alpha, data.t_voc),
+            ..data
+        }
+    }
+}
+
+/// One dimensional Kalman filter for the eCO2 value. Besides the samples of the CCS811 it can be fed
+/// with readings of a reference sensor like a NDIR CO2 sensor. As the reference is usually far more
+/// accurate, the estimate follows it while the CCS811 fills the gaps between the reference readings.
+/// The tVOC value is not changed.
+///
+/// The noise parameters are variances in ppm². The process noise describes how much the real CO2
+/// concentration changes between two samples, the measurement noises how much the sensors scatter.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// # struct Ndir;
+/// # impl Ndir { fn read_co2(&mut self) -> Option<u16> { None } }
+/// # let mut ndir = Ndir;
+/// use ccs811::filter::{Filter, Kalman};
+///
+/// let mut filter = Kalman::new(25.0, 2500.0, 900.0);
+///
+/// loop {
+///   if let Some(ppm) = ndir.read_co2() {
+///     filter.update_reference(ppm);
+///   }
+///   let data = filter.filter(ccs811.read().expect("Could not read data"));
+///   println!("Corrected e_co2: {}", data.e_co2);
+/// }
+/// ```
+pub struct Kalman {
+    process_noise: f32,
+    measurement_noise: f32,
+    reference_noise: f32,
+    estimate: Option<f32>,
+    variance: f32
+}
+
+impl Kalman {
+
+    pub fn new(process_noise: f32, measurement_noise: f32, reference_noise: f32) -> Kalman {
+        Kalman {
+            process_noise: process_noise.abs(),
+            measurement_noise: measurement_noise.abs(),
+            reference_noise: reference_noise.abs(),
+            estimate: None,
+            variance: 0.0
+        }
+    }
+
+    /// Feeds a reading of the reference sensor in ppm
+    pub fn update_reference(&mut self, e_co2: u16) {
+        self.predict();
+        self.update(f32::from(e_co2), self.reference_noise);
+    }
+
+    /// The current eCO2 estimate in ppm, None until the first value was fed
+    pub fn estimate(&self) -> Option<f32> {
+        self.estimate
+    }
+
+    fn predict(&mut self) {
+        self.variance += self.process_noise;
+    }
+
+    fn update(&mut self, measurement: f32, noise: f32) {
+        match self.estimate {
+            Some(estimate) if self.variance + noise > 0.0 => {
+                let gain = self.variance / (self.variance + noise);
+                self.estimate = Some(estimate + gain * (measurement - estimate));
+                self.variance *= 1.0 - gain;
+            },
+            Some(_) => (),
+            None => {
+                self.estimate = Some(measurement);
+                self.variance = noise;
+            }
+        }
+    }
+}
+
+impl Filter for Kalman {
+    fn filter(&mut self, data: Ccs811Data) -> Ccs811Data {
+        self.predict();
+        self.update(f32::from(data.e_co2), self.measurement_noise);
+
+        Ccs811Data {
+            e_co2: self.estimate.map_or(data.e_co2, |estimate| estimate.round() as u16),
+            ..data
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::reading;
+    use super::*;
+
+    fn e_co2s<F: Filter>(filter: &mut F, values: &[u16]) -> Vec<u16> {
+        values.iter().map(|e_co2| filter.filter(reading(*e_co2, 0)).e_co2).collect()
+    }
+
+    #[test]
+    fn moving_average() {
+        let mut filter = MovingAverage::new(3);
+
+        assert_eq!(e_co2s(&mut filter, &[400, 500, 600, 700]), vec![400, 450, 500, 600]);
+        assert_eq!(e_co2s(&mut MovingAverage::new(0), &[400, 500]), vec![400, 500]);
+    }
+
+    #[test]
+    fn ewma() {
+        let mut filter = Ewma::new(0.5);
+
+        assert_eq!(e_co2s(&mut filter, &[400, 800, 800, 400]), vec![400, 600, 700, 550]);
+        // tVOC is smoothed the same way, the rest of the reading is kept
+        let data = filter.filter(reading(400, 60));
+        assert_eq!((data.t_voc, data.since_start), (30, reading(400, 60).since_start));
+    }
+
+    #[test]
+    fn ewma_clamps_alpha() {
+        assert_eq!(e_co2s(&mut Ewma::new(f32::NAN), &[400, 800]), vec![400, 800]);
+        assert_eq!(e_co2s(&mut Ewma::new(2.0), &[400, 800]), vec![400, 800]);
+        // Close to 0 keeps the first sample
+        assert_eq!(e_co2s(&mut Ewma::new(-1.0), &[400, 800]), vec![400, 400]);
+    }
+
+    #[test]
+    fn kalman_starts_with_the_first_sample() {
+        let mut filter = Kalman::new(25.0, 2500.0, 900.0);
+        assert_eq!(filter.estimate(), None);
+
+        assert_eq!(e_co2s(&mut filter, &[800]), vec![800]);
+        assert_eq!(filter.estimate(), Some(800.0));
+        // Only eCO2 is filtered
+        assert_eq!(filter.filter(reading(800, 60)).t_voc, 60);
+    }
+
+    #[test]
+    fn kalman_follows_the_reference() {
+        let mut filter = Kalman::new(25.0, 2500.0, 100.0);
+        e_co2s(&mut filter, &[800]);
+
+        for _ in 0..10 {
+            filter.update_reference(600);
+        }
+        let estimate = filter.estimate().unwrap();
+        assert!((600.0..610.0).contains(&estimate), "{}", estimate);
+
+        // A single noisy sample of the CCS811 barely moves the estimate
+        let e_co2 = filter.filter(reading(1000, 0)).e_co2;
+        assert!((600..650).contains(&e_co2), "{}", e_co2);
+    }
+}
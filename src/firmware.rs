@@ -0,0 +1,380 @@
+use std::fmt;
+use std::str::FromStr;
+#[cfg(feature = "flash")]
+use std::fs::File;
+#[cfg(feature = "flash")]
+use std::io::{self, Read};
+#[cfg(feature = "flash")]
+use std::path::Path;
+#[cfg(feature = "flash")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "flash")]
+use crate::error::Ccs811Error;
+
+/// Version of the application firmware as stored in the FW_APP_VERSION register. Versions are ordered,
+/// e.g. `version >= FirmwareVersion { major: 2, minor: 0, trivial: 0 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub trivial: u8
+}
+
+impl FirmwareVersion {
+
+    /// The first byte holds major and minor version in its nibbles, the second one the trivial version
+    pub fn from_bytes(bytes: [u8; 2]) -> FirmwareVersion {
+        FirmwareVersion {
+            major: bytes[0] >> 4,
+            minor: bytes[0] & 0x0F,
+            trivial: bytes[1]
+        }
+    }
+
+    /// The register value, inverse of `from_bytes()`
+    pub fn to_bytes(&self) -> [u8; 2] {
+        [(self.major << 4) | (self.minor & 0x0F), self.trivial]
+    }
+
+    /// Extracts the version from the name of the official binaries like `CCS811_FW_App_v2-0-1.bin`
+    pub fn from_file_name(name: &str) -> Option<FirmwareVersion> {
+        let start = name.rfind("_v")?.checked_add(2)?;
+        let end = name.rfind(".bin").unwrap_or(name.len());
+        let mut parts = name.get(start..end)?.split('-').map(|part| part.parse::<u8>());
+
+        let version = FirmwareVersion {
+            major: parts.next()?.ok()?,
+            minor: parts.next()?.ok()?,
+            trivial: parts.next()?.ok()?
+        };
+
+        if parts.next().is_some() || version.major > 0x0F || version.minor > 0x0F {
+            return None;
+        }
+
+        Some(version)
+    }
+}
+
+impl From<[u8; 2]> for FirmwareVersion {
+    fn from(bytes: [u8; 2]) -> Self {
+        FirmwareVersion::from_bytes(bytes)
+    }
+}
+
+impl From<FirmwareVersion> for [u8; 2] {
+    fn from(version: FirmwareVersion) -> [u8; 2] {
+        version.to_bytes()
+    }
+}
+
+/// Rules of a firmware version for reading and writing the baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct BaselineQuirks {
+    /// The baseline is still settling during the warm-up, so reading it doesn't return a meaningful value
+    pub(crate) read_after_warm_up: bool,
+    /// The conditioning during the warm-up overwrites a baseline written earlier
+    pub(crate) write_after_warm_up: bool,
+    /// Changing the mode clears a written baseline
+    pub(crate) cleared_on_mode_change: bool
+}
+
+impl FirmwareVersion {
+
+    pub(crate) fn baseline_quirks(&self) -> BaselineQuirks {
+        match (self.major, self.minor) {
+            (1, _) => BaselineQuirks { write_after_warm_up: true, ..BaselineQuirks::default() },
+            (2, 0) => BaselineQuirks { read_after_warm_up: true, write_after_warm_up: true, cleared_on_mode_change: true },
+            _ => BaselineQuirks::default()
+        }
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.trivial)
+    }
+}
+
+/// Parses the version as displayed, e.g. `2.0.1`
+impl FromStr for FirmwareVersion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid firmware version {}, expected e.g. 2.0.1", value);
+        let mut parts = value.trim().split('.').map(|part| part.parse::<u8>().map_err(|_| invalid()));
+
+        let version = FirmwareVersion {
+            major: parts.next().ok_or_else(invalid)??,
+            minor: parts.next().ok_or_else(invalid)??,
+            trivial: parts.next().ok_or_else(invalid)??
+        };
+
+        match parts.next().is_some() || version.major > 0x0F || version.minor > 0x0F {
+            true => Err(invalid()),
+            false => Ok(version)
+        }
+    }
+}
+
+/// A firmware binary for the CCS811 which is fully loaded into memory, so it can be verified before
+/// the application on the chip gets erased.
+#[cfg(feature = "flash")]
+pub struct FirmwareImage {
+    data: Vec<u8>,
+    version: Option<FirmwareVersion>
+}
+
+#[cfg(feature = "flash")]
+impl FirmwareImage {
+
+    /// SHA-256 hashes of the official application binaries by the version they report, used by
+    /// `verify_known()` when no hash of the download is at hand. The hashes of the 2.0.0 and 2.0.1
+    /// binaries still have to be added, until then images have to be verified with an explicit hash.
+    pub const KNOWN_HASHES: &'static [(FirmwareVersion, &'static str)] = &[];
+
+    pub fn new(data: Vec<u8>) -> FirmwareImage {
+        FirmwareImage { data, version: None }
+    }
+
+    /// Loads the firmware binary from the file. The version of the firmware is taken from the
+    /// file name if it follows the naming of the official binaries like `CCS811_FW_App_v2-0-1.bin`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let image = ccs811::firmware::FirmwareImage::open("./CCS811_FW_App_v2-0-1.bin")
+    ///     .expect("Could not load firmware");
+    ///
+    /// println!("Firmware version {:?}", image.version());
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FirmwareImage> {
+        let version = path.as_ref().file_name()
+            .and_then(|name| name.to_str())
+            .and_then(FirmwareVersion::from_file_name);
+        let image = FirmwareImage::from_reader(File::open(path)?)?;
+
+        Ok(FirmwareImage { version, ..image })
+    }
+
+    /// Sets the version the chip should report after flashing this image
+    pub fn with_version(self, version: FirmwareVersion) -> FirmwareImage {
+        FirmwareImage { version: Some(version), ..self }
+    }
+
+    pub fn version(&self) -> Option<FirmwareVersion> {
+        self.version
+    }
+
+    /// Loads the firmware from any reader, e.g. the binary file downloaded from ams.com
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    ///
+    /// let file = File::open("./CCS811_FW_App_v2-0-1.bin").expect("No firmware found");
+    /// let image = ccs811::firmware::FirmwareImage::from_reader(file)
+    ///     .expect("Could not load firmware");
+    /// ```
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<FirmwareImage> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+
+        Ok(FirmwareImage::new(data))
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// SHA-256 hash of the image as lowercase hex string like printed by `sha256sum`
+    pub fn sha256(&self) -> String {
+        Sha256::digest(&self.data).iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Checks the image against the expected SHA-256 hash (hex, case insensitive). This detects
+    /// corrupted or truncated downloads before anything is written to the chip.
+    pub fn verify(&self, expected_sha256: &str) -> Result<(), Ccs811Error> {
+        if self.is_empty() {
+            return Err(Ccs811Error::Flash("Firmware image is empty".to_string()));
+        }
+
+        let actual = self.sha256();
+        if !actual.eq_ignore_ascii_case(expected_sha256.trim()) {
+            return Err(Ccs811Error::Flash(format!("Firmware checksum is not {} but {}", expected_sha256.trim(), actual)));
+        }
+
+        Ok(())
+    }
+
+    /// SHA-256 hash of the official binary of the version, if it's one of the `KNOWN_HASHES`
+    pub fn known_sha256(version: FirmwareVersion) -> Option<&'static str> {
+        find_sha256(FirmwareImage::KNOWN_HASHES, version)
+    }
+
+    /// Checks the image against the known hash of the official binary of its version. Fails if the
+    /// version of the image or its hash isn't known.
+    pub fn verify_known(&self) -> Result<(), Ccs811Error> {
+        self.verify_in(FirmwareImage::KNOWN_HASHES)
+    }
+
+    fn verify_in(&self, known_hashes: &[(FirmwareVersion, &str)]) -> Result<(), Ccs811Error> {
+        let version = self.version
+            .ok_or_else(|| Ccs811Error::Flash("Firmware version unknown, give the SHA-256 hash of the image".to_string()))?;
+        let sha256 = find_sha256(known_hashes, version)
+            .ok_or_else(|| Ccs811Error::Flash(format!("No known SHA-256 hash of firmware {}, give the one of the image", version)))?;
+
+        self.verify(sha256)
+    }
+}
+
+#[cfg(feature = "flash")]
+fn find_sha256<'a>(known_hashes: &[(FirmwareVersion, &'a str)], version: FirmwareVersion) -> Option<&'a str> {
+    known_hashes.iter()
+        .find(|(known, _)| *known == version)
+        .map(|(_, sha256)| *sha256)
+}
+
+#[cfg(feature = "flash")]
+impl From<Vec<u8>> for FirmwareImage {
+    fn from(data: Vec<u8>) -> FirmwareImage {
+        FirmwareImage::new(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use embedded_hal::blocking::i2c::Write;
+    use crate::chip::{CCS811, NoPin};
+    use crate::constants::{Ccs811Mode, CCS811_BASELINE, CCS811_SLAVEADDR_0, CCS811_WARM_UP};
+    use crate::codec::baseline_to_bytes;
+    use crate::error::Ccs811Error;
+    use crate::sim::SimCcs811;
+    use super::*;
+
+    fn start(application_version: [u8; 2]) -> CCS811<SimCcs811> {
+        let mut ccs811 = CCS811::new(SimCcs811::new().with_application_version(application_version), None::<NoPin>)
+            .with_discarded_samples(0);
+        ccs811.begin().unwrap();
+        ccs811.start(Ccs811Mode::Sec1).unwrap();
+
+        ccs811
+    }
+
+    fn end_warm_up(ccs811: &mut CCS811<SimCcs811>) {
+        ccs811.started = Instant::now().checked_sub(CCS811_WARM_UP);
+        ccs811.i2c.elapse(Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "flash")]
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[cfg(feature = "flash")]
+    fn image(data: &[u8], version: Option<FirmwareVersion>) -> FirmwareImage {
+        FirmwareImage { data: data.to_vec(), version }
+    }
+
+    #[cfg(feature = "flash")]
+    #[test]
+    fn matching_image_verifies_against_the_known_hash() {
+        let version = FirmwareVersion { major: 2, minor: 0, trivial: 1 };
+        let known_hashes = [(FirmwareVersion { major: 2, minor: 0, trivial: 0 }, "0000"), (version, ABC_SHA256)];
+
+        assert!(image(b"abc", Some(version)).verify_in(&known_hashes).is_ok());
+    }
+
+    #[cfg(feature = "flash")]
+    #[test]
+    fn tampered_image_fails_against_the_known_hash() {
+        let version = FirmwareVersion { major: 2, minor: 0, trivial: 1 };
+        let known_hashes = [(version, ABC_SHA256)];
+
+        assert!(matches!(image(b"abd", Some(version)).verify_in(&known_hashes), Err(Ccs811Error::Flash(_))));
+    }
+
+    #[cfg(feature = "flash")]
+    #[test]
+    fn image_of_unknown_version_fails_to_verify() {
+        let known_hashes = [(FirmwareVersion { major: 2, minor: 0, trivial: 1 }, ABC_SHA256)];
+
+        assert!(matches!(image(b"abc", None).verify_in(&known_hashes), Err(Ccs811Error::Flash(_))));
+        let other = FirmwareVersion { major: 2, minor: 0, trivial: 0 };
+        assert!(matches!(image(b"abc", Some(other)).verify_in(&known_hashes), Err(Ccs811Error::Flash(_))));
+    }
+
+    #[test]
+    fn baseline_quirks() {
+        let quirks = |major, minor| FirmwareVersion { major, minor, trivial: 0 }.baseline_quirks();
+
+        assert_eq!(quirks(1, 1), BaselineQuirks { write_after_warm_up: true, ..BaselineQuirks::default() });
+        assert_eq!(quirks(2, 0), BaselineQuirks { read_after_warm_up: true, write_after_warm_up: true, cleared_on_mode_change: true });
+        assert_eq!(quirks(2, 1), BaselineQuirks::default());
+    }
+
+    #[test]
+    fn set_baseline_is_rejected_during_the_warm_up() {
+        let mut ccs811 = start([0x20, 0x00]);
+
+        assert!(matches!(ccs811.set_baseline(0x1234), Err(Ccs811Error::BaselineNotReady { .. })));
+        assert!(matches!(ccs811.get_baseline(), Err(Ccs811Error::BaselineNotReady { .. })));
+        end_warm_up(&mut ccs811);
+        ccs811.read().unwrap();
+        assert_eq!(ccs811.i2c.baseline(), [0, 0]);
+
+        ccs811.set_baseline(0x1234).unwrap();
+        assert_eq!(ccs811.i2c.baseline(), baseline_to_bytes(0x1234));
+    }
+
+    #[test]
+    fn set_baseline_is_written_during_the_warm_up_of_2_1() {
+        let mut ccs811 = start([0x21, 0x00]);
+
+        ccs811.set_baseline(0x1234).unwrap();
+        assert_eq!(ccs811.i2c.baseline(), baseline_to_bytes(0x1234));
+        assert_eq!(ccs811.get_baseline().unwrap(), 0x1234);
+    }
+
+    #[test]
+    fn quick_start_writes_the_baseline_after_the_warm_up() {
+        let mut ccs811 = CCS811::new(SimCcs811::new().with_application_version([0x11, 0x00]), None::<NoPin>)
+            .with_discarded_samples(0);
+
+        ccs811.quick_start_with_baseline(Ccs811Mode::Sec1, 0x1234).unwrap();
+        assert_eq!(ccs811.i2c.baseline(), [0, 0]);
+
+        end_warm_up(&mut ccs811);
+        ccs811.read().unwrap();
+        assert_eq!(ccs811.i2c.baseline(), baseline_to_bytes(0x1234));
+    }
+
+    #[test]
+    fn baseline_is_written_again_after_a_mode_change_of_2_0() {
+        let mut ccs811 = start([0x20, 0x00]);
+        end_warm_up(&mut ccs811);
+        ccs811.set_baseline(0x1234).unwrap();
+
+        ccs811.start(Ccs811Mode::Sec10).unwrap();
+        // Like the firmware clearing it with the mode change
+        ccs811.i2c.write(CCS811_SLAVEADDR_0, &[CCS811_BASELINE, 0, 0]).unwrap();
+        ccs811.i2c.elapse(Duration::from_secs(10));
+        ccs811.read().unwrap();
+        assert_eq!(ccs811.i2c.baseline(), [0, 0]);
+
+        end_warm_up(&mut ccs811);
+        ccs811.i2c.elapse(Duration::from_secs(10));
+        ccs811.read().unwrap();
+        assert_eq!(ccs811.i2c.baseline(), baseline_to_bytes(0x1234));
+    }
+}
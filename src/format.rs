@@ -0,0 +1,256 @@
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::chip::Ccs811Data;
+
+/// Text formats for readings, shared by the CLI and the sinks so piped output looks the same everywhere
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use std::time::SystemTime;
+/// use ccs811::format::Format;
+///
+/// let format: Format = "csv".parse().unwrap();
+/// if let Some(header) = format.header() {
+///   println!("{}", header);
+/// }
+/// println!("{}", format.format(&ccs811.read().unwrap(), SystemTime::now()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `{"e_co2":812,"t_voc":63,"warming_up":false,"timestamp":1700000000}` with seconds since the epoch
+    Json,
+    /// `1700000000,812,63,false` with seconds since the epoch, see `header()`
+    Csv,
+    /// InfluxDB line protocol `ccs811 e_co2=812i,t_voc=63i,warming_up=false 1700000000000000000` with
+    /// nanoseconds
+    Influx,
+    /// `eCO2 812 ppm, tVOC 63 ppb (Good)` for humans
+    Plain
+}
+
+/// A reading parsed from a line written in one of the formats, see `Format::parse()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoggedReading {
+    pub timestamp: SystemTime,
+    pub e_co2: u16,
+    pub t_voc: u16,
+    pub warming_up: bool
+}
+
+impl LoggedReading {
+
+    /// Milliseconds since the epoch, the unit of Arrow and Polars timestamps
+    pub fn timestamp_millis(&self) -> i64 {
+        i64::try_from(since_epoch(self.timestamp).as_millis()).unwrap_or(i64::MAX)
+    }
+}
+
+fn since_epoch(timestamp: SystemTime) -> std::time::Duration {
+    timestamp.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+impl Format {
+
+    /// Line to print before the first reading, only CSV has one
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            Format::Csv => Some("timestamp,e_co2,t_voc,warming_up"),
+            _ => None
+        }
+    }
+
+    /// Formats the reading taken at `timestamp` as a single line without line break
+    pub fn format(&self, data: &Ccs811Data, timestamp: SystemTime) -> String {
+        let since_epoch = since_epoch(timestamp);
+
+        match self {
+            Format::Json => format!(
+                "{{\"e_co2\":{},\"t_voc\":{},\"warming_up\":{},\"timestamp\":{}}}",
+                data.e_co2, data.t_voc, data.is_warming_up(), since_epoch.as_secs()
+            ),
+            Format::Csv => format!("{},{},{},{}", since_epoch.as_secs(), data.e_co2, data.t_voc, data.is_warming_up()),
+            Format::Influx => format!(
+                "ccs811 e_co2={}i,t_voc={}i,warming_up={} {}",
+                data.e_co2, data.t_voc, data.is_warming_up(), since_epoch.as_nanos()
+            ),
+            Format::Plain => data.to_string()
+        }
+    }
+
+    /// Parses a line written by `format()` back, `None` if it isn't one or for `Plain` lacking the
+    /// timestamp. Timestamps keep the precision of the format, seconds for JSON and CSV.
+    pub fn parse(&self, line: &str) -> Option<LoggedReading> {
+        let line = line.trim();
+
+        match self {
+            Format::Json => {
+                let fields = line.strip_prefix('{')?.strip_suffix('}')?.split(',')
+                    .map(|field| field.split_once(':').map(|(key, value)| (key.trim().trim_matches('"'), value.trim())));
+                let (mut e_co2, mut t_voc, mut warming_up, mut timestamp) = (None, None, None, None);
+                for field in fields {
+                    match field? {
+                        ("e_co2", value) => e_co2 = Some(value.parse().ok()?),
+                        ("t_voc", value) => t_voc = Some(value.parse().ok()?),
+                        ("warming_up", value) => warming_up = Some(value.parse().ok()?),
+                        ("timestamp", value) => timestamp = Some(from_epoch(Duration::from_secs(value.parse().ok()?))?),
+                        // Fields added by later versions
+                        _ => ()
+                    }
+                }
+
+                Some(LoggedReading { timestamp: timestamp?, e_co2: e_co2?, t_voc: t_voc?, warming_up: warming_up? })
+            },
+            Format::Csv => match line.split(',').collect::<Vec<_>>().as_slice() {
+                [timestamp, e_co2, t_voc, warming_up] => Some(LoggedReading {
+                    timestamp: from_epoch(Duration::from_secs(timestamp.parse().ok()?))?,
+                    e_co2: e_co2.parse().ok()?,
+                    t_voc: t_voc.parse().ok()?,
+                    warming_up: warming_up.parse().ok()?
+                }),
+                _ => None
+            },
+            Format::Influx => {
+                let (fields, timestamp) = line.strip_prefix("ccs811 ")?.split_once(' ')?;
+                let field = |name: &str| fields.split(',')
+                    .find_map(|field| field.strip_prefix(name)?.strip_prefix('='));
+
+                Some(LoggedReading {
+                    timestamp: from_epoch(Duration::from_nanos(timestamp.parse().ok()?))?,
+                    e_co2: field("e_co2")?.strip_suffix('i')?.parse().ok()?,
+                    t_voc: field("t_voc")?.strip_suffix('i')?.parse().ok()?,
+                    warming_up: field("warming_up")?.parse().ok()?
+                })
+            },
+            Format::Plain => None
+        }
+    }
+
+    /// Guesses the format of a log from its first line, which is the header for CSV
+    pub fn detect(first_line: &str) -> Option<Format> {
+        let line = first_line.trim();
+        if Format::Csv.header() == Some(line) {
+            return Some(Format::Csv);
+        }
+
+        [Format::Json, Format::Csv, Format::Influx].iter().copied().find(|format| format.parse(line).is_some())
+    }
+}
+
+fn from_epoch(since_epoch: Duration) -> Option<SystemTime> {
+    UNIX_EPOCH.checked_add(since_epoch)
+}
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Unicode sparkline like `▁▂▄▇█▆` of the values, scaled between their minimum and maximum, e.g. of
+/// the eCO2 in the `history()`. Equal values are drawn as the lowest bar.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::format::sparkline;
+///
+/// println!("{}", sparkline(ccs811.history().iter().map(|data| data.e_co2)));
+/// ```
+pub fn sparkline(values: impl IntoIterator<Item = u16>) -> String {
+    let values: Vec<u16> = values.into_iter().collect();
+    let min = values.iter().copied().min().unwrap_or_default();
+    let range = u32::from(values.iter().copied().max().unwrap_or_default().saturating_sub(min));
+    let top = u32::try_from(SPARKS.len()).unwrap_or(1).saturating_sub(1);
+
+    values.iter()
+        .map(|&value| {
+            let level = u32::from(value.saturating_sub(min)).saturating_mul(top).checked_div(range).unwrap_or(0);
+            SPARKS.get(usize::try_from(level).unwrap_or(0)).copied().unwrap_or('▁')
+        })
+        .collect()
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "influx" => Ok(Format::Influx),
+            "plain" => Ok(Format::Plain),
+            _ => Err(format!("Unknown format {}, use json, csv, influx or plain", value))
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Influx => "influx",
+            Format::Plain => "plain"
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::reading;
+    use super::*;
+
+    fn timestamp() -> SystemTime {
+        from_epoch(Duration::from_secs(1_700_000_000)).unwrap()
+    }
+
+    #[test]
+    fn formats_readings() {
+        let data = reading(812, 63);
+
+        assert_eq!(Format::Json.format(&data, timestamp()), r#"{"e_co2":812,"t_voc":63,"warming_up":false,"timestamp":1700000000}"#);
+        assert_eq!(Format::Csv.format(&data, timestamp()), "1700000000,812,63,false");
+        assert_eq!(Format::Influx.format(&data, timestamp()), "ccs811 e_co2=812i,t_voc=63i,warming_up=false 1700000000000000000");
+        assert_eq!(Format::Plain.format(&data, timestamp()), data.to_string());
+    }
+
+    #[test]
+    fn parses_formatted_readings() {
+        let data = reading(812, 63);
+        let logged = LoggedReading { timestamp: timestamp(), e_co2: 812, t_voc: 63, warming_up: false };
+
+        for format in [Format::Json, Format::Csv, Format::Influx] {
+            assert_eq!(format.parse(&format.format(&data, timestamp())), Some(logged), "{}", format);
+        }
+        assert_eq!(Format::Plain.parse(&Format::Plain.format(&data, timestamp())), None);
+        // Unknown JSON fields are skipped, missing ones fail
+        assert_eq!(Format::Json.parse(r#"{"e_co2":812,"t_voc":63,"warming_up":false,"timestamp":1700000000,"room":"kitchen"}"#), Some(logged));
+        assert_eq!(Format::Json.parse(r#"{"e_co2":812,"t_voc":63,"timestamp":1700000000}"#), None);
+        assert_eq!(Format::Csv.parse("1700000000,812,63"), None);
+    }
+
+    #[test]
+    fn detects_the_format() {
+        assert_eq!(Format::detect("timestamp,e_co2,t_voc,warming_up"), Some(Format::Csv));
+        assert_eq!(Format::detect("1700000000,812,63,false"), Some(Format::Csv));
+        assert_eq!(Format::detect(r#"{"e_co2":812,"t_voc":63,"warming_up":false,"timestamp":1700000000}"#), Some(Format::Json));
+        assert_eq!(Format::detect("ccs811 e_co2=812i,t_voc=63i,warming_up=false 1700000000000000000"), Some(Format::Influx));
+        assert_eq!(Format::detect("eCO2 812 ppm"), None);
+    }
+
+    #[test]
+    fn names() {
+        for format in [Format::Json, Format::Csv, Format::Influx, Format::Plain] {
+            assert_eq!(format.to_string().parse::<Format>(), Ok(format));
+        }
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn sparklines() {
+        assert_eq!(sparkline([400, 500, 700, 1100]), "▁▂▄█");
+        assert_eq!(sparkline([600, 600]), "▁▁");
+        assert_eq!(sparkline([]), "");
+    }
+}
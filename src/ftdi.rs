@@ -0,0 +1,53 @@
+use ftdi::Device;
+use ftdi_embedded_hal::{FtHal, I2c, OutputPin};
+use std::io;
+use crate::chip::CCS811;
+use crate::error::{i2c_error, pin_error, Ccs811Error};
+
+/// USB vendor and product id of the FT232H
+const FT232H_VID: u16 = 0x0403;
+const FT232H_PID: u16 = 0x6014;
+
+/// The chip connected to a FT232H breakout
+pub type FtdiCCS811 = CCS811<I2c<Device>, OutputPin<Device>>;
+
+/// Opens the first FT232H found on USB and creates the chip on its I2C pins. Wire SCL to AD0 and SDA
+/// to AD1 and AD2 which have to be connected with each other. Optionally one of the pins AD3 to AD7
+/// can be used as wake pin by passing its number.
+/// Be aware that the FT232H doesn't support clock stretching which the CCS811 relies on. If reads
+/// fail try a lower frequency like 10kHz.
+///
+/// # Examples
+///
+/// ```
+/// let mut ccs811 = ccs811::ftdi::open(100_000, Some(3))
+///     .expect("Could not open FT232H");
+///
+/// ccs811.begin().expect("Could not init the chip");
+/// ```
+pub fn open(frequency: u32, wake_pin: Option<u8>) -> Result<FtdiCCS811, Ccs811Error> {
+    let device = ftdi::find_by_vid_pid(FT232H_VID, FT232H_PID)
+        .interface(ftdi::Interface::A)
+        .open()
+        .map_err(i2c_error("open FT232H"))?;
+
+    let hal = FtHal::init_freq(device, frequency)
+        .map_err(i2c_error("init FT232H"))?;
+    let i2c = hal.i2c()
+        .map_err(i2c_error("init I2C of FT232H"))?;
+
+    let wake = match wake_pin {
+        None => None,
+        Some(3) => Some(hal.ad3()),
+        Some(4) => Some(hal.ad4()),
+        Some(5) => Some(hal.ad5()),
+        Some(6) => Some(hal.ad6()),
+        Some(7) => Some(hal.ad7()),
+        Some(pin) => return Err(pin_error("attach to wake pin")(io::Error::new(
+            io::ErrorKind::InvalidInput, format!("AD{} can not be used as wake pin, use AD3 to AD7", pin))))
+    };
+    let wake = wake.transpose()
+        .map_err(pin_error("attach to wake pin"))?;
+
+    Ok(CCS811::new(i2c, wake))
+}
@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+use crate::chip::CCS811;
+
+/// Number of reads the error rate of `health()` is calculated over
+pub const HEALTH_WINDOW: usize = 20;
+
+// Reads needed before a high error rate fails the health, so a single error after start doesn't
+const HEALTH_MIN_READS: usize = 5;
+
+/// What's wrong with the chip, see `Health`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HealthIssue {
+    /// `errors` of the last `reads` reads failed
+    ErrorRate { errors: usize, reads: usize },
+    /// No successful read for this long although the chip samples
+    NoReading(Duration),
+    /// The chip was reset this often since the health was cleared, which hints at flaky power or wiring
+    Resets(u32),
+    /// The chip got lost and auto recovery couldn't re-initialize it, see `with_auto_recovery()`
+    Lost
+}
+
+impl Display for HealthIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HealthIssue::ErrorRate { errors, reads } => write!(f, "{} of the last {} reads failed", errors, reads),
+            HealthIssue::NoReading(elapsed) => write!(f, "no reading for {}s", elapsed.as_secs()),
+            HealthIssue::Resets(resets) => write!(f, "{} resets", resets),
+            HealthIssue::Lost => write!(f, "chip lost")
+        }
+    }
+}
+
+/// Overall state of the chip, e.g. for the health endpoint of a service. Prints like `healthy` or
+/// `degraded: 3 of the last 20 reads failed`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Health {
+    Healthy,
+    /// Readings still arrive, but something needs attention
+    Degraded(HealthIssue),
+    /// The chip doesn't deliver readings anymore. Stays failed until `clear_health()`.
+    Failed(HealthIssue)
+}
+
+impl Health {
+
+    pub fn is_healthy(&self) -> bool {
+        *self == Health::Healthy
+    }
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Health::Healthy => write!(f, "healthy"),
+            Health::Degraded(issue) => write!(f, "degraded: {}", issue),
+            Health::Failed(issue) => write!(f, "failed: {}", issue)
+        }
+    }
+}
+
+/// When `health()` reports degraded or failed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthThresholds {
+    /// Share of failed reads within the last `HEALTH_WINDOW` reads
+    pub degraded_error_rate: f32,
+    pub failed_error_rate: f32,
+    /// Sampling periods of the mode without a successful read. If the reads are further apart than the
+    /// period, the interval between them counts as period instead.
+    pub degraded_periods: u32,
+    pub failed_periods: u32,
+    /// Resets since the health was cleared, the one of `begin()` included
+    pub degraded_resets: u32
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            degraded_error_rate: 0.1,
+            failed_error_rate: 0.5,
+            degraded_periods: 3,
+            failed_periods: 10,
+            degraded_resets: 3
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct HealthTracker {
+    thresholds: HealthThresholds,
+    // Whether each of the last reads succeeded
+    reads: VecDeque<bool>,
+    last_read: Option<Instant>,
+    // Time between the last two reads, so a caller reading less often than the chip samples isn't stale
+    read_interval: Duration,
+    last_success: Option<Instant>,
+    resets_at_clear: u32,
+    latched: Option<HealthIssue>
+}
+
+impl HealthTracker {
+
+    pub(crate) fn record(&mut self, success: bool) {
+        if self.reads.len() == HEALTH_WINDOW {
+            self.reads.pop_front();
+        }
+        self.reads.push_back(success);
+
+        let now = Instant::now();
+        if let Some(last_read) = self.last_read {
+            self.read_interval = now.saturating_duration_since(last_read);
+        }
+        self.last_read = Some(now);
+        if success {
+            self.last_success = Some(now);
+        }
+    }
+
+    fn error_rate(&self) -> Option<HealthIssue> {
+        let errors = self.reads.iter().filter(|success| !**success).count();
+        let reads = self.reads.len();
+        let rate = errors as f32 / reads.max(1) as f32;
+
+        match rate >= self.thresholds.degraded_error_rate && errors > 0 {
+            true => Some(HealthIssue::ErrorRate { errors, reads }),
+            false => None
+        }
+    }
+
+    fn is_failed_rate(&self, errors: usize, reads: usize) -> bool {
+        reads >= HEALTH_MIN_READS && errors as f32 / reads as f32 >= self.thresholds.failed_error_rate
+    }
+}
+
+impl<I2C, WAKE, RESET> CCS811<I2C, WAKE, RESET> {
+
+    /// Thresholds of `health()`, see `HealthThresholds::default()` for the defaults
+    pub fn with_health_thresholds(mut self, thresholds: HealthThresholds) -> Self {
+        self.health.thresholds = thresholds;
+        self
+    }
+
+    /// Overall state of the chip judged by the error rate of the recent reads, the time since the last
+    /// successful read and the resets. A failure is latched, so it's still reported after the chip
+    /// recovered, until it's acknowledged with `clear_health()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// use ccs811::health::Health;
+    ///
+    /// match ccs811.health() {
+    ///   Health::Healthy => (),
+    ///   Health::Degraded(issue) => println!("Check the sensor: {}", issue),
+    ///   Health::Failed(issue) => println!("Sensor failed: {}", issue)
+    /// }
+    /// ```
+    pub fn health(&mut self) -> Health {
+        if let Some(issue) = self.health.latched {
+            return Health::Failed(issue);
+        }
+
+        let failed = self.failed_issue();
+        if let Some(issue) = failed {
+            self.health.latched = Some(issue);
+            return Health::Failed(issue);
+        }
+
+        self.health.error_rate()
+            .or_else(|| self.stale_periods(self.health.thresholds.degraded_periods))
+            .or_else(|| self.resets_issue())
+            .map_or(Health::Healthy, Health::Degraded)
+    }
+
+    /// Forgets the latched failure, the recent reads and the resets so far
+    pub fn clear_health(&mut self) {
+        self.health = HealthTracker {
+            thresholds: self.health.thresholds,
+            resets_at_clear: self.metrics().resets,
+            ..HealthTracker::default()
+        };
+    }
+
+    fn failed_issue(&self) -> Option<HealthIssue> {
+        if self.is_degraded() {
+            return Some(HealthIssue::Lost);
+        }
+
+        match self.health.error_rate() {
+            Some(HealthIssue::ErrorRate { errors, reads }) if self.health.is_failed_rate(errors, reads) => {
+                Some(HealthIssue::ErrorRate { errors, reads })
+            },
+            _ => self.stale_periods(self.health.thresholds.failed_periods)
+        }
+    }
+
+    /// Time without a successful read if it exceeds the periods of the mode, or as many read intervals
+    /// of the caller if it reads less often than the chip samples
+    fn stale_periods(&self, periods: u32) -> Option<HealthIssue> {
+        let period = self.mode().period()?.max(self.health.read_interval);
+        let since_start = self.since_start()?;
+        let elapsed = self.health.last_success
+            .map_or(since_start, |last_success| last_success.elapsed().min(since_start));
+
+        match elapsed > period.saturating_mul(periods) {
+            true => Some(HealthIssue::NoReading(elapsed)),
+            false => None
+        }
+    }
+
+    fn resets_issue(&self) -> Option<HealthIssue> {
+        let resets = self.metrics().resets.saturating_sub(self.health.resets_at_clear);
+
+        match resets > self.health.thresholds.degraded_resets {
+            true => Some(HealthIssue::Resets(resets)),
+            false => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip::{CCS811, NoPin};
+    use crate::constants::Ccs811Mode;
+    use crate::sim::SimCcs811;
+    use super::*;
+
+    fn begin() -> CCS811<SimCcs811> {
+        let mut ccs811 = CCS811::new(SimCcs811::new(), None::<NoPin>);
+        ccs811.begin().unwrap();
+
+        ccs811
+    }
+
+    fn ago(seconds: u64) -> Instant {
+        Instant::now().checked_sub(Duration::from_secs(seconds)).unwrap()
+    }
+
+    fn record(ccs811: &mut CCS811<SimCcs811>, successes: usize, failures: usize) {
+        (0..successes).for_each(|_| ccs811.health.record(true));
+        (0..failures).for_each(|_| ccs811.health.record(false));
+    }
+
+    #[test]
+    fn error_rate() {
+        let mut ccs811 = begin();
+        record(&mut ccs811, 19, 1);
+        assert_eq!(ccs811.health(), Health::Healthy);
+
+        record(&mut ccs811, 0, 1);
+        assert_eq!(ccs811.health(), Health::Degraded(HealthIssue::ErrorRate { errors: 2, reads: 20 }));
+    }
+
+    #[test]
+    fn failed_error_rate_is_latched() {
+        let mut ccs811 = begin();
+        // Too few reads to fail
+        record(&mut ccs811, 0, 4);
+        assert_eq!(ccs811.health(), Health::Degraded(HealthIssue::ErrorRate { errors: 4, reads: 4 }));
+
+        record(&mut ccs811, 0, 1);
+        assert_eq!(ccs811.health(), Health::Failed(HealthIssue::ErrorRate { errors: 5, reads: 5 }));
+        record(&mut ccs811, 20, 0);
+        assert_eq!(ccs811.health(), Health::Failed(HealthIssue::ErrorRate { errors: 5, reads: 5 }));
+
+        ccs811.clear_health();
+        assert_eq!(ccs811.health(), Health::Healthy);
+    }
+
+    #[test]
+    fn stale_periods() {
+        let mut ccs811 = begin();
+        ccs811.start(Ccs811Mode::Sec1).unwrap();
+        ccs811.started = Some(ago(60));
+
+        ccs811.health.last_success = Some(ago(2));
+        assert_eq!(ccs811.health(), Health::Healthy);
+
+        ccs811.health.last_success = Some(ago(5));
+        assert!(matches!(ccs811.health(), Health::Degraded(HealthIssue::NoReading(_))));
+
+        ccs811.health.last_success = Some(ago(11));
+        assert!(matches!(ccs811.health(), Health::Failed(HealthIssue::NoReading(_))));
+    }
+
+    #[test]
+    fn stale_periods_follow_the_read_interval() {
+        let mut ccs811 = begin();
+        ccs811.start(Ccs811Mode::Sec1).unwrap();
+        ccs811.started = Some(ago(600));
+
+        // Reading every 60s in Sec1 mode isn't stale after 5s
+        ccs811.health.read_interval = Duration::from_secs(60);
+        ccs811.health.last_success = Some(ago(5));
+        assert_eq!(ccs811.health(), Health::Healthy);
+
+        // but after missing 3 of the reads
+        ccs811.health.last_success = Some(ago(200));
+        assert!(matches!(ccs811.health(), Health::Degraded(HealthIssue::NoReading(_))));
+    }
+
+    #[test]
+    fn resets() {
+        let mut ccs811 = begin();
+        for _ in 0..3 {
+            ccs811.begin().unwrap();
+        }
+        assert_eq!(ccs811.health(), Health::Degraded(HealthIssue::Resets(4)));
+
+        ccs811.clear_health();
+        assert_eq!(ccs811.health(), Health::Healthy);
+    }
+}
@@ -0,0 +1,140 @@
+use ::tiny_http::{Method, Request, Response, Server};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::health::Health;
+
+#[derive(Default)]
+struct State {
+    health: Option<Health>,
+    ready: bool,
+    // Until when the last report is current
+    expires: Option<Instant>
+}
+
+/// Liveness and readiness probes for containerized deployments, e.g. Kubernetes or k3s on the edge.
+/// The loop reading the chip reports its `health()` and readiness regularly and `serve()` answers
+/// `GET /healthz` and `GET /readyz` from the last report without touching the chip:
+///
+/// - `/healthz` is `200 OK` while the chip is healthy or degraded and `503 Service Unavailable` once
+///   it failed or the loop didn't report in time, as it hangs. A liveness probe on it restarts a
+///   wedged sensor.
+/// - `/readyz` is `200 OK` while `/healthz` is and the loop reported to be ready, e.g. as it delivers
+///   valid readings after the warm-up.
+///
+/// The body tells the state like `degraded: 3 of the last 20 reads failed`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::thread;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use std::time::Duration;
+/// use ccs811::healthz::HealthProbes;
+///
+/// let probes = HealthProbes::new();
+/// let _server = probes.serve("0.0.0.0:8080").expect("Could not serve probes");
+/// loop {
+///   let ready = ccs811.read().is_ok_and(|data| data.is_valid() && !data.is_warming_up());
+///   probes.report(ccs811.health(), ready, Duration::from_secs(30));
+///   thread::sleep(Duration::from_secs(10));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct HealthProbes {
+    state: Arc<Mutex<State>>
+}
+
+impl HealthProbes {
+
+    pub fn new() -> Self {
+        HealthProbes::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A panic while reporting can't leave the state inconsistent
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Reports the health of the chip and whether the service is ready. `/healthz` fails when no
+    /// report follows within `valid_for`, so give it a few sampling intervals.
+    pub fn report(&self, health: Health, ready: bool, valid_for: Duration) {
+        let mut state = self.lock();
+        state.health = Some(health);
+        state.ready = ready;
+        state.expires = Instant::now().checked_add(valid_for);
+    }
+
+    /// Answers the probes on the address, e.g. `0.0.0.0:8080`, until the server is dropped
+    pub fn serve<A: ToSocketAddrs>(&self, address: A) -> io::Result<ProbeServer> {
+        let server = Arc::new(Server::http(address).map_err(io::Error::other)?);
+
+        let listener = server.clone();
+        let probes = self.clone();
+        let thread = thread::Builder::new().name("ccs811-healthz".to_string()).spawn(move || {
+            // Ends once the server is unblocked on drop
+            for request in listener.incoming_requests() {
+                probes.answer(request);
+            }
+        })?;
+
+        Ok(ProbeServer { server, thread: Some(thread) })
+    }
+
+    /// Status code and body of `/healthz`
+    pub fn liveness(&self) -> (u16, String) {
+        let state = self.lock();
+        match (state.health, state.expires) {
+            (None, _) => (503, "starting".to_string()),
+            (Some(_), Some(expires)) if Instant::now() > expires => (503, "no recent health report, the sampling hangs".to_string()),
+            (Some(health @ Health::Failed(_)), _) => (503, health.to_string()),
+            (Some(health), _) => (200, health.to_string())
+        }
+    }
+
+    /// Status code and body of `/readyz`
+    pub fn readiness(&self) -> (u16, String) {
+        match self.liveness() {
+            (200, _) if self.lock().ready => (200, "ready".to_string()),
+            (200, _) => (503, "not ready".to_string()),
+            not_alive => not_alive
+        }
+    }
+
+    fn answer(&self, request: Request) {
+        let (status, message) = match (request.method(), request.url().trim_end_matches('/')) {
+            (Method::Get, "/healthz") | (Method::Head, "/healthz") => self.liveness(),
+            (Method::Get, "/readyz") | (Method::Head, "/readyz") => self.readiness(),
+            (_, "/healthz") | (_, "/readyz") => (405, "Use GET".to_string()),
+            (_, path) => (404, format!("Unknown endpoint {}, use /healthz or /readyz", path))
+        };
+
+        // The prober might have given up already
+        let _ = request.respond(Response::from_string(format!("{}\n", message)).with_status_code(status));
+    }
+}
+
+/// Thread answering the probes of a `HealthProbes`, stopped when dropped
+pub struct ProbeServer {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl ProbeServer {
+
+    /// Address the server listens on, e.g. to find the port when binding to port 0
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+}
+
+impl Drop for ProbeServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
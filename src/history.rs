@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+use std::slice;
+use crate::chip::Ccs811Data;
+
+/// Fixed capacity ring buffer of the last readings kept by the driver, see `CCS811::with_history()`.
+/// The memory is allocated once, so recording doesn't allocate.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+/// let mut ccs811 = ccs811::new(i2c, None).with_history(60);
+///
+/// // ... read for a while
+///
+/// let (older, newer) = ccs811.history().as_slices();
+/// let e_co2: Vec<u16> = ccs811.history().iter().map(|data| data.e_co2).collect();
+/// ```
+#[derive(Debug, Clone)]
+pub struct History {
+    capacity: usize,
+    readings: VecDeque<Ccs811Data>
+}
+
+impl History {
+
+    pub fn new(capacity: usize) -> Self {
+        History { capacity, readings: VecDeque::with_capacity(capacity) }
+    }
+
+    pub(crate) fn record(&mut self, data: Ccs811Data) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.readings.len() == self.capacity {
+            self.readings.pop_front();
+        }
+        self.readings.push_back(data);
+    }
+
+    /// The readings oldest first in two slices, as the ring buffer may wrap around. The second one
+    /// holds the newer readings and is empty if it doesn't wrap.
+    pub fn as_slices(&self) -> (&[Ccs811Data], &[Ccs811Data]) {
+        self.readings.as_slices()
+    }
+
+    /// The readings oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &Ccs811Data> {
+        self.into_iter()
+    }
+
+    pub fn latest(&self) -> Option<&Ccs811Data> {
+        self.readings.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+
+    /// Maximum number of readings kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.readings.clear();
+    }
+}
+
+impl<'a> IntoIterator for &'a History {
+    type Item = &'a Ccs811Data;
+    type IntoIter = std::iter::Chain<slice::Iter<'a, Ccs811Data>, slice::Iter<'a, Ccs811Data>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let (older, newer) = self.as_slices();
+        older.iter().chain(newer.iter())
+    }
+}
@@ -0,0 +1,85 @@
+use std::future::Future;
+use std::pin::Pin as FuturePin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use rppal::gpio::{InputPin, Pin, Trigger};
+use crate::error::{pin_error, Ccs811Error};
+
+struct Shared {
+    fired: bool,
+    waker: Option<Waker>
+}
+
+/// The nINT pin of the chip, which is pulled low by the chip as soon as a new sample is ready and
+/// stays low until the sample was read. The interrupt has to be enabled with `start_with_interrupt()`.
+/// Waiting for it doesn't consume any CPU and works with any async runtime.
+pub struct DataReadyInterrupt {
+    pin: InputPin,
+    shared: Arc<Mutex<Shared>>
+}
+
+impl DataReadyInterrupt {
+
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use rppal::gpio::Gpio;
+    /// let int_pin = Gpio::new().expect("Can not init gpio")
+    ///                   .get(22).expect("Could not attach to interrupt pin");
+    ///
+    /// let mut interrupt = ccs811::interrupt::DataReadyInterrupt::new(int_pin)
+    ///     .expect("Could not listen to interrupt pin");
+    /// ```
+    pub fn new(pin: Pin) -> Result<DataReadyInterrupt, Ccs811Error> {
+        // nINT is open drain
+        let mut pin = pin.into_input_pullup();
+        let shared = Arc::new(Mutex::new(Shared { fired: false, waker: None }));
+
+        let callback_shared = shared.clone();
+        pin.set_async_interrupt(Trigger::FallingEdge, move |_| {
+            let mut shared = callback_shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            shared.fired = true;
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }).map_err(pin_error("listen to interrupt pin"))?;
+
+        Ok(DataReadyInterrupt { pin, shared })
+    }
+
+    /// Resolves as soon as a sample is ready. If the chip already signals a ready sample it
+    /// resolves immediately.
+    pub fn wait(&mut self) -> DataReady<'_> {
+        self.lock().fired = false;
+        // An edge between resetting and this check is caught by the level as well
+        if self.pin.is_low() {
+            self.lock().fired = true;
+        }
+
+        DataReady { interrupt: self }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Future returned by `DataReadyInterrupt::wait()`
+pub struct DataReady<'a> {
+    interrupt: &'a DataReadyInterrupt
+}
+
+impl Future for DataReady<'_> {
+    type Output = ();
+
+    fn poll(self: FuturePin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        let mut shared = self.interrupt.lock();
+        if shared.fired {
+            shared.fired = false;
+            return Poll::Ready(());
+        }
+        shared.waker = Some(context.waker().clone());
+
+        Poll::Pending
+    }
+}
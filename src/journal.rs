@@ -0,0 +1,149 @@
+use std::fmt::Display;
+use std::os::unix::net::UnixDatagram;
+use std::process;
+use std::sync::Arc;
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::sink::ReadingSink;
+
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKET: &str = "/dev/log";
+// Facility of system daemons, combined with the priority in the syslog header
+const SYSLOG_FACILITY_DAEMON: u8 = 3;
+
+/// Severity of a log entry, the syslog levels journald uses as well
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Error = 3,
+    Warning = 4,
+    Info = 6,
+    Debug = 7
+}
+
+enum Target {
+    Journald(UnixDatagram),
+    Syslog(UnixDatagram),
+    Stderr
+}
+
+/// Structured logging to journald with the native protocol, so fields like `E_CO2` can be queried with
+/// `journalctl -u ccs811d E_CO2=812`. Without journald it falls back to syslog and then to stderr, where
+/// the fields are appended to the message as `key=value`. Clones log to the same target.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let error = "Timeout";
+/// use ccs811::journal::{Journal, Priority};
+///
+/// let journal = Journal::open("ccs811d");
+/// journal.log(Priority::Warning, "Could not read baseline", &[("ERROR", &error)]);
+/// ```
+#[derive(Clone)]
+pub struct Journal {
+    target: Arc<Target>,
+    identifier: String
+}
+
+/// Appends a field to a journald datagram, values with line breaks need the binary form
+fn append_field(datagram: &mut Vec<u8>, key: &str, value: &str) {
+    datagram.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        datagram.push(b'\n');
+        datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        datagram.push(b'=');
+    }
+    datagram.extend_from_slice(value.as_bytes());
+    datagram.push(b'\n');
+}
+
+impl Journal {
+
+    /// Connects to journald or syslog, the identifier names the program in the log
+    pub fn open(identifier: &str) -> Journal {
+        let connect = |path| UnixDatagram::unbound().and_then(|socket| socket.connect(path).map(|_| socket));
+        let target = connect(JOURNALD_SOCKET).map(Target::Journald)
+            .or_else(|_| connect(SYSLOG_SOCKET).map(Target::Syslog))
+            .unwrap_or(Target::Stderr);
+
+        Journal { target: Arc::new(target), identifier: identifier.to_string() }
+    }
+
+    /// Logs the message with additional fields. Keys have to be uppercase letters, digits and
+    /// underscores like `E_CO2`. Logging never fails, if journald or syslog can't be reached the
+    /// entry goes to stderr.
+    pub fn log(&self, priority: Priority, message: &str, fields: &[(&str, &dyn Display)]) {
+        let sent = match &*self.target {
+            Target::Journald(socket) => {
+                let mut datagram = Vec::new();
+                append_field(&mut datagram, "MESSAGE", message);
+                append_field(&mut datagram, "PRIORITY", &(priority as u8).to_string());
+                append_field(&mut datagram, "SYSLOG_IDENTIFIER", &self.identifier);
+                for (key, value) in fields {
+                    append_field(&mut datagram, key, &value.to_string());
+                }
+                socket.send(&datagram).is_ok()
+            },
+            Target::Syslog(socket) => {
+                let header = SYSLOG_FACILITY_DAEMON << 3 | priority as u8;
+                let line = format!("<{}>{}[{}]: {}", header, self.identifier, process::id(), self.line(message, fields));
+                socket.send(line.as_bytes()).is_ok()
+            },
+            Target::Stderr => false
+        };
+
+        if !sent {
+            eprintln!("{}", self.line(message, fields));
+        }
+    }
+
+    fn line(&self, message: &str, fields: &[(&str, &dyn Display)]) -> String {
+        fields.iter().fold(message.to_string(), |line, (key, value)| {
+            format!("{} {}={}", line, key.to_lowercase(), value)
+        })
+    }
+}
+
+/// Logs every reading with its values as fields
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::journal::{Journal, JournalSink};
+/// use ccs811::pipeline::Pipeline;
+///
+/// let mut pipeline = Pipeline::new(ccs811)
+///     .sink(JournalSink::new(Journal::open("air")));
+/// ```
+pub struct JournalSink {
+    journal: Journal,
+    priority: Priority
+}
+
+impl JournalSink {
+
+    /// Readings are logged with the priority `Info`
+    pub fn new(journal: Journal) -> Self {
+        JournalSink { journal, priority: Priority::Info }
+    }
+
+    /// Priority of the readings, e.g. `Debug` to not flood the log when sampling every second
+    pub fn with_priority(self, priority: Priority) -> Self {
+        JournalSink { priority, ..self }
+    }
+}
+
+impl ReadingSink for JournalSink {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        self.journal.log(self.priority, &data.to_string(), &[
+            ("E_CO2", &data.e_co2),
+            ("T_VOC", &data.t_voc),
+            ("AIR_QUALITY", &data.air_quality()),
+            ("WARMING_UP", &data.is_warming_up())
+        ]);
+
+        Ok(())
+    }
+}
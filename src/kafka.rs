@@ -0,0 +1,163 @@
+use ::rdkafka::config::ClientConfig;
+use ::rdkafka::error::{KafkaError, KafkaResult};
+use ::rdkafka::producer::{BaseProducer, BaseRecord, Producer};
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::format::Format;
+use crate::sink::ReadingSink;
+
+// Readings not delivered within this time are dropped by the producer, and flushing gives up after it
+const KAFKA_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Avro schema of the `Payload::Avro` messages, to register it in a schema registry
+pub const AVRO_SCHEMA: &str = r#"{
+  "type": "record",
+  "name": "Reading",
+  "namespace": "ccs811",
+  "fields": [
+    {"name": "timestamp", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+    {"name": "e_co2", "type": "int"},
+    {"name": "t_voc", "type": "int"},
+    {"name": "warming_up", "type": "boolean"}
+  ]
+}"#;
+
+/// Encoding of the message values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Payload {
+    /// `{"e_co2":812,"t_voc":63,"warming_up":false,"timestamp":1700000000}` like `Format::Json`
+    Json,
+    /// Avro binary encoding of `AVRO_SCHEMA`. With a schema id it's prefixed with the magic byte and
+    /// the id, the wire format of the Confluent schema registry.
+    Avro { schema_id: Option<u32> }
+}
+
+impl Payload {
+
+    fn encode(&self, data: &Ccs811Data, timestamp: SystemTime) -> Vec<u8> {
+        match self {
+            Payload::Json => Format::Json.format(data, timestamp).into_bytes(),
+            Payload::Avro { schema_id } => {
+                let mut bytes = Vec::with_capacity(16);
+                if let Some(schema_id) = schema_id {
+                    bytes.push(0);
+                    bytes.extend_from_slice(&schema_id.to_be_bytes());
+                }
+                let millis = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+                write_avro_long(&mut bytes, i64::try_from(millis).unwrap_or(i64::MAX));
+                write_avro_long(&mut bytes, i64::from(data.e_co2));
+                write_avro_long(&mut bytes, i64::from(data.t_voc));
+                bytes.push(u8::from(data.is_warming_up()));
+
+                bytes
+            }
+        }
+    }
+}
+
+/// Avro encodes int and long as zig-zag variable length integer
+fn write_avro_long(bytes: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        bytes.push((zigzag & 0x7f) as u8 | 0x80);
+        zigzag >>= 7;
+    }
+    bytes.push(zigzag as u8);
+}
+
+/// Produces every reading to a Kafka topic, keyed by the sensor id so the readings of a sensor stay in
+/// one partition and in order. Messages are JSON by default or Avro with `with_payload()`.
+///
+/// Producing doesn't block, librdkafka batches the messages and sends them in the background. Readings
+/// it couldn't deliver within 30 seconds are dropped. `flush()` waits for the pending ones, which also
+/// happens when the sink is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::kafka::{KafkaSink, Payload};
+///
+/// let mut kafka = KafkaSink::new("kafka-1:9092,kafka-2:9092", "air-quality", "living-room")
+///     .expect("Could not create producer")
+///     .with_payload(Payload::Avro { schema_id: Some(1) });
+///
+/// kafka.publish(&ccs811.read().expect("Could not read data")).expect("Could not produce");
+/// ```
+pub struct KafkaSink {
+    producer: BaseProducer,
+    topic: String,
+    sensor_id: String,
+    payload: Payload
+}
+
+impl KafkaSink {
+
+    /// Only creates the producer, it connects to the brokers like `host:9092,host:9093` in the background
+    pub fn new(brokers: &str, topic: &str, sensor_id: &str) -> KafkaResult<Self> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", brokers);
+
+        Self::from_config(&config, topic, sensor_id)
+    }
+
+    /// Creates the producer with further settings of librdkafka, e.g. `security.protocol` and
+    /// `sasl.mechanism` for authentication
+    pub fn from_config(config: &ClientConfig, topic: &str, sensor_id: &str) -> KafkaResult<Self> {
+        // librdkafka panics on producing to such a topic
+        if topic.contains('\0') {
+            return Err(KafkaError::ClientCreation(format!("Topic {:?} contains a nul byte", topic)));
+        }
+        let mut config = config.clone();
+        if config.get("message.timeout.ms").is_none() {
+            config.set("message.timeout.ms", KAFKA_TIMEOUT.as_millis().to_string());
+        }
+
+        Ok(KafkaSink {
+            producer: config.create()?,
+            topic: topic.to_string(),
+            sensor_id: sensor_id.to_string(),
+            payload: Payload::Json
+        })
+    }
+
+    pub fn with_payload(mut self, payload: Payload) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Queues the reading, fails only if the queue of the producer is full or the topic is invalid
+    pub fn publish(&mut self, data: &Ccs811Data) -> KafkaResult<()> {
+        let payload = self.payload.encode(data, SystemTime::now());
+        let record = BaseRecord::to(&self.topic).key(&self.sensor_id).payload(&payload);
+        let result = self.producer.send(record).map_err(|(error, _)| error);
+        // Serves the delivery reports, so the queue doesn't fill up
+        self.producer.poll(Duration::ZERO);
+
+        result
+    }
+
+    /// Waits until the queued readings are delivered
+    pub fn flush(&mut self) -> KafkaResult<()> {
+        self.producer.flush(KAFKA_TIMEOUT)
+    }
+}
+
+impl ReadingSink for KafkaSink {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        Ok(KafkaSink::publish(self, data)?)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        Ok(KafkaSink::flush(self)?)
+    }
+}
+
+/// Delivers the queued readings, the producer itself discards them when dropped
+impl Drop for KafkaSink {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
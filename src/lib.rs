@@ -1,19 +1,119 @@
-use rppal::i2c::I2c;
-use rppal::gpio::Pin;
-use crate::chip::CCS811;
+// No input from the caller or the chip may panic the driver, errors are returned instead
+#![forbid(unsafe_code)]
+#![deny(clippy::arithmetic_side_effects, clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
+#[cfg(feature = "rppal")]
+use ::rppal::i2c::I2c;
+#[cfg(feature = "rppal")]
+use ::rppal::gpio::{Gpio, Pin};
+#[cfg(feature = "rppal")]
+use crate::chip::{Address, CCS811};
+#[cfg(feature = "rppal")]
+use crate::error::{i2c_error, pin_error};
+#[cfg(feature = "rppal")]
+use crate::rppal::RppalPin;
 
 mod constants;
 pub mod chip;
+pub mod codec;
+pub mod firmware;
+pub mod error;
+pub mod retry;
+pub mod recovery;
+pub mod health;
+pub mod compensation;
+pub mod filter;
+pub mod baseline;
+pub mod calibration;
+pub mod dual;
+pub mod diagnostics;
+pub mod profile;
+#[cfg(any(test, feature = "testing"))]
+pub mod sim;
+#[cfg(feature = "testing")]
+pub mod fake;
+pub mod sensor;
+pub mod schedule;
+#[cfg(feature = "statsd")]
+pub mod statsd;
+pub mod sink;
+#[cfg(feature = "file")]
+pub mod file;
+#[cfg(feature = "alert")]
+pub mod alert;
+#[cfg(feature = "ventilation")]
+pub mod ventilation;
+pub mod occupancy;
+pub mod concentration;
+pub mod exposure;
+#[cfg(feature = "registry")]
+pub mod registry;
+pub mod pipeline;
+pub mod format;
+pub mod wake;
+pub mod history;
+pub mod split;
+pub mod shared;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "async-std")]
+pub mod async_std;
+#[cfg(feature = "nb")]
+pub mod nonblocking;
+#[cfg(feature = "ftdi")]
+pub mod ftdi;
+#[cfg(feature = "gpio-cdev")]
+pub mod cdev;
+#[cfg(feature = "rppal")]
+pub mod rppal;
+#[cfg(feature = "rppal")]
+pub mod interrupt;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "signal-hook")]
+pub mod shutdown;
+#[cfg(all(unix, feature = "daemon"))]
+pub mod journal;
+#[cfg(feature = "otel")]
+pub mod otel;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "zmq")]
+pub mod zmq;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(feature = "mdns")]
+pub mod mdns;
+#[cfg(feature = "control")]
+pub mod control;
+#[cfg(feature = "healthz")]
+pub mod healthz;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "polars")]
+pub mod polars;
+#[cfg(feature = "display")]
+pub mod display;
 
 pub use crate::constants::Ccs811Mode as MODE;
+pub use crate::error::Ccs811Error;
+pub use crate::constants::{CCS811_SLAVEADDR_0, CCS811_SLAVEADDR_1, CCS811_MAX_BUS_CLOCK_HZ, CCS811_WARM_UP};
+pub use crate::constants::CCS811_IDLE_BEFORE_SLOWER_MODE;
+pub use crate::constants::{CCS811_HUMIDITY_MIN, CCS811_HUMIDITY_MAX, CCS811_TEMPERATURE_MIN, CCS811_TEMPERATURE_MAX};
 
-/// Creates a new instance of the chip. Be aware that in my experiences the wake pin resulted in wrong data.
+/// Creates a new instance of the chip on the Raspberry Pi. Be aware that in my experiences the wake pin resulted in wrong data.
 /// This probably is caused due to the short heating period after the awakening. To save energy I would
 /// set the Sec60 mode and leave it awake.
 ///
 /// # Examples
 ///
-/// ```
+/// ```no_run
 /// use rppal::i2c::I2c;
 /// use rppal::gpio::Gpio;
 ///
@@ -23,12 +123,44 @@ pub use crate::constants::Ccs811Mode as MODE;
 ///
 /// let mut ccs811 = ccs811::new(i2c, Some(wake_pin));
 /// ```
-pub fn new(i2c: I2c, wake: Option<Pin>) -> CCS811 {
-    let chip = CCS811 {
-        i2c,
-        // Put wake pin into output mode if set
-        wake: wake.map(|pin| pin.into_output())
-    };
-
-    return chip;
-}
\ No newline at end of file
+#[cfg(feature = "rppal")]
+pub fn new(i2c: I2c, wake: Option<Pin>) -> CCS811<I2c, RppalPin> {
+    let clock_speed = i2c.clock_speed().ok();
+    // Put wake pin into output mode if set
+    let chip = CCS811::new(i2c, wake.map(RppalPin::from));
+
+    match clock_speed {
+        Some(clock_speed) => chip.with_bus_clock(clock_speed),
+        None => chip
+    }
+}
+#[cfg(feature = "rppal")]
+impl CCS811<I2c, RppalPin> {
+
+    /// Opens the I2C bus and the wake pin given by its BCM number, initializes the chip and returns it
+    /// ready to start sampling. Failing to open the bus or the pin is reported as `Ccs811Error::I2c` or
+    /// `Ccs811Error::Pin`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ccs811::chip::{Address, CCS811};
+    ///
+    /// let mut ccs811 = CCS811::open(1, Address::Low, Some(17)).expect("Could not open the chip");
+    /// ccs811.start(ccs811::MODE::Sec1).expect("Could not start");
+    /// ```
+    pub fn open(bus: u8, address: Address, wake_bcm: Option<u8>) -> Result<Self, Ccs811Error> {
+        let i2c = I2c::with_bus(bus)
+            .map_err(i2c_error("open I2C bus"))?;
+        let wake = match wake_bcm {
+            Some(bcm) => Some(Gpio::new().and_then(|gpio| gpio.get(bcm))
+                .map_err(pin_error("open wake pin"))?),
+            None => None
+        };
+
+        let mut chip = new(i2c, wake).with_address(address);
+        chip.begin()?;
+
+        Ok(chip)
+    }
+}
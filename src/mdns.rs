@@ -0,0 +1,67 @@
+use ::mdns_sd::{Error, ServiceDaemon, ServiceInfo};
+use std::fs;
+use std::time::Duration;
+
+/// DNS-SD service type the sensors are announced as
+pub const SERVICE_TYPE: &str = "_ccs811._tcp.local.";
+
+/// Name of the machine as in `/etc/hostname`, `ccs811` if it can't be read
+pub fn host_name() -> String {
+    fs::read_to_string("/etc/hostname").ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "ccs811".to_string())
+}
+
+/// Announces an endpoint of the sensor as `_ccs811._tcp` service via mDNS, so dashboards and apps on
+/// the LAN find it without configuration, e.g. with `avahi-browse _ccs811._tcp` or `dns-sd -B
+/// _ccs811._tcp`. The service is announced on all interfaces and follows changes of their addresses.
+/// The TXT record carries the given properties and the crate version as `version`. Dropping the
+/// advertisement withdraws the service.
+///
+/// # Examples
+///
+/// ```
+/// use ccs811::mdns::Advertisement;
+///
+/// let advertisement = Advertisement::announce("Kitchen", 8080, &[("sensor", "kitchen"), ("path", "/reading")])
+///     .expect("Could not announce the sensor");
+/// ```
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String
+}
+
+impl Advertisement {
+
+    /// Announces the service instance, e.g. `Kitchen`, with the endpoint on `port` of this host.
+    /// Instance names have to be unique on the LAN.
+    pub fn announce(instance: &str, port: u16, properties: &[(&str, &str)]) -> Result<Self, Error> {
+        let properties: Vec<(&str, &str)> = properties.iter().copied()
+            .chain([("version", env!("CARGO_PKG_VERSION"))])
+            .collect();
+        let info = ServiceInfo::new(SERVICE_TYPE, instance, &format!("{}.local.", host_name()), (), port, properties.as_slice())?
+            .enable_addr_auto();
+        let fullname = info.get_fullname().to_string();
+
+        let daemon = ServiceDaemon::new()?;
+        daemon.register(info)?;
+
+        Ok(Advertisement { daemon, fullname })
+    }
+
+    /// Full name of the service like `Kitchen._ccs811._tcp.local.`
+    pub fn fullname(&self) -> &str {
+        &self.fullname
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        // Sends the goodbye, so browsers remove the sensor at once instead of when the record expires
+        if let Ok(status) = self.daemon.unregister(&self.fullname) {
+            let _ = status.recv_timeout(Duration::from_secs(1));
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
@@ -0,0 +1,250 @@
+use rumqttc::{Client, ClientError, Event, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, sleep, JoinHandle};
+use std::time::{Duration, SystemTime};
+use crate::alert::{Alert, Notifier};
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::format::Format;
+use crate::sink::ReadingSink;
+
+/// Connection settings of a `MqttPublisher`
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Topic the readings are published to
+    pub topic: String,
+    /// Retained topic which is `online` while connected and set to `offline` by the broker when the
+    /// connection is lost (Last Will)
+    pub availability_topic: String,
+    pub keep_alive: Duration,
+    /// Wait after the first failed connection attempt. It doubles with every further attempt.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Readings kept while the broker isn't reachable. The oldest are dropped first.
+    pub buffer_size: usize
+}
+
+impl MqttConfig {
+
+    /// Default config publishing to `topic` and `<topic>/availability` on port 1883
+    pub fn new(host: &str, topic: &str) -> Self {
+        MqttConfig {
+            host: host.to_string(),
+            port: 1883,
+            client_id: "ccs811".to_string(),
+            topic: topic.to_string(),
+            availability_topic: format!("{}/availability", topic),
+            keep_alive: Duration::from_secs(30),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            buffer_size: 1000
+        }
+    }
+}
+
+/// Publishes readings as JSON like `{"e_co2":400,"t_voc":0,"timestamp":1700000000}` to a MQTT broker.
+/// The connection is kept up by a background thread which reconnects with exponential backoff.
+/// Readings taken while offline are buffered and published by the background thread as soon as it
+/// reconnected, without waiting for the next reading.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::thread::sleep;
+/// # use std::time::Duration;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::mqtt::{MqttConfig, MqttPublisher};
+///
+/// let mut publisher = MqttPublisher::connect(MqttConfig::new("localhost", "home/air"));
+///
+/// loop {
+///   let data = ccs811.read().expect("Could not read data");
+///   publisher.publish(&data);
+///   sleep(Duration::from_secs(10));
+/// }
+/// ```
+pub struct MqttPublisher {
+    client: Client,
+    topic: String,
+    availability_topic: String,
+    online: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    buffer: Arc<Mutex<VecDeque<String>>>,
+    buffer_size: usize,
+    handle: Option<JoinHandle<()>>
+}
+
+impl MqttPublisher {
+
+    pub fn connect(config: MqttConfig) -> Self {
+        let mut options = MqttOptions::new(config.client_id.clone(), config.host.clone(), config.port);
+        options.set_keep_alive(config.keep_alive);
+        options.set_last_will(LastWill::new(config.availability_topic.clone(), "offline", QoS::AtLeastOnce, true));
+
+        let (client, mut connection) = Client::new(options, 100);
+        let online = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let buffer = Arc::new(Mutex::new(VecDeque::new()));
+
+        let handle = {
+            let client = client.clone();
+            let online = online.clone();
+            let stopped = stopped.clone();
+            let buffer = buffer.clone();
+            let topic = config.topic.clone();
+            let availability_topic = config.availability_topic.clone();
+            let (initial_backoff, max_backoff) = (config.initial_backoff, config.max_backoff);
+            let mut backoff = initial_backoff;
+
+            thread::spawn(move || {
+                for event in connection.iter() {
+                    match event {
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            backoff = initial_backoff;
+                            online.store(true, Ordering::SeqCst);
+                            let _ = client.try_publish(availability_topic.as_str(), QoS::AtLeastOnce, true, "online");
+                            flush(&client, &topic, &online, &buffer);
+                        },
+                        Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                        Ok(_) => (),
+                        Err(_) if stopped.load(Ordering::SeqCst) => break,
+                        Err(_) => {
+                            online.store(false, Ordering::SeqCst);
+                            sleep(backoff);
+                            backoff = min(backoff.saturating_mul(2), max_backoff);
+                        }
+                    }
+                }
+            })
+        };
+
+        MqttPublisher {
+            client,
+            topic: config.topic,
+            availability_topic: config.availability_topic,
+            online,
+            stopped,
+            buffer,
+            buffer_size: config.buffer_size,
+            handle: Some(handle)
+        }
+    }
+
+    /// Whether the broker is currently connected
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::SeqCst)
+    }
+
+    /// Number of readings waiting to be published
+    pub fn buffered(&self) -> usize {
+        self.buffer.lock().map_or(0, |buffer| buffer.len())
+    }
+
+    /// Notifier publishing alerts as JSON to the topic over the connection of this publisher, e.g. for an
+    /// `AlertSink`. Alerts are queued while the broker isn't connected.
+    pub fn notifier(&self, topic: &str) -> MqttNotifier {
+        MqttNotifier { client: self.client.clone(), topic: topic.to_string() }
+    }
+
+    /// Publishes the reading or buffers it while the broker isn't connected
+    pub fn publish(&mut self, data: &Ccs811Data) {
+        if let Ok(mut buffer) = self.buffer.lock() {
+            if buffer.len() >= self.buffer_size {
+                buffer.pop_front();
+            }
+            buffer.push_back(Format::Json.format(data, SystemTime::now()));
+        }
+
+        self.flush();
+    }
+
+    /// Publishes the buffered readings if the broker is connected. The background thread does so
+    /// itself after reconnecting.
+    pub fn flush(&mut self) {
+        flush(&self.client, &self.topic, &self.online, &self.buffer);
+    }
+}
+
+/// Publishes the buffered readings while the broker is connected and the queue of the client takes
+/// them. It's never blocked, as the connection thread empties the queue.
+fn flush(client: &Client, topic: &str, online: &AtomicBool, buffer: &Mutex<VecDeque<String>>) {
+    let mut buffer = match buffer.lock() {
+        Ok(buffer) => buffer,
+        Err(_) => return
+    };
+
+    while online.load(Ordering::SeqCst) {
+        let payload = match buffer.front() {
+            Some(payload) => payload.clone(),
+            None => break
+        };
+
+        if client.try_publish(topic, QoS::AtLeastOnce, false, payload).is_err() {
+            break;
+        }
+        buffer.pop_front();
+    }
+}
+
+impl ReadingSink for MqttPublisher {
+    /// Never fails, readings are buffered while the broker isn't connected
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        MqttPublisher::publish(self, data);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        MqttPublisher::flush(self);
+        Ok(())
+    }
+}
+
+/// Publishes alerts to a MQTT topic, see `MqttPublisher::notifier()` and `Alert::to_json()`
+///
+/// # Examples
+///
+/// ```
+/// use ccs811::alert::{AlertSink, Metric};
+/// use ccs811::mqtt::{MqttConfig, MqttPublisher};
+///
+/// let publisher = MqttPublisher::connect(MqttConfig::new("localhost", "home/air"));
+/// let mut alerts = AlertSink::new(publisher.notifier("home/air/alerts"))
+///     .level(Metric::ECo2, 1000);
+/// ```
+pub struct MqttNotifier {
+    client: Client,
+    topic: String
+}
+
+impl MqttNotifier {
+
+    /// Fails if the queue of the connection is full
+    pub fn notify(&mut self, alert: &Alert) -> Result<(), ClientError> {
+        self.client.try_publish(self.topic.as_str(), QoS::AtLeastOnce, false, alert.to_json())
+    }
+}
+
+impl Notifier for MqttNotifier {
+    fn notify(&mut self, alert: &Alert) -> Result<(), BoxError> {
+        Ok(MqttNotifier::notify(self, alert)?)
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // The Last Will is only sent on connection loss, so announce a graceful shutdown ourselves
+        let _ = self.client.try_publish(self.availability_topic.as_str(), QoS::AtLeastOnce, true, "offline");
+        let _ = self.client.try_disconnect();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
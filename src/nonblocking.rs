@@ -0,0 +1,243 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::time::Instant;
+use crate::chip::{CCS811, Ccs811Data};
+use crate::constants::{*};
+use crate::error::{Ccs811Error, SourceError};
+
+/// Progress of the non-blocking initialization started by `try_begin()`
+pub(crate) enum BeginState {
+    Idle,
+    Resetting(Instant),
+    Starting(Instant)
+}
+
+impl<I2C, WAKE, RESET, E> CCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError,
+          WAKE: OutputPin,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin,
+          <RESET as OutputPin>::Error: SourceError {
+
+    /// Non-blocking variant of `begin()` following the `nb` conventions. Instead of sleeping until
+    /// the chip finished its reset and app start, it returns `WouldBlock` until the corresponding
+    /// delay elapsed. Call it repeatedly, e.g. from a superloop, until it returns `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn do_other_work() {}
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// loop {
+    ///   match ccs811.try_begin() {
+    ///     Ok(()) => break,
+    ///     Err(nb::Error::WouldBlock) => do_other_work(),
+    ///     Err(nb::Error::Other(error)) => panic!("Could not init the chip: {}", error)
+    ///   }
+    /// }
+    /// ```
+    pub fn try_begin(&mut self) -> nb::Result<(), Ccs811Error> {
+        let result = self.step_begin();
+
+        if let Err(nb::Error::Other(_)) = result {
+            // Start over on the next call
+            self.begin_state = BeginState::Idle;
+            let _ = self.sleep();
+        }
+
+        result
+    }
+
+    fn step_begin(&mut self) -> nb::Result<(), Ccs811Error> {
+        match self.begin_state {
+            BeginState::Idle => {
+                self.check_bus_clock()?;
+                self.release_reset()?;
+                self.awake()?;
+                self.send_reset()?;
+                self.begin_state = BeginState::Resetting(Instant::now());
+
+                Err(nb::Error::WouldBlock)
+            },
+            BeginState::Resetting(since) if since.elapsed() < CCS811_WAIT_AFTER_RESET_US => Err(nb::Error::WouldBlock),
+            BeginState::Resetting(_) => {
+                self.check_hw_id()?;
+                self.send_app_start()?;
+                self.begin_state = BeginState::Starting(Instant::now());
+
+                Err(nb::Error::WouldBlock)
+            },
+            BeginState::Starting(since) if since.elapsed() < CCS811_WAIT_AFTER_APPSTART_US => Err(nb::Error::WouldBlock),
+            BeginState::Starting(_) => {
+                self.check_status(CCS811_STATUS_APP_MODE | CCS811_STATUS_APP_VERIFY)?;
+                self.read_app_version()?;
+                self.begin_state = BeginState::Idle;
+                self.counters.init();
+
+                Ok(self.sleep()?)
+            }
+        }
+    }
+
+    /// Non-blocking variant of `read()` following the `nb` conventions. Returns `WouldBlock` as long
+    /// as the chip has no new sample ready (DATA_READY status bit is clear). Readiness is taken from
+    /// the status byte of ALG_RESULT_DATA, so polling costs a single transaction. Samples discarded
+    /// after `start()` (see `with_discarded_samples()`) also return `WouldBlock`. Like `read()` it
+    /// recovers a lost chip, tracks a hot swapped one and counts into `health()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// match ccs811.try_read() {
+    ///   Ok(data) => println!("t_voc: {}, e_co2: {}", data.t_voc, data.e_co2),
+    ///   Err(nb::Error::WouldBlock) => (),
+    ///   Err(nb::Error::Other(error)) => println!("Could not read data: {}", error)
+    /// }
+    /// ```
+    pub fn try_read(&mut self) -> nb::Result<Ccs811Data, Ccs811Error> {
+        let result = self.try_read_sample();
+        // Polling while the chip is busy is neither a success nor a failure
+        if !matches!(result, Err(nb::Error::WouldBlock)) {
+            self.health.record(result.is_ok());
+        }
+
+        result
+    }
+
+    fn try_read_sample(&mut self) -> nb::Result<Ccs811Data, Ccs811Error> {
+        self.check_reconnected()?;
+        let result = self.read_recovered_result();
+        let buffer = self.track_presence(result)?;
+
+        // Errors are reported even without a new sample, like read() does
+        if buffer[5] == 0 && (buffer[4] & CCS811_STATUS_DATA_READY) == 0 || self.discard_sample(&buffer) {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        Ok(self.parse_result(buffer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+    use std::time::Duration;
+    use crate::chip::{CCS811, NoPin};
+    use crate::health::{Health, HealthIssue};
+    use crate::sim::SimCcs811;
+    use super::*;
+
+    fn begin() -> CCS811<SimCcs811> {
+        let mut ccs811 = CCS811::new(SimCcs811::new(), None::<NoPin>).with_discarded_samples(0);
+        ccs811.begin().unwrap();
+        ccs811.start(Ccs811Mode::Sec1).unwrap();
+
+        ccs811
+    }
+
+    /// Calls `try_begin()` until it's done, sleeping for the delays of the chip in between
+    fn try_begin(ccs811: &mut CCS811<SimCcs811>) -> (Result<(), Ccs811Error>, usize) {
+        let mut blocked = 0_usize;
+        loop {
+            match ccs811.try_begin() {
+                Err(nb::Error::WouldBlock) => {
+                    blocked = blocked.saturating_add(1);
+                    sleep(CCS811_WAIT_AFTER_RESET_US);
+                },
+                Err(nb::Error::Other(error)) => return (Err(error), blocked),
+                Ok(()) => return (Ok(()), blocked)
+            }
+        }
+    }
+
+    #[test]
+    fn try_begin_waits_for_reset_and_app_start() {
+        let mut ccs811 = CCS811::new(SimCcs811::new(), None::<NoPin>);
+
+        let (result, blocked) = try_begin(&mut ccs811);
+        assert!(result.is_ok());
+        assert_eq!(blocked, 2);
+        assert!(ccs811.i2c.is_app_mode());
+    }
+
+    #[test]
+    fn try_begin_starts_over_after_an_error() {
+        let mut ccs811 = CCS811::new(SimCcs811::new().without_application(), None::<NoPin>);
+
+        let (result, _) = try_begin(&mut ccs811);
+        assert!(matches!(result, Err(Ccs811Error::NoValidApplication(_))));
+        // Resets the chip again
+        assert!(matches!(ccs811.try_begin(), Err(nb::Error::WouldBlock)));
+        assert!(matches!(ccs811.begin_state, BeginState::Resetting(_)));
+    }
+
+    #[test]
+    fn try_read_blocks_until_a_sample_is_ready() {
+        let mut ccs811 = begin();
+
+        assert!(matches!(ccs811.try_read(), Err(nb::Error::WouldBlock)));
+        ccs811.i2c.set_air(800, 60);
+        ccs811.i2c.elapse(Duration::from_secs(1));
+        let data = ccs811.try_read().unwrap();
+        assert_eq!((data.e_co2, data.t_voc), (800, 60));
+        // The sample was consumed
+        assert!(matches!(ccs811.try_read(), Err(nb::Error::WouldBlock)));
+    }
+
+    #[test]
+    fn try_read_discards_the_first_sample() {
+        let mut ccs811 = begin().with_discarded_samples(1);
+        ccs811.start(Ccs811Mode::Sec1).unwrap();
+
+        ccs811.i2c.elapse(Duration::from_secs(1));
+        assert!(matches!(ccs811.try_read(), Err(nb::Error::WouldBlock)));
+        ccs811.i2c.elapse(Duration::from_secs(1));
+        assert!(ccs811.try_read().is_ok());
+    }
+
+    #[test]
+    fn try_read_counts_into_health() {
+        let mut ccs811 = begin();
+
+        // Polling without a new sample is no failed read
+        for _ in 0..10 {
+            assert!(matches!(ccs811.try_read(), Err(nb::Error::WouldBlock)));
+        }
+        assert_eq!(ccs811.health(), Health::Healthy);
+
+        ccs811.i2c.fail_next(1);
+        assert!(matches!(ccs811.try_read(), Err(nb::Error::Other(Ccs811Error::I2c { .. }))));
+        assert_eq!(ccs811.health(), Health::Degraded(HealthIssue::ErrorRate { errors: 1, reads: 1 }));
+    }
+
+    #[test]
+    fn try_read_recovers_the_chip() {
+        let mut ccs811 = begin().with_auto_recovery(1);
+
+        ccs811.i2c.unplug();
+        ccs811.i2c.plug();
+        ccs811.i2c.set_air(900, 80);
+        assert!(matches!(ccs811.try_read(), Err(nb::Error::WouldBlock)));
+        assert!(ccs811.i2c.is_app_mode());
+        assert_eq!(ccs811.mode(), Ccs811Mode::Sec1);
+
+        ccs811.i2c.elapse(Duration::from_secs(1));
+        assert_eq!(ccs811.try_read().unwrap().e_co2, 900);
+    }
+
+    #[test]
+    fn try_read_tracks_hot_swap() {
+        let mut ccs811 = begin().with_hot_swap(2, Duration::ZERO);
+
+        ccs811.i2c.unplug();
+        assert!(matches!(ccs811.try_read(), Err(nb::Error::Other(Ccs811Error::I2c { .. }))));
+        assert!(matches!(ccs811.try_read(), Err(nb::Error::Other(Ccs811Error::Disconnected { .. }))));
+        assert!(ccs811.is_disconnected());
+
+        ccs811.i2c.plug();
+        assert!(matches!(ccs811.try_read(), Err(nb::Error::WouldBlock)));
+        assert!(!ccs811.is_disconnected());
+    }
+}
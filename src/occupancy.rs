@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use crate::chip::Ccs811Data;
+
+// The estimate has to be this far from the current number of occupants to change it, so an estimate
+// around x.5 doesn't flip between two numbers
+const HYSTERESIS: f32 = 0.75;
+
+/// The room and its occupants, which determine how fast eCO2 rises per person
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OccupancyConfig {
+    /// Volume of the room in m³
+    pub volume: f32,
+    /// Air changes per hour through ventilation and leaks, around 0.5 for a room with closed windows
+    pub air_changes: f32,
+    /// eCO2 outdoors in ppm, which the room approaches when empty
+    pub outdoor: u16,
+    /// Litres of CO2 a person exhales per hour, around 18 while sitting and more while moving
+    pub co2_per_person: f32
+}
+
+impl Default for OccupancyConfig {
+    fn default() -> Self {
+        OccupancyConfig { volume: 40.0, air_changes: 0.5, outdoor: 420, co2_per_person: 18.0 }
+    }
+}
+
+/// Change of the estimated number of people in the room
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OccupancyEvent {
+    OccupancyIncreased { from: u16, to: u16 },
+    OccupancyDecreased { from: u16, to: u16 },
+    /// Nobody seems to be in the room anymore
+    RoomVacated { from: u16 }
+}
+
+/// Experimental estimate of the number of people in a room from how fast eCO2 rises or decays. People
+/// exhale CO2 at a fairly constant rate while the ventilation exchanges the air with the outdoor air,
+/// so the rise of eCO2 beyond what the ventilation removes tells the number of people.
+///
+/// This is a heuristic: the chip derives eCO2 from VOCs, which cooking or cleaning raise as well, and
+/// the volume and air changes are rarely known exactly. It's good for automation like turning off the
+/// heating of a vacated room, not for counting people.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// # struct Heating;
+/// # impl Heating { fn set_eco_mode(&self) {} }
+/// # let heating = Heating;
+/// use ccs811::occupancy::{OccupancyConfig, OccupancyEstimator, OccupancyEvent};
+///
+/// let mut estimator = OccupancyEstimator::new(OccupancyConfig { volume: 75.0, ..OccupancyConfig::default() });
+///
+/// loop {
+///   let data = ccs811.sleep_until_next_sample().expect("Could not read data");
+///   if let Some(OccupancyEvent::RoomVacated { .. }) = estimator.record(&data) {
+///     heating.set_eco_mode();
+///   }
+/// }
+/// ```
+pub struct OccupancyEstimator {
+    config: OccupancyConfig,
+    window: Duration,
+    history: VecDeque<(u16, Instant)>,
+    estimate: Option<f32>,
+    occupants: u16
+}
+
+impl OccupancyEstimator {
+
+    /// Estimates from the readings of the last 10 minutes, starting with an empty room
+    pub fn new(config: OccupancyConfig) -> Self {
+        OccupancyEstimator {
+            config,
+            window: Duration::from_secs(10 * 60),
+            history: VecDeque::new(),
+            estimate: None,
+            occupants: 0
+        }
+    }
+
+    /// Time span the rise or decay is calculated over. Longer windows react slower, but are less
+    /// affected by the noise of the readings.
+    pub fn with_window(self, window: Duration) -> Self {
+        OccupancyEstimator { window, ..self }
+    }
+
+    /// Number of people estimated from the readings of the last window, `None` until half a window is
+    /// recorded
+    pub fn estimate(&self) -> Option<f32> {
+        self.estimate
+    }
+
+    /// Number of people in the room, which changes with the events
+    pub fn occupants(&self) -> u16 {
+        self.occupants
+    }
+
+    /// Records a reading taken just now and returns whether the number of occupants changed
+    pub fn record(&mut self, data: &Ccs811Data) -> Option<OccupancyEvent> {
+        self.record_at(data, Instant::now())
+    }
+
+    /// Records a reading taken at the given time and returns whether the number of occupants changed
+    pub fn record_at(&mut self, data: &Ccs811Data, at: Instant) -> Option<OccupancyEvent> {
+        // The readings during the warm-up rise and fall regardless of the people in the room
+        if !data.is_valid() || data.is_warming_up() {
+            self.history.clear();
+            self.estimate = None;
+            return None;
+        }
+
+        while let Some((_, recorded)) = self.history.front() {
+            if at.saturating_duration_since(*recorded) <= self.window {
+                break;
+            }
+            self.history.pop_front();
+        }
+        self.history.push_back((data.e_co2, at));
+
+        let estimate = self.calculate()?;
+        self.estimate = Some(estimate);
+        if (estimate - f32::from(self.occupants)).abs() < HYSTERESIS {
+            return None;
+        }
+
+        let from = self.occupants;
+        self.occupants = estimate.round() as u16;
+        match self.occupants {
+            0 => Some(OccupancyEvent::RoomVacated { from }),
+            to if to > from => Some(OccupancyEvent::OccupancyIncreased { from, to }),
+            to => Some(OccupancyEvent::OccupancyDecreased { from, to })
+        }
+    }
+
+    /// Solves the CO2 balance of the room, rise = people * exhaled / volume - air changes * excess over
+    /// outdoor, for the number of people
+    fn calculate(&self) -> Option<f32> {
+        let (first, since) = *self.history.front()?;
+        let (last, at) = *self.history.back()?;
+        let elapsed = at.saturating_duration_since(since);
+        // A shorter history makes the noise of the readings look like people coming or leaving
+        if elapsed.is_zero() || elapsed < self.window.checked_div(2).unwrap_or_default() {
+            return None;
+        }
+
+        let rise_per_hour = (f32::from(last) - f32::from(first)) / elapsed.as_secs_f32() * 3600.0;
+        let mean = self.history.iter().map(|(e_co2, _)| f32::from(*e_co2)).sum::<f32>() / self.history.len() as f32;
+        let removed_per_hour = self.config.air_changes * (mean - f32::from(self.config.outdoor)).max(0.0);
+        // Litres in m³ give ppm per hour and person
+        let per_person = self.config.co2_per_person * 1000.0 / self.config.volume;
+
+        let people = (rise_per_hour + removed_per_hour) / per_person;
+        people.is_finite().then_some(people.max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::reading;
+    use super::*;
+
+    // Without ventilation 450ppm per hour and person
+    const CLOSED_ROOM: OccupancyConfig = OccupancyConfig { volume: 40.0, air_changes: 0.0, outdoor: 420, co2_per_person: 18.0 };
+
+    fn minutes(start: Instant, minutes: u32) -> Instant {
+        start.checked_add(Duration::from_secs(60).saturating_mul(minutes)).unwrap()
+    }
+
+    /// Records one reading per minute and returns the events
+    fn record(estimator: &mut OccupancyEstimator, start: Instant, e_co2s: &[u16]) -> Vec<Option<OccupancyEvent>> {
+        (0..).zip(e_co2s)
+            .map(|(minute, e_co2)| estimator.record_at(&reading(*e_co2, 0), minutes(start, minute)))
+            .collect()
+    }
+
+    #[test]
+    fn rise_tells_the_occupants() {
+        let start = Instant::now();
+        let mut estimator = OccupancyEstimator::new(CLOSED_ROOM);
+
+        // 15ppm per minute are 900ppm per hour, the estimate needs half the window
+        let events = record(&mut estimator, start, &[420, 435, 450, 465, 480, 495]);
+        assert_eq!(events, vec![None, None, None, None, None, Some(OccupancyEvent::OccupancyIncreased { from: 0, to: 2 })]);
+        assert_eq!((estimator.estimate(), estimator.occupants()), (Some(2.0), 2));
+
+        let event = estimator.record_at(&reading(420, 0), minutes(start, 6));
+        assert_eq!(event, Some(OccupancyEvent::RoomVacated { from: 2 }));
+    }
+
+    #[test]
+    fn ventilation_removes_co2() {
+        let start = Instant::now();
+        let mut estimator = OccupancyEstimator::new(OccupancyConfig::default());
+
+        // 0.5 air changes remove 450ppm per hour of the excess of 900ppm, which one person exhales
+        let events = record(&mut estimator, start, &[1320; 6]);
+        assert_eq!(events.last(), Some(&Some(OccupancyEvent::OccupancyIncreased { from: 0, to: 1 })));
+    }
+
+    #[test]
+    fn small_changes_are_ignored() {
+        let start = Instant::now();
+        let mut estimator = OccupancyEstimator::new(CLOSED_ROOM);
+
+        // 26ppm in 5 minutes are 0.69 people
+        let events = record(&mut estimator, start, &[420, 425, 430, 436, 441, 446]);
+        assert!(events.iter().all(Option::is_none));
+        assert_eq!(estimator.occupants(), 0);
+        assert!(estimator.estimate().is_some_and(|estimate| (0.6..0.75).contains(&estimate)));
+    }
+
+    #[test]
+    fn warm_up_clears_the_estimate() {
+        let start = Instant::now();
+        let mut estimator = OccupancyEstimator::new(CLOSED_ROOM);
+        record(&mut estimator, start, &[420, 435, 450, 465, 480, 495]);
+
+        let warming_up = Ccs811Data { since_start: Some(Duration::ZERO), ..reading(800, 0) };
+        assert_eq!(estimator.record_at(&warming_up, minutes(start, 6)), None);
+        assert_eq!((estimator.estimate(), estimator.occupants()), (None, 2));
+    }
+}
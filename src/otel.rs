@@ -0,0 +1,115 @@
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::{Counter, Gauge, Meter};
+use crate::chip::Ccs811Data;
+use crate::error::{BoxError, Ccs811Error, Recovery};
+use crate::exposure::Twa;
+use crate::sink::ReadingSink;
+
+/// Exports readings and driver errors through the OpenTelemetry metrics API. The instruments are
+/// created on the given meter, so any configured exporter like OTLP picks them up.
+///
+/// - `ccs811.eco2` gauge in ppm
+/// - `ccs811.tvoc` gauge in ppb
+/// - `ccs811.eco2.twa` and `ccs811.tvoc.twa` gauges of the time-weighted averages recorded with
+///   `record_twa()`, with the attribute `window` like `8h`
+/// - `ccs811.errors` counter with the attributes `kind` and `recovery`
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::otel::OtelMetrics;
+///
+/// let metrics = OtelMetrics::new(&opentelemetry::global::meter("ccs811"));
+///
+/// match ccs811.read() {
+///   Ok(data) => metrics.record(&data),
+///   Err(error) => metrics.record_error(&error)
+/// }
+/// ```
+pub struct OtelMetrics {
+    e_co2: Gauge<u64>,
+    t_voc: Gauge<u64>,
+    e_co2_twa: Gauge<f64>,
+    t_voc_twa: Gauge<f64>,
+    errors: Counter<u64>,
+    attributes: Vec<KeyValue>
+}
+
+impl OtelMetrics {
+
+    pub fn new(meter: &Meter) -> Self {
+        OtelMetrics {
+            e_co2: meter.u64_gauge("ccs811.eco2")
+                .with_description("Equivalent CO2 calculated by the chip")
+                .with_unit("ppm")
+                .build(),
+            t_voc: meter.u64_gauge("ccs811.tvoc")
+                .with_description("Total volatile organic compounds calculated by the chip")
+                .with_unit("ppb")
+                .build(),
+            e_co2_twa: meter.f64_gauge("ccs811.eco2.twa")
+                .with_description("Time-weighted average of the equivalent CO2")
+                .with_unit("ppm")
+                .build(),
+            t_voc_twa: meter.f64_gauge("ccs811.tvoc.twa")
+                .with_description("Time-weighted average of the total volatile organic compounds")
+                .with_unit("ppb")
+                .build(),
+            errors: meter.u64_counter("ccs811.errors")
+                .with_description("Errors of the CCS811 driver")
+                .build(),
+            attributes: Vec::new()
+        }
+    }
+
+    /// Attributes added to all measurements, e.g. the room of the sensor
+    pub fn with_attributes(self, attributes: Vec<KeyValue>) -> Self {
+        OtelMetrics { attributes, ..self }
+    }
+
+    pub fn record(&self, data: &Ccs811Data) {
+        self.e_co2.record(u64::from(data.e_co2), &self.attributes);
+        self.t_voc.record(u64::from(data.t_voc), &self.attributes);
+    }
+
+    /// Records the time-weighted averages of an `exposure::ExposureTracker`
+    pub fn record_twa(&self, twa: &Twa) {
+        let hours = twa.window.as_secs().checked_div(3600).unwrap_or_default();
+        let mut attributes = self.attributes.clone();
+        attributes.push(KeyValue::new("window", format!("{}h", hours)));
+
+        self.e_co2_twa.record(f64::from(twa.e_co2), &attributes);
+        self.t_voc_twa.record(f64::from(twa.t_voc), &attributes);
+    }
+
+    pub fn record_error(&self, error: &Ccs811Error) {
+        let mut attributes = self.attributes.clone();
+        attributes.push(KeyValue::new("kind", error.kind()));
+        attributes.push(KeyValue::new("recovery", recovery(error.recovery_hint())));
+
+        self.errors.add(1, &attributes);
+    }
+}
+
+impl ReadingSink for OtelMetrics {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        self.record(data);
+        Ok(())
+    }
+
+    fn record_error(&mut self, error: &Ccs811Error) -> Result<(), BoxError> {
+        OtelMetrics::record_error(self, error);
+        Ok(())
+    }
+}
+
+fn recovery(recovery: Recovery) -> &'static str {
+    match recovery {
+        Recovery::Retry => "retry",
+        Recovery::Reinitialize => "reinitialize",
+        Recovery::Reflash => "reflash",
+        Recovery::Hardware => "hardware",
+        Recovery::Usage => "usage"
+    }
+}
@@ -0,0 +1,252 @@
+use chrono::{DateTime, Utc};
+use ::parquet::basic::Compression;
+use ::parquet::data_type::{BoolType, DataType, Int32Type, Int64Type};
+use ::parquet::errors::ParquetError;
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use ::parquet::schema::parser::parse_message_type;
+use ::parquet::schema::types::Type;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::sink::ReadingSink;
+
+const SCHEMA: &str = "
+    message reading {
+        REQUIRED INT64 timestamp (TIMESTAMP(MILLIS, true));
+        REQUIRED INT32 e_co2 (INTEGER(16, false));
+        REQUIRED INT32 t_voc (INTEGER(16, false));
+        REQUIRED BOOLEAN warming_up;
+        REQUIRED INT32 current_ua (INTEGER(8, false));
+        REQUIRED INT32 adc (INTEGER(16, false));
+    }
+";
+
+/// Time span covered by one Parquet file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    /// `readings-2024-05-01T13.parquet`
+    Hourly,
+    /// `readings-2024-05-01.parquet`
+    Daily
+}
+
+impl Partition {
+
+    /// Name of the file without extension, UTC so changes of the daylight saving time don't overlap
+    fn name(&self, timestamp: SystemTime) -> String {
+        let time = DateTime::<Utc>::from(timestamp);
+
+        match self {
+            Partition::Hourly => time.format("readings-%Y-%m-%dT%H").to_string(),
+            Partition::Daily => time.format("readings-%Y-%m-%d").to_string()
+        }
+    }
+}
+
+// Readings buffered for the next row group, one vector per column
+#[derive(Default)]
+struct Columns {
+    timestamps: Vec<i64>,
+    e_co2: Vec<i32>,
+    t_voc: Vec<i32>,
+    warming_up: Vec<bool>,
+    current_ua: Vec<i32>,
+    adc: Vec<i32>
+}
+
+impl Columns {
+
+    fn push(&mut self, data: &Ccs811Data, timestamp: SystemTime) {
+        let millis = DateTime::<Utc>::from(timestamp).timestamp_millis();
+        self.timestamps.push(millis);
+        self.e_co2.push(i32::from(data.e_co2));
+        self.t_voc.push(i32::from(data.t_voc));
+        self.warming_up.push(data.is_warming_up());
+        self.current_ua.push(i32::from(data.raw.current_ua()));
+        self.adc.push(i32::from(data.raw.adc()));
+    }
+
+    fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    fn write(&self, writer: &mut SerializedFileWriter<File>) -> Result<(), ParquetError> {
+        let mut row_group = writer.next_row_group()?;
+        write_column::<Int64Type>(&mut row_group, &self.timestamps)?;
+        write_column::<Int32Type>(&mut row_group, &self.e_co2)?;
+        write_column::<Int32Type>(&mut row_group, &self.t_voc)?;
+        write_column::<BoolType>(&mut row_group, &self.warming_up)?;
+        write_column::<Int32Type>(&mut row_group, &self.current_ua)?;
+        write_column::<Int32Type>(&mut row_group, &self.adc)?;
+        row_group.close()?;
+
+        Ok(())
+    }
+}
+
+fn write_column<T: DataType>(row_group: &mut SerializedRowGroupWriter<File>, values: &[T::T]) -> Result<(), ParquetError> {
+    let mut column = row_group.next_column()?
+        .ok_or_else(|| ParquetError::General("More columns written than in the schema".to_string()))?;
+    column.typed::<T>().write_batch(values, None, None)?;
+
+    column.close()
+}
+
+// File of the current partition
+struct PartitionFile {
+    name: String,
+    path: PathBuf,
+    writer: SerializedFileWriter<File>
+}
+
+/// Writes readings to Parquet files with one file per hour or day, which pandas or polars load directly,
+/// e.g. `polars.read_parquet("readings-*.parquet")`. Columns are `timestamp` in milliseconds UTC, `e_co2`,
+/// `t_voc`, `warming_up`, `current_ua` and `adc`.
+///
+/// Readings are buffered and written as row group every `with_batch_size()` readings. A Parquet file is
+/// only readable once it's closed, which happens at the end of its hour or day and on `flush()`. Readings
+/// after a `flush()` within the same period go to a new file with a counter like
+/// `readings-2024-05-01-1.parquet`, as Parquet files can't be appended.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::parquet::{ParquetSink, Partition};
+///
+/// let mut parquet = ParquetSink::new("/var/lib/ccs811/parquet", Partition::Daily)
+///     .expect("Could not create directory");
+///
+/// parquet.publish(&ccs811.read().expect("Could not read data")).expect("Could not write reading");
+/// // Before exiting
+/// parquet.flush().expect("Could not close file");
+/// ```
+pub struct ParquetSink {
+    directory: PathBuf,
+    partition: Partition,
+    batch_size: usize,
+    schema: Arc<Type>,
+    properties: Arc<WriterProperties>,
+    file: Option<PartitionFile>,
+    columns: Columns
+}
+
+impl ParquetSink {
+
+    /// Creates the directory if it doesn't exist. Row groups hold 3600 readings by default.
+    pub fn new<P: AsRef<Path>>(directory: P, partition: Partition) -> Result<Self, ParquetError> {
+        fs::create_dir_all(directory.as_ref())?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        Ok(ParquetSink {
+            directory: directory.as_ref().to_path_buf(),
+            partition,
+            batch_size: 3600,
+            schema: Arc::new(parse_message_type(SCHEMA)?),
+            properties: Arc::new(properties),
+            file: None,
+            columns: Columns::default()
+        })
+    }
+
+    /// Number of readings buffered before they are written as row group. Less readings are lost if the
+    /// program crashes, but more row groups make the files larger.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Path of the file currently written
+    pub fn path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|file| file.path.as_path())
+    }
+
+    pub fn publish(&mut self, data: &Ccs811Data) -> Result<(), ParquetError> {
+        let timestamp = SystemTime::now();
+        let name = self.partition.name(timestamp);
+        if self.file.as_ref().is_some_and(|file| file.name != name) {
+            self.flush()?;
+        }
+        if self.file.is_none() {
+            self.file = Some(self.create(name)?);
+        }
+
+        self.columns.push(data, timestamp);
+        if self.columns.len() >= self.batch_size {
+            self.write_batch()?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the buffered readings and closes the file, so it can be read
+    pub fn flush(&mut self) -> Result<(), ParquetError> {
+        self.write_batch()?;
+        if let Some(file) = self.file.take() {
+            file.writer.close()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> Result<(), ParquetError> {
+        if let Some(file) = &mut self.file {
+            if !self.columns.is_empty() {
+                self.columns.write(&mut file.writer)?;
+            }
+        }
+        self.columns = Columns::default();
+
+        Ok(())
+    }
+
+    /// Creates the file of the partition, with a counter if it already exists
+    fn create(&self, name: String) -> Result<PartitionFile, ParquetError> {
+        let mut counter = 0_u32;
+        loop {
+            let path = match counter {
+                0 => self.directory.join(format!("{}.parquet", name)),
+                counter => self.directory.join(format!("{}-{}.parquet", name, counter))
+            };
+
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    let writer = SerializedFileWriter::new(file, self.schema.clone(), self.properties.clone())?;
+                    return Ok(PartitionFile { name, path, writer });
+                },
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists && counter < u32::MAX => {
+                    counter = counter.saturating_add(1);
+                },
+                Err(error) => return Err(error.into())
+            }
+        }
+    }
+}
+
+impl ReadingSink for ParquetSink {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        Ok(ParquetSink::publish(self, data)?)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        Ok(ParquetSink::flush(self)?)
+    }
+}
+
+/// Closes the file, so it's readable even if `flush()` wasn't called
+impl Drop for ParquetSink {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
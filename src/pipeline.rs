@@ -0,0 +1,217 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::collections::VecDeque;
+use thiserror::Error;
+use crate::chip::{CCS811, Ccs811Data};
+use crate::error::{BoxError, Ccs811Error, SourceError};
+use crate::filter::Filter;
+use crate::sink::ReadingSink;
+
+/// Where a pipeline gets its readings from, usually the chip itself
+pub trait ReadingSource {
+    fn next_reading(&mut self) -> Result<Ccs811Data, Ccs811Error>;
+}
+
+impl<I2C, WAKE, RESET, E> ReadingSource for CCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError,
+          WAKE: OutputPin,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin,
+          <RESET as OutputPin>::Error: SourceError {
+
+    fn next_reading(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        self.read()
+    }
+}
+
+impl<F> ReadingSource for F where F: FnMut() -> Result<Ccs811Data, Ccs811Error> {
+    fn next_reading(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        self()
+    }
+}
+
+/// Step between source and sinks which can change a reading or drop it by returning `None`. All
+/// filters are stages which never drop readings.
+pub trait Stage {
+    fn process(&mut self, data: Ccs811Data) -> Option<Ccs811Data>;
+}
+
+impl<F: Filter> Stage for F {
+    fn process(&mut self, data: Ccs811Data) -> Option<Ccs811Data> {
+        Some(self.filter(data))
+    }
+}
+
+/// Drops readings which aren't `is_valid()`
+pub struct Validity;
+
+impl Stage for Validity {
+    fn process(&mut self, data: Ccs811Data) -> Option<Ccs811Data> {
+        if data.is_valid() {
+            Some(data)
+        } else {
+            None
+        }
+    }
+}
+
+/// Drops readings deviating more than the maximum from the median of the last `window` readings.
+/// Dropped readings still count for the median, so a lasting change of the air passes after half
+/// of the window.
+pub struct OutlierRejection {
+    window: usize,
+    max_e_co2_deviation: u16,
+    max_t_voc_deviation: u16,
+    e_co2: VecDeque<u16>,
+    t_voc: VecDeque<u16>
+}
+
+impl OutlierRejection {
+
+    /// A window of 0 is treated like 1 which doesn't reject anything
+    pub fn new(window: usize, max_e_co2_deviation: u16, max_t_voc_deviation: u16) -> Self {
+        let window = window.max(1);
+
+        OutlierRejection {
+            window,
+            max_e_co2_deviation,
+            max_t_voc_deviation,
+            e_co2: VecDeque::with_capacity(window),
+            t_voc: VecDeque::with_capacity(window)
+        }
+    }
+}
+
+/// Adds the value to the window and returns the median of the window before
+fn push_median(values: &mut VecDeque<u16>, window: usize, value: u16) -> Option<u16> {
+    let mut sorted: Vec<u16> = values.iter().copied().collect();
+    sorted.sort_unstable();
+    let median = sorted.get(sorted.len() / 2).copied();
+
+    if values.len() == window {
+        values.pop_front();
+    }
+    values.push_back(value);
+
+    median
+}
+
+fn deviates(median: Option<u16>, value: u16, max_deviation: u16) -> bool {
+    median.is_some_and(|median| value.abs_diff(median) > max_deviation)
+}
+
+impl Stage for OutlierRejection {
+    fn process(&mut self, data: Ccs811Data) -> Option<Ccs811Data> {
+        let e_co2_median = push_median(&mut self.e_co2, self.window, data.e_co2);
+        let t_voc_median = push_median(&mut self.t_voc, self.window, data.t_voc);
+
+        if deviates(e_co2_median, data.e_co2, self.max_e_co2_deviation)
+            || deviates(t_voc_median, data.t_voc, self.max_t_voc_deviation) {
+            None
+        } else {
+            Some(data)
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PipelineError {
+    #[error("Could not read from source")]
+    Source(#[source] Ccs811Error),
+    #[error("Could not publish to sink")]
+    Sink(#[source] BoxError)
+}
+
+/// Reads from a source, passes the readings through a chain of stages like filters, outlier rejection
+/// and validity checks and publishes them to all sinks. Errors of the source are recorded by the sinks.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::thread::sleep;
+/// # use std::time::Duration;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// # let statsd = |_: &ccs811::chip::Ccs811Data| -> Result<(), ccs811::error::BoxError> { Ok(()) };
+/// # let publisher = statsd;
+/// use ccs811::filter::Ewma;
+/// use ccs811::pipeline::{OutlierRejection, Pipeline, Validity};
+///
+/// let mut pipeline = Pipeline::new(ccs811)
+///     .stage(Validity)
+///     .stage(OutlierRejection::new(5, 1000, 500))
+///     .stage(Ewma::new(0.3))
+///     .sink(statsd)
+///     .sink(publisher);
+///
+/// loop {
+///   if let Err(error) = pipeline.run_once() {
+///     println!("{}", error);
+///   }
+///   sleep(Duration::from_secs(10));
+/// }
+/// ```
+pub struct Pipeline<S> {
+    source: S,
+    stages: Vec<Box<dyn Stage + Send>>,
+    sinks: Vec<Box<dyn ReadingSink + Send>>
+}
+
+impl<S: ReadingSource> Pipeline<S> {
+
+    pub fn new(source: S) -> Self {
+        Pipeline { source, stages: Vec::new(), sinks: Vec::new() }
+    }
+
+    /// Appends a stage, readings pass the stages in the order they were added
+    pub fn stage<T: Stage + Send + 'static>(self, stage: T) -> Self {
+        self.boxed_stage(Box::new(stage))
+    }
+
+    pub fn boxed_stage(mut self, stage: Box<dyn Stage + Send>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn sink<T: ReadingSink + Send + 'static>(self, sink: T) -> Self {
+        self.boxed_sink(Box::new(sink))
+    }
+
+    pub fn boxed_sink(mut self, sink: Box<dyn ReadingSink + Send>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Access to the source, e.g. to change the mode of the chip
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Reads once and publishes the reading unless a stage dropped it. Returns the published reading.
+    pub fn run_once(&mut self) -> Result<Option<Ccs811Data>, PipelineError> {
+        let data = match self.source.next_reading() {
+            Ok(data) => data,
+            Err(error) => {
+                // The error of the source is more important than failing to record it
+                let _ = self.sinks.record_error(&error);
+                return Err(PipelineError::Source(error));
+            }
+        };
+
+        let data = match self.stages.iter_mut().try_fold(data, |data, stage| stage.process(data)) {
+            Some(data) => data,
+            None => return Ok(None)
+        };
+
+        self.sinks.publish(&data)
+            .map_err(PipelineError::Sink)?;
+
+        Ok(Some(data))
+    }
+
+    /// Flushes all sinks, e.g. before shutting down
+    pub fn flush(&mut self) -> Result<(), PipelineError> {
+        self.sinks.flush()
+            .map_err(PipelineError::Sink)
+    }
+}
@@ -0,0 +1,62 @@
+use ::polars::prelude::{Column, DataFrame, DataType, PolarsResult, TimeUnit, TimeZone};
+use std::convert::TryFrom;
+use std::path::Path;
+use crate::file::read_log;
+use crate::format::LoggedReading;
+use crate::history::History;
+
+/// Converts the readings of the ring buffer of the driver to a data frame, oldest first. The readings
+/// have no timestamp, but `since_start` tells when they were sampled relative to `start()`. Columns are
+/// `e_co2`, `t_voc`, `warming_up`, `current_ua`, `adc` and `since_start`, which is null in Idle mode.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+/// let mut ccs811 = ccs811::new(i2c, None).with_history(3600);
+///
+/// // ... read for a while
+///
+/// let frame = ccs811::polars::from_history(ccs811.history()).expect("Could not convert history");
+/// println!("{}", frame);
+/// ```
+pub fn from_history(history: &History) -> PolarsResult<DataFrame> {
+    let since_start = history.iter()
+        .map(|data| data.since_start.map(|since_start| i64::try_from(since_start.as_millis()).unwrap_or(i64::MAX)))
+        .collect::<Vec<_>>();
+
+    DataFrame::new(vec![
+        Column::new("e_co2".into(), history.iter().map(|data| data.e_co2).collect::<Vec<_>>()),
+        Column::new("t_voc".into(), history.iter().map(|data| data.t_voc).collect::<Vec<_>>()),
+        Column::new("warming_up".into(), history.iter().map(|data| data.is_warming_up()).collect::<Vec<_>>()),
+        Column::new("current_ua".into(), history.iter().map(|data| data.raw.current_ua()).collect::<Vec<_>>()),
+        Column::new("adc".into(), history.iter().map(|data| data.raw.adc()).collect::<Vec<_>>()),
+        Column::new("since_start".into(), since_start).cast(&DataType::Duration(TimeUnit::Milliseconds))?
+    ])
+}
+
+/// Converts readings parsed from a log to a data frame. Columns are `timestamp` in milliseconds UTC,
+/// `e_co2`, `t_voc` and `warming_up`.
+pub fn from_log(readings: &[LoggedReading]) -> PolarsResult<DataFrame> {
+    let timestamps = readings.iter().map(LoggedReading::timestamp_millis).collect::<Vec<_>>();
+
+    DataFrame::new(vec![
+        Column::new("timestamp".into(), timestamps)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, Some(TimeZone::UTC)))?,
+        Column::new("e_co2".into(), readings.iter().map(|reading| reading.e_co2).collect::<Vec<_>>()),
+        Column::new("t_voc".into(), readings.iter().map(|reading| reading.t_voc).collect::<Vec<_>>()),
+        Column::new("warming_up".into(), readings.iter().map(|reading| reading.warming_up).collect::<Vec<_>>())
+    ])
+}
+
+/// Reads a log written by `FileSink` into a data frame, see `from_log()` and `file::read_log()`
+///
+/// # Examples
+///
+/// ```no_run
+/// let frame = ccs811::polars::read_log_file("/var/log/ccs811/readings.csv").expect("Could not read log");
+/// println!("{} readings", frame.height());
+/// ```
+pub fn read_log_file<P: AsRef<Path>>(path: P) -> PolarsResult<DataFrame> {
+    from_log(&read_log(path)?)
+}
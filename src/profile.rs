@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::time::Duration;
+use crate::chip::CCS811;
+use crate::diagnostics::TransactionKind;
+
+/// Distribution of the durations of one kind of operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Percentiles {
+    /// Number of recorded durations
+    pub count: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration
+}
+
+impl Percentiles {
+
+    fn from_durations(durations: &VecDeque<Duration>) -> Percentiles {
+        let mut sorted: Vec<Duration> = durations.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest rank, so p99 of less than 100 durations is the maximum
+        let percentile = |percent: usize| {
+            let rank = sorted.len().saturating_mul(percent).div_ceil(100);
+            sorted.get(rank.saturating_sub(1)).copied().unwrap_or_default()
+        };
+
+        Percentiles {
+            count: sorted.len(),
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: sorted.last().copied().unwrap_or_default()
+        }
+    }
+}
+
+impl Display for Percentiles {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} samples, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}", self.count, self.p50, self.p90, self.p99, self.max)
+    }
+}
+
+/// Durations of the I2C transactions and wake-ups recorded by the profiler, see
+/// `CCS811::with_profiler()`. Prints one line per kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    /// Register reads including retries
+    pub read: Percentiles,
+    /// Register writes including retries
+    pub write: Percentiles,
+    /// Setting the wake pin including the wait until the chip is awake
+    pub wake: Percentiles
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<8}{}", "Read", self.read)?;
+        writeln!(f, "{:<8}{}", "Write", self.write)?;
+        writeln!(f, "{:<8}{}", "Wake", self.wake)
+    }
+}
+
+/// Keeps the last durations of each kind
+pub(crate) struct Profiler {
+    capacity: usize,
+    reads: VecDeque<Duration>,
+    writes: VecDeque<Duration>,
+    wakes: VecDeque<Duration>
+}
+
+fn push(durations: &mut VecDeque<Duration>, capacity: usize, duration: Duration) {
+    if durations.len() == capacity {
+        durations.pop_front();
+    }
+    durations.push_back(duration);
+}
+
+impl Profiler {
+
+    fn new(capacity: usize) -> Profiler {
+        Profiler { capacity, reads: VecDeque::new(), writes: VecDeque::new(), wakes: VecDeque::new() }
+    }
+
+    pub(crate) fn record(&mut self, kind: TransactionKind, duration: Duration) {
+        match kind {
+            TransactionKind::Read => push(&mut self.reads, self.capacity, duration),
+            TransactionKind::Write => push(&mut self.writes, self.capacity, duration)
+        }
+    }
+
+    pub(crate) fn record_wake(&mut self, duration: Duration) {
+        push(&mut self.wakes, self.capacity, duration);
+    }
+}
+
+impl<I2C, WAKE, RESET> CCS811<I2C, WAKE, RESET> {
+
+    /// Records the durations of the last `capacity` reads, writes and wake-ups for `profile()`, e.g. to
+    /// find out why a read loop occasionally blocks. A capacity of 0 disables the profiler, which is the
+    /// default.
+    pub fn with_profiler(mut self, capacity: usize) -> Self {
+        self.profiler = match capacity {
+            0 => None,
+            capacity => Some(Profiler::new(capacity))
+        };
+        self
+    }
+
+    /// Percentiles of the recorded durations, `None` unless enabled with `with_profiler()`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+    /// let mut ccs811 = ccs811::new(i2c, None).with_profiler(1000);
+    ///
+    /// // After a while of reading
+    /// if let Some(profile) = ccs811.profile() {
+    ///   println!("{}", profile);
+    /// }
+    /// ```
+    pub fn profile(&self) -> Option<Profile> {
+        self.profiler.as_ref().map(|profiler| Profile {
+            read: Percentiles::from_durations(&profiler.reads),
+            write: Percentiles::from_durations(&profiler.writes),
+            wake: Percentiles::from_durations(&profiler.wakes)
+        })
+    }
+}
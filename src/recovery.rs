@@ -0,0 +1,300 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::fmt::{self, Display};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use crate::chip::CCS811;
+use crate::constants::*;
+use crate::error::{Ccs811Error, SourceError};
+
+// Time for the supply to settle between two attempts to re-initialize a browned out chip
+const RECOVERY_DELAY: Duration = Duration::from_millis(100);
+
+/// Why the driver considers the chip lost
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossReason {
+    /// The chip doesn't answer anymore, e.g. because it was unplugged
+    NoResponse,
+    /// Another HW_ID than 0x81 was read, 0xFF if nothing drives the bus
+    HardwareId(u8),
+    /// The chip is back in boot loader mode, it was reset by a brown-out
+    BootMode
+}
+
+impl Display for LossReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LossReason::NoResponse => write!(f, "chip doesn't respond"),
+            LossReason::HardwareId(hw_id) => write!(f, "HWID is {:#x}", hw_id),
+            LossReason::BootMode => write!(f, "chip is in boot mode")
+        }
+    }
+}
+
+/// Passed to the callback of `on_recovery()`
+#[derive(Debug)]
+pub enum RecoveryEvent<'a> {
+    /// A read found the chip lost, the driver is degraded until it's re-initialized
+    Lost(LossReason),
+    /// The chip was re-initialized and sampling continues
+    Recovered { attempts: u8 },
+    /// All attempts failed, the next read tries again
+    Failed { attempts: u8, error: &'a Ccs811Error },
+    /// The chip left the bus, reads fail with `Ccs811Error::Disconnected` until it's back, see
+    /// `with_hot_swap()`
+    Disconnected,
+    /// The chip is back on the bus and was re-initialized after being gone for `outage`
+    Reconnected { outage: Duration }
+}
+
+type RecoveryCallback = Box<dyn FnMut(&RecoveryEvent) + Send>;
+
+#[derive(Default)]
+pub(crate) struct AutoRecovery {
+    attempts: u8,
+    callback: Option<RecoveryCallback>,
+    degraded: bool,
+    hot_swap: Option<HotSwap>
+}
+
+struct HotSwap {
+    failures: u8,
+    probe_interval: Duration,
+    // Transport failures in a row since the last successful read
+    consecutive: u8,
+    disconnected: Option<Disconnection>
+}
+
+#[derive(Clone, Copy)]
+struct Disconnection {
+    since: Instant,
+    last_probe: Instant
+}
+
+impl AutoRecovery {
+
+    fn notify(&mut self, event: RecoveryEvent) {
+        if let Some(callback) = &mut self.callback {
+            callback(&event);
+        }
+    }
+}
+
+impl<I2C, WAKE, RESET> CCS811<I2C, WAKE, RESET> {
+
+    /// Re-initializes the chip when `read()` finds it browned out or replugged: the result reads 0xFF,
+    /// the HW_ID is wrong or the chip fell back into boot mode. Up to `attempts` times it's reset (with
+    /// the reset pin if there is one), the application is started and the mode, the baseline set with
+    /// `set_baseline()` and the environment set with `set_env_data()` are restored. Disabled with 0
+    /// attempts, which is the default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+    /// use ccs811::recovery::RecoveryEvent;
+    ///
+    /// let mut ccs811 = ccs811::new(i2c, None)
+    ///     .with_auto_recovery(3)
+    ///     .on_recovery(|event| match event {
+    ///       RecoveryEvent::Lost(reason) => println!("Lost the chip: {}", reason),
+    ///       RecoveryEvent::Recovered { attempts } => println!("Recovered after {} attempts", attempts),
+    ///       RecoveryEvent::Failed { error, .. } => println!("Could not recover: {}", error),
+    ///       RecoveryEvent::Disconnected => println!("The chip left the bus"),
+    ///       RecoveryEvent::Reconnected { outage } => println!("The chip is back after {:?}", outage)
+    ///     });
+    /// ```
+    pub fn with_auto_recovery(mut self, attempts: u8) -> Self {
+        self.auto_recovery.attempts = attempts;
+        self
+    }
+
+    /// Called when the chip got lost, when recovering it succeeded or failed and when it left or returned
+    /// to the bus with `with_hot_swap()`
+    pub fn on_recovery<F>(mut self, callback: F) -> Self where F: FnMut(&RecoveryEvent) + Send + 'static {
+        self.auto_recovery.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Considers the chip gone from the bus after `failures` reads in a row failed on the bus and it
+    /// doesn't answer with its HW_ID anymore, e.g. when it's unplugged. Instead of trying to reach it
+    /// with every read, reads fail with `Ccs811Error::Disconnected` at once and the bus is probed for the
+    /// chip at most every `probe_interval`. When it's back, it's re-initialized like by
+    /// `with_auto_recovery()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+    /// use std::time::Duration;
+    /// use ccs811::recovery::RecoveryEvent;
+    ///
+    /// let mut ccs811 = ccs811::new(i2c, None)
+    ///     .with_hot_swap(3, Duration::from_secs(10))
+    ///     .on_recovery(|event| match event {
+    ///       RecoveryEvent::Disconnected => println!("Sensor unplugged"),
+    ///       RecoveryEvent::Reconnected { outage } => println!("Sensor back after {}s", outage.as_secs()),
+    ///       _ => ()
+    ///     });
+    /// ```
+    pub fn with_hot_swap(mut self, failures: u8, probe_interval: Duration) -> Self {
+        self.auto_recovery.hot_swap = Some(HotSwap { failures, probe_interval, consecutive: 0, disconnected: None });
+        self
+    }
+
+    /// Whether the chip got lost and couldn't be re-initialized yet
+    pub fn is_degraded(&self) -> bool {
+        self.auto_recovery.degraded
+    }
+
+    /// Whether the chip left the bus and hasn't come back yet, see `with_hot_swap()`
+    pub fn is_disconnected(&self) -> bool {
+        self.auto_recovery.hot_swap.as_ref().is_some_and(|hot_swap| hot_swap.disconnected.is_some())
+    }
+}
+
+impl<I2C, WAKE, RESET, E> CCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError,
+          WAKE: OutputPin,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin,
+          <RESET as OutputPin>::Error: SourceError {
+
+    /// Checks the HW_ID and status if the result of a read looks like the chip got lost. Returns
+    /// `None` if it's still there or auto recovery is disabled.
+    pub(crate) fn check_lost(&mut self, result: &Result<[u8; 8], Ccs811Error>) -> Option<LossReason> {
+        let suspicious = match result {
+            Ok(buffer) => buffer.iter().all(|&byte| byte == 0xFF) || buffer[4] & CCS811_STATUS_APP_MODE == 0,
+            Err(Ccs811Error::I2c { .. }) => true,
+            Err(_) => false
+        };
+        if self.auto_recovery.attempts == 0 || !suspicious {
+            return None;
+        }
+
+        if self.awake().is_err() {
+            return None;
+        }
+        let hw_id = self.read_register_byte(CCS811_HW_ID);
+        let status = self.read_register_byte(CCS811_STATUS);
+        let _ = self.sleep();
+
+        match (hw_id, status) {
+            (Err(_), _) => Some(LossReason::NoResponse),
+            (Ok(hw_id), _) if hw_id != 0x81 => Some(LossReason::HardwareId(hw_id)),
+            (Ok(_), Ok(status)) if status & CCS811_STATUS_APP_MODE == 0 => Some(LossReason::BootMode),
+            _ => None
+        }
+    }
+
+    /// Re-initializes the lost chip and restores mode and baseline, gives up after the configured
+    /// number of attempts with the error of the last one
+    pub(crate) fn recover(&mut self, reason: LossReason) -> Result<(), Ccs811Error> {
+        self.auto_recovery.degraded = true;
+        self.auto_recovery.notify(RecoveryEvent::Lost(reason));
+
+        let (mode, baseline) = (self.mode(), self.baseline);
+        let mut attempts: u8 = 0;
+        loop {
+            attempts = attempts.saturating_add(1);
+            match self.reinitialize(mode, baseline) {
+                Ok(()) => {
+                    self.auto_recovery.degraded = false;
+                    self.auto_recovery.notify(RecoveryEvent::Recovered { attempts });
+                    return Ok(());
+                },
+                Err(error) if attempts >= self.auto_recovery.attempts => {
+                    self.auto_recovery.notify(RecoveryEvent::Failed { attempts, error: &error });
+                    return Err(error);
+                },
+                Err(_) => sleep(RECOVERY_DELAY)
+            }
+        }
+    }
+
+    fn reinitialize(&mut self, mode: Ccs811Mode, baseline: Option<u16>) -> Result<(), Ccs811Error> {
+        if self.reset.is_some() {
+            self.hard_reset()?;
+        }
+        self.begin()?;
+        self.start(mode)?;
+        if let Some(baseline) = baseline {
+            self.restore_baseline(baseline)?;
+        }
+
+        self.restore_env_data()
+    }
+
+    /// Fails with `Ccs811Error::Disconnected` while the chip is gone, probing for it at most every
+    /// probe interval. A chip which is back is re-initialized.
+    pub(crate) fn check_reconnected(&mut self) -> Result<(), Ccs811Error> {
+        let (probe_interval, disconnection) = match &self.auto_recovery.hot_swap {
+            Some(HotSwap { probe_interval, disconnected: Some(disconnection), .. }) => (*probe_interval, *disconnection),
+            _ => return Ok(())
+        };
+        let outage = disconnection.since.elapsed();
+        if disconnection.last_probe.elapsed() < probe_interval {
+            return Err(Ccs811Error::Disconnected { outage });
+        }
+
+        let (mode, baseline) = (self.mode(), self.baseline);
+        let reconnected = self.probe() && self.reinitialize(mode, baseline).is_ok();
+        if let Some(hot_swap) = &mut self.auto_recovery.hot_swap {
+            hot_swap.disconnected = match reconnected {
+                true => None,
+                false => Some(Disconnection { last_probe: Instant::now(), ..disconnection })
+            };
+            hot_swap.consecutive = 0;
+        }
+        if !reconnected {
+            return Err(Ccs811Error::Disconnected { outage });
+        }
+
+        self.auto_recovery.degraded = false;
+        self.auto_recovery.notify(RecoveryEvent::Reconnected { outage });
+        Ok(())
+    }
+
+    /// Counts the reads failing on the bus in a row and turns the failure into
+    /// `Ccs811Error::Disconnected` once the chip doesn't answer anymore
+    pub(crate) fn track_presence<T>(&mut self, result: Result<T, Ccs811Error>) -> Result<T, Ccs811Error> {
+        let transport_failure = matches!(result, Err(Ccs811Error::I2c { .. }) | Err(Ccs811Error::HardwareId(0xFF)));
+        match &mut self.auto_recovery.hot_swap {
+            Some(hot_swap) if transport_failure => {
+                hot_swap.consecutive = hot_swap.consecutive.saturating_add(1);
+                if hot_swap.consecutive < hot_swap.failures {
+                    return result;
+                }
+            },
+            Some(hot_swap) => {
+                hot_swap.consecutive = 0;
+                return result;
+            },
+            None => return result
+        }
+        if self.probe() {
+            return result;
+        }
+
+        let now = Instant::now();
+        if let Some(hot_swap) = &mut self.auto_recovery.hot_swap {
+            hot_swap.disconnected = Some(Disconnection { since: now, last_probe: now });
+        }
+        self.auto_recovery.degraded = true;
+        self.auto_recovery.notify(RecoveryEvent::Disconnected);
+
+        Err(Ccs811Error::Disconnected { outage: Duration::ZERO })
+    }
+
+    /// Whether the chip answers with its HW_ID
+    fn probe(&mut self) -> bool {
+        if self.awake().is_err() {
+            return false;
+        }
+        let hw_id = self.read_register_byte(CCS811_HW_ID);
+        let _ = self.sleep();
+
+        matches!(hw_id, Ok(0x81))
+    }
+}
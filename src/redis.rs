@@ -0,0 +1,90 @@
+use ::redis::{Client, Connection, RedisResult};
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime};
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::format::Format;
+use crate::sink::ReadingSink;
+
+// Connecting and every command give up after this time, so a dead server doesn't block the read loop
+const REDIS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Publishes every reading as JSON like `{"e_co2":812,"t_voc":63,"warming_up":false,"timestamp":1700000000}`
+/// to a Redis channel. Optionally the reading is also stored in a key which expires, so dashboards can
+/// `GET` the latest reading and tell a dead sensor by the missing key.
+///
+/// The connection is opened with the first reading. If a command fails, the next reading reconnects.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use std::time::Duration;
+/// use ccs811::redis::RedisSink;
+///
+/// let mut redis = RedisSink::new("redis://127.0.0.1/", "home/air")
+///     .expect("Invalid Redis URL")
+///     .with_latest("home/air/latest", Duration::from_secs(300));
+///
+/// redis.publish(&ccs811.read().expect("Could not read data")).expect("Could not publish");
+/// ```
+pub struct RedisSink {
+    client: Client,
+    connection: Option<Connection>,
+    channel: String,
+    latest: Option<(String, Duration)>
+}
+
+impl RedisSink {
+
+    /// Only checks the URL like `redis://:password@host:6379/0`, doesn't connect yet
+    pub fn new(url: &str, channel: &str) -> RedisResult<Self> {
+        Ok(RedisSink {
+            client: Client::open(url)?,
+            connection: None,
+            channel: channel.to_string(),
+            latest: None
+        })
+    }
+
+    /// Sets the key to the latest reading, which expires after the TTL unless a newer one arrives
+    pub fn with_latest(self, key: &str, ttl: Duration) -> Self {
+        RedisSink { latest: Some((key.to_string(), ttl)), ..self }
+    }
+
+    pub fn publish(&mut self, data: &Ccs811Data) -> RedisResult<()> {
+        let payload = Format::Json.format(data, SystemTime::now());
+
+        let mut pipe = ::redis::pipe();
+        pipe.cmd("PUBLISH").arg(&self.channel).arg(&payload).ignore();
+        if let Some((key, ttl)) = &self.latest {
+            let ttl_ms = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1);
+            pipe.cmd("SET").arg(key).arg(&payload).arg("PX").arg(ttl_ms).ignore();
+        }
+
+        let connection = match &mut self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(self.connect()?)
+        };
+        let result = pipe.query::<()>(connection);
+        if result.is_err() {
+            self.connection = None;
+        }
+
+        result
+    }
+
+    fn connect(&self) -> RedisResult<Connection> {
+        let connection = self.client.get_connection_with_timeout(REDIS_TIMEOUT)?;
+        connection.set_read_timeout(Some(REDIS_TIMEOUT))?;
+        connection.set_write_timeout(Some(REDIS_TIMEOUT))?;
+
+        Ok(connection)
+    }
+}
+
+impl ReadingSink for RedisSink {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        Ok(RedisSink::publish(self, data)?)
+    }
+}
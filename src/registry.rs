@@ -0,0 +1,272 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use crate::MODE;
+use crate::calibration::{CalibrationProfile, ProfileStore};
+use crate::chip::Address;
+use crate::pipeline::{OutlierRejection, Stage, Validity};
+#[cfg(feature = "rppal")]
+use rppal::i2c::I2c;
+#[cfg(feature = "rppal")]
+use crate::{chip::CCS811, error::Ccs811Error, rppal::RppalPin};
+
+/// Which readings of a sensor to pass on. Parses from `none`, `validity` or `outlier <window>
+/// <max eCO2> <max tVOC>`, e.g. `outlier 5 1000 500`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Validation {
+    /// Pass on every reading
+    #[default]
+    None,
+    /// Drop readings which aren't `is_valid()`, see `pipeline::Validity`
+    Validity,
+    /// Drop readings deviating from the median of the last readings, see `pipeline::OutlierRejection`
+    Outlier { window: usize, max_e_co2: u16, max_t_voc: u16 }
+}
+
+impl Validation {
+
+    /// Pipeline stage applying the validation, `None` if every reading passes
+    pub fn stage(&self) -> Option<Box<dyn Stage + Send>> {
+        match *self {
+            Validation::None => None,
+            Validation::Validity => Some(Box::new(Validity)),
+            Validation::Outlier { window, max_e_co2, max_t_voc } => {
+                Some(Box::new(OutlierRejection::new(window, max_e_co2, max_t_voc)))
+            }
+        }
+    }
+}
+
+impl FromStr for Validation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Unknown validation {}, use none, validity or outlier <window> <max eCO2> <max tVOC>", value);
+        let mut words = value.split_whitespace();
+
+        let validation = match words.next() {
+            Some("none") => Validation::None,
+            Some("validity") => Validation::Validity,
+            Some("outlier") => match (words.next(), words.next(), words.next()) {
+                (Some(window), Some(max_e_co2), Some(max_t_voc)) => Validation::Outlier {
+                    window: window.parse().map_err(|_| invalid())?,
+                    max_e_co2: max_e_co2.parse().map_err(|_| invalid())?,
+                    max_t_voc: max_t_voc.parse().map_err(|_| invalid())?
+                },
+                _ => return Err(invalid())
+            },
+            _ => return Err(invalid())
+        };
+        match words.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(validation)
+        }
+    }
+}
+
+/// How one chip of a deployment is connected and sampled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SensorConfig {
+    /// Name of the sensor, e.g. the room it's in
+    pub label: String,
+    /// I2C bus the chip is connected to, 1 by default
+    pub bus: u8,
+    pub address: Address,
+    /// BCM number of the GPIO connected to nWAKE, `None` if nWAKE is tied to ground
+    pub wake_pin: Option<u8>,
+    /// Mode to sample in, 1s by default
+    pub mode: MODE,
+    pub validation: Validation,
+    /// Name of the calibration profile to apply on start, see `SensorRegistry::profile()`
+    pub profile: Option<String>
+}
+
+impl SensorConfig {
+
+    /// Chip at 0x5A on bus 1 without wake pin, sampling every second
+    pub fn new(label: &str) -> Self {
+        SensorConfig {
+            label: label.to_string(),
+            bus: 1,
+            address: Address::Low,
+            wake_pin: None,
+            mode: MODE::Sec1,
+            validation: Validation::None,
+            profile: None
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |error: &dyn std::fmt::Display| format!("Sensor {} has an invalid {}: {}", self.label, key, error);
+
+        match key {
+            "bus" => self.bus = value.parse().map_err(|error| invalid(&error))?,
+            "address" => self.address = parse_address(value).map_err(|error| invalid(&error))?,
+            "wake_pin" => self.wake_pin = Some(value.parse().map_err(|error| invalid(&error))?),
+            "mode" => self.mode = value.parse().map_err(|error| invalid(&error))?,
+            "validation" => self.validation = value.parse().map_err(|error| invalid(&error))?,
+            "profile" => self.profile = Some(value.to_string()),
+            _ => return Err(format!("Sensor {} has an unknown setting {}", self.label, key))
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl SensorConfig {
+
+    /// Opens the bus and wake pin of the sensor and initializes the chip, see `CCS811::open()`
+    pub fn open(&self) -> Result<CCS811<I2c, RppalPin>, Ccs811Error> {
+        CCS811::open(self.bus, self.address, self.wake_pin)
+    }
+}
+
+/// `0x5a`, `0x5b`, `low` or `high`
+fn parse_address(value: &str) -> Result<Address, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "low" => Ok(Address::Low),
+        "high" => Ok(Address::High),
+        hex => u8::from_str_radix(hex.trim_start_matches("0x"), 16).ok()
+            .and_then(|address| Address::try_from(address).ok())
+            .ok_or_else(|| format!("{} is neither 0x5a nor 0x5b", value))
+    }
+}
+
+/// The chips of a deployment with several sensors, shared by own programs, `ccs811d` and `ccs811ctl`.
+/// Labels as well as bus and address have to be unique.
+///
+/// Registries are loaded from files with a section per sensor, all settings are optional:
+///
+/// ```text
+/// # Directory of the calibration profiles, see ccs811::calibration::ProfileStore
+/// profiles = /var/lib/ccs811/profiles
+///
+/// [kitchen]
+/// bus = 1
+/// address = 0x5a
+/// wake_pin = 17
+/// mode = 60s
+/// validation = validity
+/// profile = kitchen
+///
+/// [bedroom]
+/// address = 0x5b
+/// validation = outlier 5 1000 500
+/// ```
+///
+/// # Examples
+///
+/// ```no_run
+/// use ccs811::registry::SensorRegistry;
+///
+/// let registry = SensorRegistry::load("/etc/ccs811/sensors.conf").expect("Could not load sensors");
+/// let kitchen = registry.sensor("kitchen").expect("No sensor kitchen");
+///
+/// let mut ccs811 = kitchen.open().expect("Could not open the chip");
+/// if let Some(profile) = registry.profile(kitchen).expect("Could not load profile") {
+///   profile.apply(&mut ccs811).expect("Could not apply profile");
+/// }
+/// ccs811.start(kitchen.mode).expect("Could not start");
+/// ```
+#[derive(Default)]
+pub struct SensorRegistry {
+    sensors: Vec<SensorConfig>,
+    profiles: Option<ProfileStore>
+}
+
+impl SensorRegistry {
+
+    pub fn new() -> Self {
+        SensorRegistry::default()
+    }
+
+    /// Loads the registry, a file not containing a registry is reported as `io::ErrorKind::InvalidData`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::read_to_string(path)?.parse().map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Directory of the calibration profiles the sensors refer to
+    pub fn with_profiles<P: AsRef<Path>>(self, directory: P) -> Self {
+        SensorRegistry { profiles: Some(ProfileStore::new(directory)), ..self }
+    }
+
+    /// Adds the sensor unless its label or bus and address are taken by another sensor
+    pub fn add(&mut self, sensor: SensorConfig) -> Result<(), String> {
+        if let Some(other) = self.sensors.iter().find(|other| other.label == sensor.label) {
+            return Err(format!("Sensor {} is registered twice", other.label));
+        }
+        if let Some(other) = self.sensors.iter().find(|other| (other.bus, other.address) == (sensor.bus, sensor.address)) {
+            return Err(format!("Sensors {} and {} share bus {} and address 0x{:02x}",
+                other.label, sensor.label, sensor.bus, u8::from(sensor.address)));
+        }
+
+        self.sensors.push(sensor);
+        Ok(())
+    }
+
+    pub fn sensor(&self, label: &str) -> Option<&SensorConfig> {
+        self.sensors.iter().find(|sensor| sensor.label == label)
+    }
+
+    /// The sensors in the order they were added
+    pub fn sensors(&self) -> &[SensorConfig] {
+        &self.sensors
+    }
+
+    pub fn profiles(&self) -> Option<&ProfileStore> {
+        self.profiles.as_ref()
+    }
+
+    /// Loads the calibration profile of the sensor, `None` if it has none. A sensor with a profile in a
+    /// registry without profile directory is reported as `io::ErrorKind::NotFound`.
+    pub fn profile(&self, sensor: &SensorConfig) -> io::Result<Option<CalibrationProfile>> {
+        let name = match &sensor.profile {
+            Some(name) => name,
+            None => return Ok(None)
+        };
+        let store = self.profiles.as_ref().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound,
+            format!("Sensor {} has the profile {} but no profiles directory is configured", sensor.label, name)))?;
+
+        store.load(name).map(Some)
+    }
+}
+
+impl FromStr for SensorRegistry {
+    type Err = String;
+
+    fn from_str(file: &str) -> Result<Self, String> {
+        let mut registry = SensorRegistry::new();
+        let mut sensor: Option<SensorConfig> = None;
+
+        for (number, line) in file.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(label) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                if let Some(sensor) = sensor.replace(SensorConfig::new(label.trim())) {
+                    registry.add(sensor)?;
+                }
+                continue;
+            }
+
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| format!("Line {} is neither [sensor] nor key = value", number.saturating_add(1)))?;
+            let (key, value) = (key.trim(), value.trim());
+            match &mut sensor {
+                Some(sensor) => sensor.set(key, value)?,
+                None if key == "profiles" => registry = registry.with_profiles(value),
+                None => return Err(format!("Unknown setting {} before the first [sensor]", key))
+            }
+        }
+        if let Some(sensor) = sensor {
+            registry.add(sensor)?;
+        }
+
+        Ok(registry)
+    }
+}
@@ -0,0 +1,247 @@
+use embedded_hal::blocking::i2c;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use crate::error::is_clock_stretching;
+
+// Operations of a request frame: op, address, register, length and the data of a write. A write
+// without data is a command like APP_START.
+const OP_READ: u8 = 0x01;
+const OP_WRITE: u8 = 0x02;
+
+// Status of a response frame: status, length and the read data or the error message
+const STATUS_OK: u8 = 0x00;
+const STATUS_ERROR: u8 = 0x01;
+const STATUS_TIMEOUT: u8 = 0x02;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// I2C bus forwarding the register reads and writes over TCP to a `Bridge` on the Raspberry Pi, so
+/// the full driver including `begin()` and `read()` runs on a workstation against the remote chip. The
+/// wake pin isn't forwarded, so nWAKE has to be tied low.
+///
+/// Each operation is a round trip, the driver isn't slower than on the Pi as long as the latency is
+/// well below its delays of a few milliseconds. Errors of the remote bus are reported as `io::Error`,
+/// timeouts of a stretched clock as `io::ErrorKind::TimedOut` so the driver retries them as usual. A
+/// broken connection is re-established on the next operation.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ccs811::chip::{CCS811, NoPin};
+/// use ccs811::remote::RemoteBus;
+///
+/// let bus = RemoteBus::connect("raspberrypi.local:8811").expect("Could not connect to the bridge");
+/// let mut ccs811 = CCS811::new(bus, None::<NoPin>);
+///
+/// ccs811.begin().expect("Could not init the chip");
+/// ccs811.start(ccs811::MODE::Sec1).expect("Could not start");
+/// ```
+pub struct RemoteBus {
+    addresses: Vec<SocketAddr>,
+    stream: Option<TcpStream>,
+    timeout: Duration
+}
+
+impl RemoteBus {
+
+    /// Connects to the bridge, e.g. at `raspberrypi.local:8811`
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let mut bus = RemoteBus { addresses: address.to_socket_addrs()?.collect(), stream: None, timeout: DEFAULT_TIMEOUT };
+        bus.stream()?;
+
+        Ok(bus)
+    }
+
+    /// How long to wait for the bridge to answer, 5 seconds by default
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        if let Some(stream) = &self.stream {
+            let _ = stream.set_read_timeout(Some(timeout));
+        }
+        RemoteBus { timeout, ..self }
+    }
+
+    fn stream(&mut self) -> io::Result<&mut TcpStream> {
+        if self.stream.is_none() {
+            let stream = TcpStream::connect(self.addresses.as_slice())?;
+            // Every frame is a round trip, waiting for more data to send only adds latency
+            stream.set_nodelay(true)?;
+            stream.set_read_timeout(Some(self.timeout))?;
+            self.stream = Some(stream);
+        }
+
+        self.stream.as_mut().ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "Not connected to the bridge"))
+    }
+
+    /// Sends the request and reads the data of the response into the buffer
+    fn transact(&mut self, request: &[u8], buffer: &mut [u8]) -> io::Result<()> {
+        let result = self.stream().and_then(|stream| {
+            stream.write_all(request)?;
+            read_response(stream, buffer)
+        });
+        // Only errors of the remote bus leave the connection in a known state
+        if let Err(error) = &result {
+            if error.get_ref().is_none_or(|error| !error.is::<RemoteError>()) {
+                self.stream = None;
+            }
+        }
+
+        result
+    }
+}
+
+/// Error the bridge reported for the bus of the Raspberry Pi
+#[derive(Debug)]
+struct RemoteError(String);
+
+impl std::fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Remote bus: {}", self.0)
+    }
+}
+
+impl std::error::Error for RemoteError {}
+
+fn read_response(stream: &mut TcpStream, buffer: &mut [u8]) -> io::Result<()> {
+    let mut header = [0; 2];
+    stream.read_exact(&mut header)?;
+    let [status, length] = header;
+    let mut payload = vec![0; usize::from(length)];
+    stream.read_exact(&mut payload)?;
+
+    match status {
+        STATUS_OK if payload.len() == buffer.len() => {
+            buffer.copy_from_slice(&payload);
+            Ok(())
+        },
+        STATUS_OK => Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("The bridge sent {} bytes instead of {}", payload.len(), buffer.len()))),
+        STATUS_ERROR | STATUS_TIMEOUT => {
+            let kind = if status == STATUS_TIMEOUT { io::ErrorKind::TimedOut } else { io::ErrorKind::Other };
+            Err(io::Error::new(kind, RemoteError(String::from_utf8_lossy(&payload).into_owned())))
+        },
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown status {:#04x} from the bridge", status)))
+    }
+}
+
+fn length(bytes: &[u8]) -> io::Result<u8> {
+    u8::try_from(bytes.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput,
+        format!("{} bytes are more than a frame takes", bytes.len())))
+}
+
+/// The driver writes the register followed by its data, the frame carries them separately
+impl i2c::Write for RemoteBus {
+    type Error = io::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> io::Result<()> {
+        let (register, data) = bytes.split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "A write needs at least the register"))?;
+        let request: Vec<u8> = [OP_WRITE, address, *register, length(data)?].iter().chain(data).copied().collect();
+        self.transact(&request, &mut [])
+    }
+}
+
+/// Only reads of a register are supported, which are the only ones the driver does
+impl i2c::WriteRead for RemoteBus {
+    type Error = io::Error;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> io::Result<()> {
+        let register = match bytes {
+            [register] => *register,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Only reads of a single register are forwarded"))
+        };
+        let length = length(buffer)?;
+        self.transact(&[OP_READ, address, register, length], buffer)
+    }
+}
+
+/// Serves the bus of the Raspberry Pi to a `RemoteBus`, e.g. `Bridge::new(I2c::with_bus(1)?)`. Run
+/// it with `ccs811ctl bridge`. Clients are served one after another, as they would disturb each
+/// other's transactions with the chip anyway.
+///
+/// The bridge gives access to every chip on the bus without authentication, so only expose it on a
+/// trusted network.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::TcpListener;
+/// use rppal::i2c::I2c;
+/// use ccs811::remote::Bridge;
+///
+/// let listener = TcpListener::bind("0.0.0.0:8811").expect("Could not listen");
+/// Bridge::new(I2c::with_bus(1).unwrap()).serve(&listener).expect("Could not accept clients");
+/// ```
+pub struct Bridge<B> {
+    bus: B
+}
+
+impl<B, E> Bridge<B> where B: i2c::Write<Error = E> + i2c::WriteRead<Error = E>, E: std::error::Error + 'static {
+
+    pub fn new(bus: B) -> Self {
+        Bridge { bus }
+    }
+
+    /// Serves the clients connecting to the listener until accepting one fails
+    pub fn serve(&mut self, listener: &TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept()?;
+            // A client going away is no reason to stop serving the next one
+            let _ = self.handle(stream);
+        }
+    }
+
+    /// Serves a single client until it disconnects. Malformed requests end the connection with
+    /// `io::ErrorKind::InvalidData`.
+    pub fn handle(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut request = [0; 4];
+        let mut data = [0; 255];
+
+        loop {
+            match stream.read_exact(&mut request) {
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                result => result?
+            }
+            let [op, address, register, length] = request;
+            let data = data.get_mut(..usize::from(length)).unwrap_or(&mut []);
+
+            let result = match op {
+                OP_READ => self.bus.write_read(address, &[register], data),
+                OP_WRITE => {
+                    stream.read_exact(data)?;
+                    let bytes: Vec<u8> = std::iter::once(register).chain(data.iter().copied()).collect();
+                    self.bus.write(address, &bytes)
+                },
+                _ => {
+                    let message = format!("Unknown operation {:#04x}", op);
+                    respond(&mut stream, STATUS_ERROR, message.as_bytes())?;
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                }
+            };
+
+            match result {
+                Ok(()) if op == OP_READ => respond(&mut stream, STATUS_OK, data)?,
+                Ok(()) => respond(&mut stream, STATUS_OK, &[])?,
+                Err(error) => {
+                    let status = if is_clock_stretching(&error) { STATUS_TIMEOUT } else { STATUS_ERROR };
+                    respond(&mut stream, status, error.to_string().as_bytes())?;
+                }
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: u8, payload: &[u8]) -> io::Result<()> {
+    // Error messages are cut off rather than failing the response
+    let payload = payload.get(..usize::from(u8::MAX)).unwrap_or(payload);
+    let length = u8::try_from(payload.len()).unwrap_or(u8::MAX);
+    let response: Vec<u8> = [status, length].iter().chain(payload).copied().collect();
+
+    stream.write_all(&response)
+}
@@ -0,0 +1,220 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+/// How often a failed I2C transaction is repeated and how long to wait in between
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub retries: u8,
+    pub delay: Duration
+}
+
+impl RetryPolicy {
+
+    /// Don't retry at all
+    pub const NONE: RetryPolicy = RetryPolicy { retries: 0, delay: Duration::from_millis(0) };
+
+    pub fn new(retries: u8, delay: Duration) -> Self {
+        RetryPolicy { retries, delay }
+    }
+
+    /// Runs the transaction until it succeeds or the retries are exhausted and returns the last result
+    pub(crate) fn run<T, E>(&self, mut transaction: impl FnMut() -> Result<T, E>) -> Result<T, E> {
+        let mut attempt = 0;
+        loop {
+            match transaction() {
+                Err(_) if attempt < self.retries => {
+                    attempt = attempt.saturating_add(1);
+                    sleep(self.delay);
+                },
+                result => return result
+            }
+        }
+    }
+
+    /// Like `run()`, but if it's the first transaction after waking the chip and it failed with an error
+    /// `is_nack()` accepts, it's repeated once after `settle` without counting against the retries. The
+    /// chip commonly NACKs the first transaction right after nWAKE went low. Returns whether the attempt
+    /// had to be repeated.
+    pub(crate) fn run_after_wake<T, E>(&self, woken: bool, settle: Duration, is_nack: impl Fn(&E) -> bool, mut transaction: impl FnMut() -> Result<T, E>) -> (Result<T, E>, bool) {
+        let mut first = woken;
+        let mut repeated = false;
+        let result = self.run(|| match transaction() {
+            Err(error) if first && is_nack(&error) => {
+                first = false;
+                repeated = true;
+                sleep(settle);
+                transaction()
+            },
+            result => {
+                first = false;
+                result
+            }
+        });
+
+        (result, repeated)
+    }
+
+    /// Repeats a failed transaction as long as `repeat()` accepts its error and retries are left. The delay
+    /// doubles with every attempt. Returns the last result and the number of retries.
+    pub(crate) fn run_backoff<T, E>(&self, result: Result<T, E>, repeat: impl Fn(&E) -> bool, mut transaction: impl FnMut() -> Result<T, E>) -> (Result<T, E>, u8) {
+        let mut result = result;
+        let mut delay = self.delay;
+        let mut attempt = 0;
+        while attempt < self.retries && result.as_ref().err().is_some_and(&repeat) {
+            attempt = attempt.saturating_add(1);
+            sleep(delay);
+            delay = delay.saturating_mul(2);
+            result = transaction();
+        }
+
+        (result, attempt)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::NONE
+    }
+}
+
+/// Retry policies for the different kinds of transactions. By default only transactions failing because
+/// of clock stretching are retried.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+/// use std::time::Duration;
+/// use ccs811::retry::{RetryConfig, RetryPolicy};
+///
+/// let mut ccs811 = ccs811::new(i2c, None).with_retry(RetryConfig {
+///     read: RetryPolicy::new(5, Duration::from_millis(10)),
+///     write: RetryPolicy::new(2, Duration::from_millis(10)),
+///     flash: RetryPolicy::NONE,
+///     clock_stretch: RetryPolicy::new(3, Duration::from_millis(5))
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// Reading registers like the result data, status or versions
+    pub read: RetryPolicy,
+    /// Writing registers like the mode, env data, baseline and commands like reset or app start
+    pub write: RetryPolicy,
+    /// Erasing the application and writing the firmware while flashing
+    pub flash: RetryPolicy,
+    /// Reads and writes failing because the chip stretched the clock longer than the I2C controller
+    /// tolerates. They're retried on top of the other policies with a doubling delay to give the chip
+    /// time, flashing is never retried this way.
+    pub clock_stretch: RetryPolicy
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            read: RetryPolicy::NONE,
+            write: RetryPolicy::NONE,
+            flash: RetryPolicy::NONE,
+            clock_stretch: RetryPolicy::new(3, Duration::from_millis(5))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Error { Nack, Other }
+
+    /// Fails with the errors in order, then succeeds with the number of attempts made
+    fn attempt(errors: &[Error]) -> impl FnMut() -> Result<usize, Error> + '_ {
+        let mut attempts = 0;
+        move || {
+            let error = errors.get(attempts).copied();
+            attempts = attempts.saturating_add(1);
+            error.map_or(Ok(attempts), Err)
+        }
+    }
+
+    fn is_nack(error: &Error) -> bool {
+        *error == Error::Nack
+    }
+
+    #[test]
+    fn run_retries_until_success() {
+        let policy = RetryPolicy::new(2, Duration::ZERO);
+
+        assert_eq!(policy.run(attempt(&[Error::Other, Error::Nack])), Ok(3));
+        assert_eq!(policy.run(attempt(&[])), Ok(1));
+    }
+
+    #[test]
+    fn run_returns_the_last_error() {
+        let policy = RetryPolicy::new(2, Duration::ZERO);
+
+        assert_eq!(policy.run(attempt(&[Error::Nack, Error::Nack, Error::Other])), Err(Error::Other));
+        assert_eq!(RetryPolicy::NONE.run(attempt(&[Error::Nack])), Err(Error::Nack));
+    }
+
+    #[test]
+    fn run_after_wake_repeats_a_nack() {
+        let (result, repeated) = RetryPolicy::NONE.run_after_wake(true, Duration::ZERO, is_nack, attempt(&[Error::Nack]));
+
+        assert_eq!(result, Ok(2));
+        assert!(repeated);
+    }
+
+    #[test]
+    fn run_after_wake_fails_on_other_errors() {
+        let (result, repeated) = RetryPolicy::NONE.run_after_wake(true, Duration::ZERO, is_nack, attempt(&[Error::Other]));
+
+        assert_eq!(result, Err(Error::Other));
+        assert!(!repeated);
+    }
+
+    #[test]
+    fn run_after_wake_repeats_only_the_first_transaction() {
+        let (result, repeated) = RetryPolicy::NONE.run_after_wake(false, Duration::ZERO, is_nack, attempt(&[Error::Nack]));
+        assert_eq!(result, Err(Error::Nack));
+        assert!(!repeated);
+
+        // The repeat doesn't count against the retries
+        let policy = RetryPolicy::new(1, Duration::ZERO);
+        let (result, _) = policy.run_after_wake(true, Duration::ZERO, is_nack, attempt(&[Error::Nack, Error::Nack]));
+        assert_eq!(result, Ok(3));
+    }
+
+    #[test]
+    fn run_backoff_repeats_accepted_errors() {
+        let policy = RetryPolicy::new(3, Duration::ZERO);
+
+        let (result, retries) = policy.run_backoff(Err(Error::Nack), is_nack, attempt(&[Error::Nack]));
+        assert_eq!((result, retries), (Ok(2), 2));
+
+        let (result, retries) = policy.run_backoff(Ok(0), is_nack, attempt(&[]));
+        assert_eq!((result, retries), (Ok(0), 0));
+    }
+
+    #[test]
+    fn run_backoff_stops_on_other_errors_and_exhausted_retries() {
+        let policy = RetryPolicy::new(2, Duration::ZERO);
+
+        let (result, retries) = policy.run_backoff(Err(Error::Nack), is_nack, attempt(&[Error::Other]));
+        assert_eq!((result, retries), (Err(Error::Other), 1));
+
+        let (result, retries) = policy.run_backoff(Err(Error::Nack), is_nack, attempt(&[Error::Nack, Error::Nack]));
+        assert_eq!((result, retries), (Err(Error::Nack), 2));
+    }
+
+    #[test]
+    fn run_backoff_doubles_the_delay() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(10));
+        let started = Instant::now();
+
+        let (result, _) = policy.run_backoff(Err(Error::Nack), is_nack, attempt(&[Error::Nack, Error::Nack]));
+        assert_eq!(result, Err(Error::Nack));
+        // 10ms and 20ms
+        assert!(started.elapsed() >= Duration::from_millis(30));
+    }
+}
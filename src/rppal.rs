@@ -0,0 +1,65 @@
+use ::rppal::gpio::{OutputPin as RpiOutputPin, Pin};
+use embedded_hal::digital::v2::OutputPin;
+use std::convert::Infallible;
+
+/// GPIO of the Raspberry Pi usable as wake or reset pin. rppal 0.11 only implements the deprecated
+/// embedded-hal v1 `OutputPin`, which reaches `digital::v2` through the compatibility shim with `()` as
+/// error. As `()` isn't a `std::error::Error`, the driver can't use such pins directly. Setting a memory
+/// mapped GPIO can't fail, so this adapter's error is `Infallible`.
+///
+/// `ccs811::new()` and `CCS811::open()` wrap the wake pin already.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+/// use rppal::gpio::Gpio;
+/// use ccs811::rppal::RppalPin;
+///
+/// let reset_pin = Gpio::new().expect("Can not init gpio")
+///                     .get(27).expect("Could not attach to reset pin");
+///
+/// let mut ccs811 = ccs811::new(i2c, None).with_reset(RppalPin::from(reset_pin));
+/// ```
+#[derive(Debug)]
+pub struct RppalPin {
+    pin: RpiOutputPin
+}
+
+impl RppalPin {
+
+    pub fn new(pin: RpiOutputPin) -> Self {
+        RppalPin { pin }
+    }
+
+    pub fn into_inner(self) -> RpiOutputPin {
+        self.pin
+    }
+}
+
+impl From<RpiOutputPin> for RppalPin {
+    fn from(pin: RpiOutputPin) -> Self {
+        RppalPin::new(pin)
+    }
+}
+
+/// Puts the pin into output mode
+impl From<Pin> for RppalPin {
+    fn from(pin: Pin) -> Self {
+        RppalPin::new(pin.into_output())
+    }
+}
+
+impl OutputPin for RppalPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        RpiOutputPin::set_low(&mut self.pin);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        RpiOutputPin::set_high(&mut self.pin);
+        Ok(())
+    }
+}
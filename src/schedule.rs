@@ -0,0 +1,206 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::Duration;
+use crate::constants::Ccs811Mode;
+
+/// Time of day with minute precision, parsed from strings like `22:30`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeOfDay {
+    hour: u8,
+    minute: u8
+}
+
+impl TimeOfDay {
+
+    pub fn new(hour: u8, minute: u8) -> Option<Self> {
+        if hour < 24 && minute < 60 {
+            Some(TimeOfDay { hour, minute })
+        } else {
+            None
+        }
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Time of day must look like 22:30 but is {}", value);
+        let (hour, minute) = value.trim().split_once(':')
+            .ok_or_else(invalid)?;
+
+        TimeOfDay::new(hour.parse().map_err(|_| invalid())?, minute.parse().map_err(|_| invalid())?)
+            .ok_or_else(invalid)
+    }
+}
+
+impl Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// Whether `time` lies in the range `from` (inclusive) to `to` (exclusive). Ranges like 22:00 to
+/// 07:00 wrap around midnight.
+fn in_range(time: TimeOfDay, from: TimeOfDay, to: TimeOfDay) -> bool {
+    if from <= to {
+        time >= from && time < to
+    } else {
+        time >= from || time < to
+    }
+}
+
+/// Actions which run once a day at a fixed time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Store the current baseline so it can be restored after a restart
+    SaveBaseline
+}
+
+/// How the chip samples at a time of day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampling {
+    pub mode: Ccs811Mode,
+    /// How often the data is read
+    pub interval: Duration
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// Sample with the mode and interval between the times
+    Sample { from: TimeOfDay, to: TimeOfDay, sampling: Sampling },
+    /// Run the action every day at the time
+    At { time: TimeOfDay, action: Action }
+}
+
+/// Time of day rules for reading the chip. The first `Sample` rule covering the current time wins,
+/// outside of all rules the default sampling applies. Be aware that the chip should be idle for 10
+/// minutes before switching to a lower sampling rate.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use ccs811::schedule::{Action, Rule, Schedule, Sampling, TimeOfDay};
+///
+/// let schedule = Schedule::new(Sampling { mode: ccs811::MODE::Sec60, interval: Duration::from_secs(60) })
+///     .rule(Rule::Sample {
+///         from: "22:00".parse().unwrap(),
+///         to: "07:00".parse().unwrap(),
+///         sampling: Sampling { mode: ccs811::MODE::Idle, interval: Duration::from_secs(60) }
+///     })
+///     .rule(Rule::At { time: "03:00".parse().unwrap(), action: Action::SaveBaseline });
+///
+/// let sampling = schedule.sampling_at(TimeOfDay::new(23, 0).unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    default: Sampling,
+    rules: Vec<Rule>
+}
+
+impl Schedule {
+
+    pub fn new(default: Sampling) -> Self {
+        Schedule { default, rules: Vec::new() }
+    }
+
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Sampling which applies at the time
+    pub fn sampling_at(&self, time: TimeOfDay) -> Sampling {
+        self.rules.iter()
+            .find_map(|rule| match rule {
+                Rule::Sample { from, to, sampling } if in_range(time, *from, *to) => Some(*sampling),
+                _ => None
+            })
+            .unwrap_or(self.default)
+    }
+
+    /// Actions due after `since` up to and including `until`. Call it with the time of the previous
+    /// and the current check, so every action runs once even if the check isn't exactly on time.
+    pub fn actions_between(&self, since: TimeOfDay, until: TimeOfDay) -> Vec<Action> {
+        if since == until {
+            return Vec::new();
+        }
+
+        self.rules.iter()
+            .filter_map(|rule| match rule {
+                Rule::At { time, action } if *time != since && (in_range(*time, since, until) || *time == until) => {
+                    Some(*action)
+                },
+                _ => None
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> TimeOfDay {
+        value.parse().unwrap()
+    }
+
+    fn sampling(mode: Ccs811Mode) -> Sampling {
+        Sampling { mode, interval: Duration::from_secs(60) }
+    }
+
+    fn schedule() -> Schedule {
+        Schedule::new(sampling(Ccs811Mode::Sec60))
+            .rule(Rule::Sample { from: time("22:00"), to: time("07:00"), sampling: sampling(Ccs811Mode::Idle) })
+            .rule(Rule::Sample { from: time("06:00"), to: time("09:00"), sampling: sampling(Ccs811Mode::Sec1) })
+            .rule(Rule::At { time: time("03:00"), action: Action::SaveBaseline })
+    }
+
+    #[test]
+    fn parses_times_of_day() {
+        assert_eq!(time(" 7:05 "), TimeOfDay::new(7, 5).unwrap());
+        assert_eq!(time("23:59").to_string(), "23:59");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("12:60".parse::<TimeOfDay>().is_err());
+        assert!("1230".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn first_covering_rule_wins() {
+        let schedule = schedule();
+
+        assert_eq!(schedule.sampling_at(time("12:00")).mode, Ccs811Mode::Sec60);
+        // Wraps around midnight
+        assert_eq!(schedule.sampling_at(time("23:00")).mode, Ccs811Mode::Idle);
+        assert_eq!(schedule.sampling_at(time("00:30")).mode, Ccs811Mode::Idle);
+        // Both rules cover 06:30, the end is exclusive
+        assert_eq!(schedule.sampling_at(time("06:30")).mode, Ccs811Mode::Idle);
+        assert_eq!(schedule.sampling_at(time("07:00")).mode, Ccs811Mode::Sec1);
+        assert_eq!(schedule.sampling_at(time("09:00")).mode, Ccs811Mode::Sec60);
+    }
+
+    #[test]
+    fn actions_run_once() {
+        let schedule = schedule();
+
+        assert_eq!(schedule.actions_between(time("02:59"), time("03:00")), vec![Action::SaveBaseline]);
+        assert_eq!(schedule.actions_between(time("03:00"), time("03:01")), vec![]);
+        // A late check still runs it
+        assert_eq!(schedule.actions_between(time("02:50"), time("03:10")), vec![Action::SaveBaseline]);
+        assert_eq!(schedule.actions_between(time("23:50"), time("03:10")), vec![Action::SaveBaseline]);
+        assert_eq!(schedule.actions_between(time("03:00"), time("03:00")), vec![]);
+    }
+}
@@ -0,0 +1,108 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use crate::chip::{AirQuality, CCS811, Ccs811Data};
+use crate::compensation::Environment;
+use crate::constants::Ccs811Mode;
+use crate::error::{Ccs811Error, SourceError};
+#[cfg(feature = "testing")]
+use crate::fake::FakeCcs811;
+
+/// Air quality as measured by any sensor, without the chip specific details of `Ccs811Data`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AirReading {
+    /// Equivalent CO2 in ppm
+    pub e_co2: u16,
+    /// Total volatile organic compounds in ppb
+    pub t_voc: u16,
+    /// Whether the sensor is still warming up and the values are unreliable
+    pub warming_up: bool
+}
+
+impl AirReading {
+
+    /// The worse of the levels of eCO2 and tVOC
+    pub fn air_quality(&self) -> AirQuality {
+        AirQuality::from_e_co2(self.e_co2).max(AirQuality::from_t_voc(self.t_voc))
+    }
+}
+
+impl From<Ccs811Data> for AirReading {
+    fn from(data: Ccs811Data) -> Self {
+        AirReading { e_co2: data.e_co2, t_voc: data.t_voc, warming_up: data.is_warming_up() }
+    }
+}
+
+/// The parts of an air quality sensor applications usually need. Implemented by `CCS811` and, with
+/// the `testing` feature, `FakeCcs811`, so applications written against it can swap in mocks or other sensors like the
+/// SGP30, whose modes map to the closest sampling period.
+///
+/// # Examples
+///
+/// ```
+/// use ccs811::sensor::AirQualitySensor;
+///
+/// fn ventilate<S: AirQualitySensor>(sensor: &mut S) -> Result<bool, S::Error> {
+///   Ok(sensor.measure()?.e_co2 > 1000)
+/// }
+/// ```
+pub trait AirQualitySensor {
+    type Error: std::error::Error;
+
+    /// The latest reading of the sensor
+    fn measure(&mut self) -> Result<AirReading, Self::Error>;
+
+    /// Humidity and temperature for the compensation of the readings
+    fn set_environment(&mut self, environment: Environment) -> Result<(), Self::Error>;
+
+    /// Starts sampling with the period of the mode or stops it in Idle mode
+    fn set_mode(&mut self, mode: Ccs811Mode) -> Result<(), Self::Error>;
+
+    fn mode(&self) -> Ccs811Mode;
+}
+
+impl<I2C, WAKE, RESET, E> AirQualitySensor for CCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError,
+          WAKE: OutputPin,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin,
+          <RESET as OutputPin>::Error: SourceError {
+    type Error = Ccs811Error;
+
+    fn measure(&mut self) -> Result<AirReading, Ccs811Error> {
+        self.read().map(AirReading::from)
+    }
+
+    fn set_environment(&mut self, environment: Environment) -> Result<(), Ccs811Error> {
+        self.set_env_data(environment.humidity, environment.temperature)
+    }
+
+    fn set_mode(&mut self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.start(mode)
+    }
+
+    fn mode(&self) -> Ccs811Mode {
+        CCS811::mode(self)
+    }
+}
+
+#[cfg(feature = "testing")]
+impl AirQualitySensor for FakeCcs811 {
+    type Error = Ccs811Error;
+
+    fn measure(&mut self) -> Result<AirReading, Ccs811Error> {
+        self.read().map(AirReading::from)
+    }
+
+    fn set_environment(&mut self, environment: Environment) -> Result<(), Ccs811Error> {
+        self.set_env_data(environment.humidity, environment.temperature)
+    }
+
+    fn set_mode(&mut self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        self.start(mode)
+    }
+
+    fn mode(&self) -> Ccs811Mode {
+        FakeCcs811::mode(self)
+    }
+}
@@ -0,0 +1,125 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use crate::chip::{CCS811, Ccs811Data, NoPin};
+use crate::error::{Ccs811Error, SourceError};
+use crate::pipeline::ReadingSource;
+
+struct Shared<I2C, WAKE, RESET> {
+    chip: CCS811<I2C, WAKE, RESET>,
+    // Last reading and until when it's the latest sample of the chip
+    latest: Option<(Ccs811Data, Instant)>,
+    coalesced: u64
+}
+
+/// Handle of a chip read by several threads, e.g. a sampling loop, a HTTP endpoint and a display. The
+/// handle is cheap to clone and all clones share the same chip.
+///
+/// Reads within one sample period are coalesced: the first one reads the chip, the others get the same
+/// reading without a transaction on the bus, as the chip has no newer sample anyway. Reads waiting for
+/// the one in progress get its reading as well. Failed reads aren't shared, so the next caller reads
+/// the chip again.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # let i2c = rppal::i2c::I2c::with_bus(1).unwrap();
+/// # struct Screen;
+/// # impl Screen { fn show(&self, _: u16) {} }
+/// # let screen = Screen;
+/// use std::thread;
+/// use ccs811::shared::SharedCCS811;
+///
+/// let ccs811 = SharedCCS811::new(ccs811::new(i2c, None));
+/// ccs811.with(|chip| chip.start(ccs811::MODE::Sec10)).expect("Could not start");
+///
+/// let display = ccs811.clone();
+/// thread::spawn(move || loop {
+///   if let Ok(data) = display.read() {
+///     screen.show(data.e_co2);
+///   }
+///   thread::sleep(Duration::from_secs(1));
+/// });
+///
+/// let data = ccs811.read().expect("Could not read data");
+/// ```
+pub struct SharedCCS811<I2C, WAKE = NoPin, RESET = NoPin> {
+    shared: Arc<Mutex<Shared<I2C, WAKE, RESET>>>
+}
+
+impl<I2C, WAKE, RESET> Clone for SharedCCS811<I2C, WAKE, RESET> {
+    fn clone(&self) -> Self {
+        SharedCCS811 { shared: self.shared.clone() }
+    }
+}
+
+impl<I2C, WAKE, RESET> SharedCCS811<I2C, WAKE, RESET> {
+
+    pub fn new(chip: CCS811<I2C, WAKE, RESET>) -> Self {
+        SharedCCS811 { shared: Arc::new(Mutex::new(Shared { chip, latest: None, coalesced: 0 })) }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared<I2C, WAKE, RESET>> {
+        // A panic in another thread doesn't leave the chip in an unusable state
+        self.shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Runs the operation with exclusive access to the chip, e.g. to change the mode. The next read
+    /// reads the chip again, as the operation might have changed what it reports.
+    pub fn with<T, F>(&self, operation: F) -> T where F: FnOnce(&mut CCS811<I2C, WAKE, RESET>) -> T {
+        let mut shared = self.lock();
+        shared.latest = None;
+
+        operation(&mut shared.chip)
+    }
+
+    /// Number of reads answered with the reading of another read instead of reading the chip
+    pub fn coalesced_reads(&self) -> u64 {
+        self.lock().coalesced
+    }
+}
+
+impl<I2C, WAKE, RESET, E> SharedCCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError,
+          WAKE: OutputPin,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin,
+          <RESET as OutputPin>::Error: SourceError {
+
+    /// Reads the chip unless another read already got its latest sample, see
+    /// [`CCS811::read`](../chip/struct.CCS811.html#method.read)
+    pub fn read(&self) -> Result<Ccs811Data, Ccs811Error> {
+        let mut shared = self.lock();
+        if let Some((data, until)) = shared.latest {
+            if Instant::now() < until {
+                shared.coalesced = shared.coalesced.saturating_add(1);
+                return Ok(data);
+            }
+        }
+
+        let result = shared.chip.read();
+        // Without a next sample, e.g. in Idle mode, every read goes to the chip
+        shared.latest = match (&result, shared.chip.next_sample_eta()) {
+            (Ok(data), Some(eta)) => Some((*data, eta)),
+            _ => None
+        };
+
+        result
+    }
+}
+
+impl<I2C, WAKE, RESET, E> ReadingSource for SharedCCS811<I2C, WAKE, RESET>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError,
+          WAKE: OutputPin,
+          <WAKE as OutputPin>::Error: SourceError,
+          RESET: OutputPin,
+          <RESET as OutputPin>::Error: SourceError {
+
+    fn next_reading(&mut self) -> Result<Ccs811Data, Ccs811Error> {
+        self.read()
+    }
+}
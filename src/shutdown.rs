@@ -0,0 +1,67 @@
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// How often a sleep checks whether the shutdown was requested
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Notices SIGTERM and SIGINT, so long running applications can leave the chip in a defined state
+/// instead of being killed in the middle of it. See `CCS811::shutdown()`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # fn save(_: u16) {}
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::shutdown::Shutdown;
+///
+/// let shutdown = Shutdown::register().expect("Could not register signal handlers");
+///
+/// while !shutdown.is_requested() {
+///   let data = ccs811.read().expect("Could not read data");
+///   shutdown.sleep(Duration::from_secs(60));
+/// }
+///
+/// if let Some(baseline) = ccs811.shutdown().expect("Could not shut down the chip") {
+///   save(baseline);
+/// }
+/// ```
+pub struct Shutdown {
+    requested: Arc<AtomicBool>
+}
+
+impl Shutdown {
+
+    pub fn register() -> io::Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(SIGTERM, requested.clone())?;
+        signal_hook::flag::register(SIGINT, requested.clone())?;
+
+        Ok(Shutdown { requested })
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Sleeps for the duration unless a shutdown is requested in the meantime. Returns `false` if the
+    /// sleep was interrupted. A duration too long to reach sleeps until the shutdown.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let end = Instant::now().checked_add(duration);
+        loop {
+            if self.is_requested() {
+                return false;
+            }
+
+            let remaining = end.map_or(POLL_INTERVAL, |end| end.saturating_duration_since(Instant::now()));
+            if remaining == Duration::from_secs(0) {
+                return true;
+            }
+            sleep(remaining.min(POLL_INTERVAL));
+        }
+    }
+}
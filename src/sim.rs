@@ -0,0 +1,421 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+use std::convert::Infallible;
+use std::fmt::{self, Display};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use crate::chip::{Ccs811Data, RawData, Status};
+use crate::constants::*;
+
+const STATUS_ERROR: u8 = 0b00000001;
+const STATUS_DATA_READY: u8 = 0b00001000;
+
+const ERROR_WRITE_REG_INVALID: u8 = 0b00000001;
+const ERROR_READ_REG_INVALID: u8 = 0b00000010;
+const ERROR_MEASMODE_INVALID: u8 = 0b00000100;
+
+const RESET_KEY: [u8; 4] = [0x11, 0xE5, 0x72, 0x8A];
+const ERASE_KEY: [u8; 4] = [0xE7, 0xA7, 0xE6, 0x09];
+
+/// A valid reading sampled in Sec1 mode after the warm-up, e.g. to feed filters and trackers
+pub fn reading(e_co2: u16, t_voc: u16) -> Ccs811Data {
+    let [e_co2_high, e_co2_low] = e_co2.to_be_bytes();
+    let [t_voc_high, t_voc_low] = t_voc.to_be_bytes();
+
+    Ccs811Data {
+        e_co2,
+        t_voc,
+        raw: RawData([e_co2_high, e_co2_low, t_voc_high, t_voc_low, 0x98, 0x00, 0x00, 0x00]),
+        status: Status(0x98),
+        mode: Ccs811Mode::Sec1,
+        since_start: Some(CCS811_WARM_UP)
+    }
+}
+
+/// The simulated chip didn't acknowledge a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimError {
+    /// No chip answers on the address
+    AddressNack(u8),
+    /// The transaction failed because of `SimCcs811::fail_next()`
+    Injected
+}
+
+impl Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimError::AddressNack(address) => write!(f, "No acknowledge from address {:#04x}", address),
+            SimError::Injected => write!(f, "Injected bus error")
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+/// Software model of the chip's registers and state machine behind the embedded-hal I2C traits. It
+/// boots into boot loader mode, supports the erase, write and verify flow of flashing and sets
+/// DATA_READY according to the measurement mode. Time is simulated, so tests advance it with
+/// `elapse()` and are deterministic. As discarding the first samples after `start()` would wait in
+/// real time, disable it with `with_discarded_samples(0)` or elapse the time of the discarded samples
+/// and use `try_read()`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use ccs811::chip::{CCS811, NoPin};
+/// use ccs811::sim::SimCcs811;
+///
+/// let mut ccs811 = CCS811::new(SimCcs811::new(), None::<NoPin>).with_discarded_samples(0);
+/// ccs811.begin().unwrap();
+/// ccs811.start(ccs811::MODE::Sec1).unwrap();
+///
+/// ccs811.i2c.set_air(800, 60);
+/// ccs811.i2c.elapse(Duration::from_secs(1));
+///
+/// assert_eq!(ccs811.read().unwrap().e_co2, 800);
+/// ```
+pub struct SimCcs811 {
+    address: u8,
+    app_mode: bool,
+    app_valid: bool,
+    app_erased: bool,
+    app_verified: bool,
+    firmware: Vec<u8>,
+    application_version: [u8; 2],
+    meas_mode: u8,
+    env_data: [u8; 4],
+    baseline: [u8; 2],
+    error_id: u8,
+    data_ready: bool,
+    since_sample: Duration,
+    e_co2: u16,
+    t_voc: u16,
+    raw: [u8; 2],
+    failures: usize,
+    unplugged: bool
+}
+
+impl Default for SimCcs811 {
+    fn default() -> Self {
+        SimCcs811::new()
+    }
+}
+
+impl SimCcs811 {
+
+    /// A chip on `CCS811_SLAVEADDR_0` with a valid application 2.0.0 in boot loader mode, like after
+    /// power up
+    pub fn new() -> Self {
+        SimCcs811 {
+            address: CCS811_SLAVEADDR_0,
+            app_mode: false,
+            app_valid: true,
+            app_erased: false,
+            app_verified: false,
+            firmware: Vec::new(),
+            application_version: [0x20, 0x00],
+            meas_mode: 0,
+            env_data: [0x64, 0x00, 0x64, 0x00],
+            baseline: [0, 0],
+            error_id: 0,
+            data_ready: false,
+            since_sample: Duration::from_secs(0),
+            e_co2: 400,
+            t_voc: 0,
+            raw: [0x18, 0x80],
+            failures: 0,
+            unplugged: false
+        }
+    }
+
+    pub fn with_address(self, address: u8) -> Self {
+        SimCcs811 { address, ..self }
+    }
+
+    /// Simulate a chip without application, e.g. after an interrupted flash
+    pub fn without_application(self) -> Self {
+        SimCcs811 { app_valid: false, ..self }
+    }
+
+    /// Version reported by APP_VERSION, also after flashing
+    pub fn with_application_version(self, application_version: [u8; 2]) -> Self {
+        SimCcs811 { application_version, ..self }
+    }
+
+    /// Values of the next samples
+    pub fn set_air(&mut self, e_co2: u16, t_voc: u16) {
+        self.e_co2 = e_co2;
+        self.t_voc = t_voc;
+    }
+
+    /// RAW_DATA of the next samples, 6 bits current in µA and 10 bits ADC value
+    pub fn set_raw(&mut self, current_ua: u8, adc: u16) {
+        self.raw = [(current_ua << 2) | ((adc >> 8) as u8 & 0b11), adc as u8];
+    }
+
+    /// Sets bits in ERROR_ID, e.g. 0b00010000 for HEATER_FAULT
+    pub fn inject_error(&mut self, error_id: u8) {
+        self.error_id |= error_id;
+    }
+
+    /// Let the next `count` transactions fail
+    pub fn fail_next(&mut self, count: usize) {
+        self.failures = count;
+    }
+
+    /// Removes the chip from the bus, so no transaction is acknowledged until `plug()`
+    pub fn unplug(&mut self) {
+        self.unplugged = true;
+    }
+
+    /// Puts the chip back on the bus, powered up in boot loader mode
+    pub fn plug(&mut self) {
+        self.reset();
+    }
+
+    /// Advances the simulated time and samples according to the measurement mode
+    pub fn elapse(&mut self, duration: Duration) {
+        let period = match self.meas_mode >> 4 & 0b111 {
+            1 => Duration::from_secs(1),
+            2 => Duration::from_secs(10),
+            3 => Duration::from_secs(60),
+            4 => Duration::from_millis(250),
+            _ => return
+        };
+
+        self.since_sample = self.since_sample.saturating_add(duration);
+        if self.app_mode && self.since_sample >= period {
+            self.since_sample = Duration::from_secs(0);
+            self.data_ready = true;
+        }
+    }
+
+    /// The firmware written with APP_DATA since the last erase
+    pub fn firmware(&self) -> &[u8] {
+        &self.firmware
+    }
+
+    pub fn is_app_mode(&self) -> bool {
+        self.app_mode
+    }
+
+    /// Content of ENV_DATA as written by the driver
+    pub fn env_data(&self) -> [u8; 4] {
+        self.env_data
+    }
+
+    /// Content of BASELINE as written by the driver
+    pub fn baseline(&self) -> [u8; 2] {
+        self.baseline
+    }
+
+    pub fn status(&self) -> u8 {
+        let mut status = 0;
+        if self.app_mode { status |= CCS811_STATUS_APP_MODE; }
+        if self.app_erased { status |= CCS811_STATUS_APP_ERASE; }
+        if self.app_verified { status |= CCS811_STATUS_APP_VERIFY; }
+        if self.app_valid { status |= CCS811_STATUS_APP_VALID; }
+        if self.data_ready { status |= STATUS_DATA_READY; }
+        if self.error_id != 0 { status |= STATUS_ERROR; }
+
+        status
+    }
+
+    fn transaction(&mut self, address: u8) -> Result<(), SimError> {
+        if self.failures > 0 {
+            self.failures = self.failures.saturating_sub(1);
+            return Err(SimError::Injected);
+        }
+        if address != self.address || self.unplugged {
+            return Err(SimError::AddressNack(address));
+        }
+
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        *self = SimCcs811 {
+            address: self.address,
+            app_valid: self.app_valid,
+            firmware: std::mem::take(&mut self.firmware),
+            application_version: self.application_version,
+            e_co2: self.e_co2,
+            t_voc: self.t_voc,
+            raw: self.raw,
+            ..SimCcs811::new()
+        };
+    }
+
+    fn write_register(&mut self, register: u8, data: &[u8]) {
+        match (self.app_mode, register, data) {
+            (_, CCS811_SW_RESET, _) if data == RESET_KEY => self.reset(),
+            (true, CCS811_MEAS_MODE, &[meas_mode]) => {
+                if meas_mode >> 4 & 0b111 > 4 {
+                    self.error_id |= ERROR_MEASMODE_INVALID;
+                } else {
+                    self.meas_mode = meas_mode;
+                    self.since_sample = Duration::from_secs(0);
+                }
+            },
+            (true, CCS811_ENV_DATA, _) if data.len() == 4 => self.env_data.copy_from_slice(data),
+            (true, CCS811_BASELINE, _) if data.len() == 2 => self.baseline.copy_from_slice(data),
+            (false, CCS811_APP_START, []) => self.app_mode = self.app_valid,
+            (false, CCS811_APP_ERASE, _) if data == ERASE_KEY => {
+                self.firmware.clear();
+                self.app_valid = false;
+                self.app_erased = true;
+                self.app_verified = false;
+            },
+            (false, CCS811_APP_DATA, _) if self.app_erased && data.len() <= 8 => self.firmware.extend_from_slice(data),
+            (false, CCS811_APP_VERIFY, []) => {
+                self.app_verified = true;
+                self.app_valid = self.app_erased && !self.firmware.is_empty();
+            },
+            // Setting the register pointer for a following read
+            (_, _, []) => (),
+            _ => self.error_id |= ERROR_WRITE_REG_INVALID
+        }
+    }
+
+    fn read_register(&mut self, register: u8, buffer: &mut [u8]) {
+        let status = self.status();
+        let content: Vec<u8> = match (self.app_mode, register) {
+            (_, CCS811_STATUS) => vec![status],
+            (_, CCS811_HW_ID) => vec![0x81],
+            (_, CCS811_HW_VERSION) => vec![0x12],
+            (_, CCS811_FW_BOOT_VERSION) => vec![0x10, 0x00],
+            (_, CCS811_FW_APP_VERSION) => self.application_version.to_vec(),
+            (_, CCS811_ERROR_ID) => {
+                let error_id = self.error_id;
+                self.error_id = 0;
+                vec![error_id]
+            },
+            (true, CCS811_MEAS_MODE) => vec![self.meas_mode],
+            (true, CCS811_BASELINE) => self.baseline.to_vec(),
+            (true, CCS811_ALG_RESULT_DATA) => {
+                self.data_ready = false;
+                vec![
+                    (self.e_co2 >> 8) as u8, self.e_co2 as u8,
+                    (self.t_voc >> 8) as u8, self.t_voc as u8,
+                    status, self.error_id,
+                    self.raw[0], self.raw[1]
+                ]
+            },
+            _ => {
+                self.error_id |= ERROR_READ_REG_INVALID;
+                vec![]
+            }
+        };
+
+        for (index, byte) in buffer.iter_mut().enumerate() {
+            *byte = content.get(index).copied().unwrap_or(0);
+        }
+    }
+}
+
+impl Write for SimCcs811 {
+    type Error = SimError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), SimError> {
+        self.transaction(address)?;
+
+        if let Some((register, data)) = bytes.split_first() {
+            self.write_register(*register, data);
+        }
+
+        Ok(())
+    }
+}
+
+impl WriteRead for SimCcs811 {
+    type Error = SimError;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), SimError> {
+        self.transaction(address)?;
+
+        match bytes.first() {
+            Some(register) => self.read_register(*register, buffer),
+            None => self.error_id |= ERROR_READ_REG_INVALID
+        }
+
+        Ok(())
+    }
+}
+
+/// A level change of a `SimPin`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinTransition {
+    /// Whether the pin was set low, else high
+    pub low: bool,
+    pub at: Instant
+}
+
+/// Output pin recording every `set_low()` and `set_high()` with a timestamp, e.g. as wake pin to
+/// check the wake sequencing of the driver. Clones share the recording, so keep a clone before
+/// passing the pin to the driver.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use ccs811::chip::CCS811;
+/// use ccs811::sim::{SimCcs811, SimPin};
+///
+/// let wake = SimPin::new();
+/// let mut ccs811 = CCS811::new(SimCcs811::new(), Some(wake.clone()));
+/// ccs811.begin().unwrap();
+///
+/// // The chip is only woken up around transactions and got 50µs to settle
+/// assert!(!wake.is_low());
+/// let transitions = wake.transitions();
+/// assert!(transitions[1].at - transitions[0].at >= Duration::from_micros(50));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SimPin {
+    transitions: Arc<Mutex<Vec<PinTransition>>>
+}
+
+impl SimPin {
+
+    pub fn new() -> Self {
+        SimPin::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<PinTransition>> {
+        self.transitions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// All level changes so far, oldest first
+    pub fn transitions(&self) -> Vec<PinTransition> {
+        self.lock().clone()
+    }
+
+    /// Whether the pin was set low last. A pin which was never set counts as high.
+    pub fn is_low(&self) -> bool {
+        self.lock().last().is_some_and(|transition| transition.low)
+    }
+
+    /// Forgets the recorded transitions, e.g. to only look at the ones of the next operation
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn record(&self, low: bool) {
+        self.lock().push(PinTransition { low, at: Instant::now() });
+    }
+}
+
+impl OutputPin for SimPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.record(true);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.record(false);
+        Ok(())
+    }
+}
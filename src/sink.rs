@@ -0,0 +1,78 @@
+use crate::chip::Ccs811Data;
+use crate::error::{BoxError, Ccs811Error};
+
+/// Destination of readings like a MQTT broker or a monitoring system. All sinks of this crate
+/// implement it and it can be implemented for own destinations. Closures taking a reading are sinks
+/// as well and a `Vec` of boxed sinks publishes to all of them.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use std::time::SystemTime;
+/// use ccs811::chip::Ccs811Data;
+/// use ccs811::format::Format;
+/// use ccs811::sink::ReadingSink;
+///
+/// let mut sinks: Vec<Box<dyn ReadingSink + Send>> = vec![
+///     Box::new(|data: &Ccs811Data| {
+///         println!("{}", Format::Csv.format(data, SystemTime::now()));
+///         Ok(())
+///     }),
+///     Box::new(|data: &Ccs811Data| {
+///         if data.e_co2 > 1500 {
+///             eprintln!("Open the window, eCO2 is {} ppm", data.e_co2);
+///         }
+///         Ok(())
+///     })
+/// ];
+///
+/// sinks.publish(&ccs811.read().expect("Could not read data")).expect("Could not publish");
+/// ```
+pub trait ReadingSink {
+
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError>;
+
+    /// Called with errors of the driver, e.g. to count them. Ignores them by default.
+    fn record_error(&mut self, _error: &Ccs811Error) -> Result<(), BoxError> {
+        Ok(())
+    }
+
+    /// Sends readings the sink buffered, e.g. before shutting down. Does nothing by default.
+    fn flush(&mut self) -> Result<(), BoxError> {
+        Ok(())
+    }
+}
+
+impl<F> ReadingSink for F where F: FnMut(&Ccs811Data) -> Result<(), BoxError> {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        self(data)
+    }
+}
+
+/// Consumes all results and returns the first error
+pub(crate) fn first_error(results: impl Iterator<Item = Result<(), BoxError>>) -> Result<(), BoxError> {
+    let mut first = Ok(());
+    for result in results {
+        if first.is_ok() {
+            first = result;
+        }
+    }
+
+    first
+}
+
+/// Publishes to all sinks, even if some of them fail. Returns the first error.
+impl ReadingSink for Vec<Box<dyn ReadingSink + Send>> {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        first_error(self.iter_mut().map(|sink| sink.publish(data)))
+    }
+
+    fn record_error(&mut self, error: &Ccs811Error) -> Result<(), BoxError> {
+        first_error(self.iter_mut().map(|sink| sink.record_error(error)))
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        first_error(self.iter_mut().map(|sink| sink.flush()))
+    }
+}
@@ -0,0 +1,181 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use std::time::{Duration, Instant};
+use crate::chip::{CCS811, Ccs811Data, Status};
+use crate::codec::parse_alg_result;
+use crate::constants::{*};
+use crate::error::{Ccs811Error, SourceError, i2c_error};
+
+/// Bus owning half of a split driver. Its methods only do I2C transactions without delays, so they
+/// can run in a task triggered by the nINT interrupt. The wake pin isn't handled, nWAKE has to stay low.
+pub struct Ccs811Bus<I2C> {
+    i2c: I2C,
+    address: u8
+}
+
+/// State and parsing half of a split driver. It doesn't touch the bus, so it can be shared with an
+/// interrupt handler and only has to be locked while a result is parsed.
+#[derive(Debug, Clone)]
+pub struct Ccs811State {
+    mode: Ccs811Mode,
+    started: Option<Instant>,
+    discarded_samples: u8,
+    pending_discards: u8
+}
+
+impl<I2C, WAKE, RESET> CCS811<I2C, WAKE, RESET> {
+
+    /// Splits the initialized driver for interrupt driven firmware, e.g. with RTIC: the nINT handler
+    /// spawns a task which reads the result with the `Ccs811Bus` and parses it with the `Ccs811State`.
+    /// Call `begin()` before, as the initialization needs delays. The wake and reset pins are dropped,
+    /// take them from the public fields before if they are still needed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+    /// ccs811.begin().expect("Could not init the chip");
+    /// let (mut bus, mut state) = ccs811.split();
+    ///
+    /// bus.start(ccs811::MODE::Sec1).expect("Could not start");
+    /// state.start(ccs811::MODE::Sec1);
+    ///
+    /// // In the task spawned by the nINT interrupt
+    /// let buffer = bus.read_result().expect("Could not read");
+    /// if let Some(data) = state.parse(buffer).expect("Invalid data") {
+    ///   println!("eCO2 {} ppm", data.e_co2);
+    /// }
+    /// ```
+    pub fn split(self) -> (Ccs811Bus<I2C>, Ccs811State) {
+        let state = Ccs811State {
+            mode: self.mode(),
+            started: self.started,
+            discarded_samples: self.discarded_samples,
+            pending_discards: 0
+        };
+        let bus = Ccs811Bus { address: self.address(), i2c: self.i2c };
+
+        (bus, state)
+    }
+}
+
+impl<I2C, E> Ccs811Bus<I2C>
+    where I2C: Write<Error = E> + WriteRead<Error = E>,
+          E: SourceError {
+
+    /// Sets the mode with the nINT interrupt on new data, see `Ccs811State::start()`. The other bits of
+    /// MEAS_MODE like INT_THRESH are kept, as with `CCS811::start()`.
+    pub fn start(&mut self, mode: Ccs811Mode) -> Result<(), Ccs811Error> {
+        let mut meas_mode = [0];
+        self.i2c.write_read(self.address, &[CCS811_MEAS_MODE], &mut meas_mode)
+            .map_err(i2c_error("read mode"))?;
+        let meas_mode = (meas_mode[0] & !CCS811_MEAS_MODE_DRIVE_MODE) | ((mode as u8) << 4) | CCS811_MEAS_MODE_INT_DATARDY;
+
+        self.i2c.write(self.address, &[CCS811_MEAS_MODE, meas_mode])
+            .map_err(i2c_error("set mode"))
+    }
+
+    /// Reads ALG_RESULT_DATA, to be passed to `Ccs811State::parse()`
+    pub fn read_result(&mut self) -> Result<[u8; 8], Ccs811Error> {
+        let mut buffer = [0; 8];
+        self.i2c.write_read(self.address, &[CCS811_ALG_RESULT_DATA], &mut buffer)
+            .map_err(i2c_error("read chip data"))?;
+
+        Ok(buffer)
+    }
+
+    /// Gives the bus back, e.g. to share it with other devices
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl Ccs811State {
+
+    /// Records the mode written with `Ccs811Bus::start()`, the first samples are discarded as with
+    /// `CCS811::read()`
+    pub fn start(&mut self, mode: Ccs811Mode) {
+        self.mode = mode;
+        self.started = mode.period().map(|_| Instant::now());
+        self.pending_discards = if mode.period().is_some() { self.discarded_samples } else { 0 };
+    }
+
+    pub fn mode(&self) -> Ccs811Mode {
+        self.mode
+    }
+
+    /// Time since sampling was started, `None` in Idle mode
+    pub fn since_start(&self) -> Option<Duration> {
+        self.started.map(|started| started.elapsed())
+    }
+
+    /// Parses a result read by `Ccs811Bus::read_result()`. Returns `None` if the chip had no new
+    /// sample or the sample is discarded after starting.
+    pub fn parse(&mut self, buffer: [u8; 8]) -> Result<Option<Ccs811Data>, Ccs811Error> {
+        if buffer[5] != 0 {
+            return Err(Ccs811Error::Chip(buffer[5]));
+        }
+        if !Status(buffer[4]).is_data_ready() {
+            return Ok(None);
+        }
+        if self.pending_discards > 0 {
+            self.pending_discards = self.pending_discards.saturating_sub(1);
+            return Ok(None);
+        }
+
+        parse_alg_result(buffer, self.mode, self.since_start()).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::chip::{CCS811, Interrupts, NoPin};
+    use crate::sim::SimCcs811;
+    use super::*;
+
+    fn split() -> (Ccs811Bus<SimCcs811>, Ccs811State) {
+        let mut ccs811 = CCS811::new(SimCcs811::new(), None::<NoPin>);
+        ccs811.begin().unwrap();
+        ccs811.set_interrupts(Interrupts { data_ready: true, threshold: true }).unwrap();
+
+        ccs811.split()
+    }
+
+    #[test]
+    fn start_keeps_the_threshold_interrupt() {
+        let (mut bus, mut state) = split();
+
+        bus.start(Ccs811Mode::Sec1).unwrap();
+        state.start(Ccs811Mode::Sec1);
+
+        let mut ccs811 = CCS811::new(bus.release(), None::<NoPin>);
+        assert_eq!(ccs811.interrupts().unwrap(), Interrupts { data_ready: true, threshold: true });
+        assert_eq!(state.mode(), Ccs811Mode::Sec1);
+    }
+
+    #[test]
+    fn parse_discards_the_first_sample() {
+        let (mut bus, mut state) = split();
+        bus.start(Ccs811Mode::Sec1).unwrap();
+        state.start(Ccs811Mode::Sec1);
+
+        // Nothing sampled yet
+        assert_eq!(state.parse(bus.read_result().unwrap()).unwrap(), None);
+
+        bus.i2c.set_air(500, 10);
+        bus.i2c.elapse(Duration::from_secs(1));
+        assert_eq!(state.parse(bus.read_result().unwrap()).unwrap(), None);
+
+        bus.i2c.set_air(600, 20);
+        bus.i2c.elapse(Duration::from_secs(1));
+        let data = state.parse(bus.read_result().unwrap()).unwrap().unwrap();
+        assert_eq!((data.e_co2, data.t_voc), (600, 20));
+    }
+
+    #[test]
+    fn parse_reports_chip_errors() {
+        let mut state = Ccs811State { mode: Ccs811Mode::Sec1, started: None, discarded_samples: 0, pending_discards: 0 };
+
+        assert!(matches!(state.parse([0, 0, 0, 0, 0x98, 0x10, 0, 0]), Err(Ccs811Error::Chip(0x10))));
+    }
+}
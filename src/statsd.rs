@@ -0,0 +1,135 @@
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use crate::chip::Ccs811Data;
+use crate::error::{BoxError, Ccs811Error};
+use crate::exposure::Twa;
+use crate::sink::ReadingSink;
+
+/// How tags are appended to the metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagFormat {
+    /// `ccs811.eco2:812|g|#room:kitchen` as understood by Datadog and Telegraf with datadog_extensions
+    DogStatsd,
+    /// `ccs811.eco2,room=kitchen:812|g` as understood by Telegraf
+    Influx,
+    /// Plain StatsD without tags
+    None
+}
+
+/// Sends readings as gauges and errors as counters via UDP to a StatsD server like the Datadog agent
+/// or Telegraf. Metrics are `<prefix>.eco2` and `<prefix>.tvoc` as gauges and `<prefix>.errors` counting
+/// errors tagged with their kind. Time-weighted averages of an `exposure::ExposureTracker` are sent
+/// with `publish_twa()` as `<prefix>.eco2.twa_8h` and `<prefix>.tvoc.twa_8h` gauges, named after the
+/// window in hours.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use ccs811::sink::ReadingSink;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::statsd::StatsdSink;
+///
+/// let mut statsd = StatsdSink::new("127.0.0.1:8125")
+///     .expect("Could not open socket")
+///     .with_tag("room", "kitchen");
+///
+/// match ccs811.read() {
+///   Ok(data) => statsd.publish(&data).expect("Could not send metrics"),
+///   Err(error) => statsd.record_error(&error).expect("Could not send metrics")
+/// }
+/// ```
+pub struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+    tags: Vec<(String, String)>,
+    tag_format: TagFormat
+}
+
+impl StatsdSink {
+
+    pub fn new<A: ToSocketAddrs>(server: A) -> io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(server)?;
+
+        Ok(StatsdSink {
+            socket,
+            prefix: "ccs811".to_string(),
+            tags: Vec::new(),
+            tag_format: TagFormat::DogStatsd
+        })
+    }
+
+    /// Prefix of the metric names, `ccs811` by default
+    pub fn with_prefix(self, prefix: &str) -> Self {
+        StatsdSink { prefix: prefix.to_string(), ..self }
+    }
+
+    /// Adds a tag to all metrics
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_tag_format(self, tag_format: TagFormat) -> Self {
+        StatsdSink { tag_format, ..self }
+    }
+
+    pub fn publish(&mut self, data: &Ccs811Data) -> io::Result<()> {
+        let lines = [
+            self.metric("eco2", &data.e_co2.to_string(), "g", &[]),
+            self.metric("tvoc", &data.t_voc.to_string(), "g", &[])
+        ];
+
+        self.socket.send(lines.join("\n").as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn publish_twa(&mut self, twa: &Twa) -> io::Result<()> {
+        let hours = twa.window.as_secs().checked_div(3600).unwrap_or_default();
+        let lines = [
+            self.metric(&format!("eco2.twa_{}h", hours), &format!("{:.1}", twa.e_co2), "g", &[]),
+            self.metric(&format!("tvoc.twa_{}h", hours), &format!("{:.1}", twa.t_voc), "g", &[])
+        ];
+
+        self.socket.send(lines.join("\n").as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn record_error(&mut self, error: &Ccs811Error) -> io::Result<()> {
+        let line = self.metric("errors", "1", "c", &[("kind", error.kind())]);
+        self.socket.send(line.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn metric(&self, name: &str, value: &str, kind: &str, extra_tags: &[(&str, &str)]) -> String {
+        let tags: Vec<(&str, &str)> = self.tags.iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .chain(extra_tags.iter().copied())
+            .collect();
+
+        match self.tag_format {
+            TagFormat::DogStatsd if !tags.is_empty() => {
+                let tags: Vec<String> = tags.iter().map(|(key, value)| format!("{}:{}", key, value)).collect();
+                format!("{}.{}:{}|{}|#{}", self.prefix, name, value, kind, tags.join(","))
+            },
+            TagFormat::Influx => {
+                let tags: String = tags.iter().map(|(key, value)| format!(",{}={}", key, value)).collect();
+                format!("{}.{}{}:{}|{}", self.prefix, name, tags, value, kind)
+            },
+            _ => format!("{}.{}:{}|{}", self.prefix, name, value, kind)
+        }
+    }
+}
+
+impl ReadingSink for StatsdSink {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        Ok(StatsdSink::publish(self, data)?)
+    }
+
+    fn record_error(&mut self, error: &Ccs811Error) -> Result<(), BoxError> {
+        Ok(StatsdSink::record_error(self, error)?)
+    }
+}
@@ -0,0 +1,126 @@
+use ::tokio::sync::broadcast::{self, Receiver, Sender};
+use ::tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::sink::ReadingSink;
+
+// broadcast::channel() panics on larger capacities
+const MAX_CAPACITY: usize = usize::MAX / 2;
+
+/// What a `ReadingReceiver` got from the broadcast
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Received {
+    Reading(Ccs811Data),
+    /// The receiver fell behind, the oldest `missed` readings were dropped for it. The next call gets
+    /// the oldest reading still kept.
+    Lagged { missed: u64 }
+}
+
+/// Hands every reading to any number of tasks through a `tokio::sync::broadcast` channel. Publishing
+/// never waits for the receivers, so a stalled consumer, e.g. a MQTT connection without network,
+/// can't hold up the sampling. The channel keeps the last `capacity` readings, a receiver falling
+/// further behind gets `Received::Lagged` with the number of dropped readings instead of missing them
+/// unnoticed. Without receivers the readings are dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use ccs811::pipeline::Pipeline;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::tokio::{ReadingBroadcast, Received};
+///
+/// let broadcast = ReadingBroadcast::new(16);
+/// let mut receiver = broadcast.subscribe();
+///
+/// tokio::spawn(async move {
+///     while let Some(received) = receiver.recv().await {
+///         match received {
+///             Received::Reading(data) => println!("eCO2 {}ppm", data.e_co2),
+///             Received::Lagged { missed } => println!("Too slow, missed {} readings", missed)
+///         }
+///     }
+/// });
+///
+/// let mut pipeline = Pipeline::new(ccs811).sink(broadcast);
+/// ```
+#[derive(Clone)]
+pub struct ReadingBroadcast {
+    sender: Sender<Ccs811Data>
+}
+
+impl ReadingBroadcast {
+
+    /// Keeps the last `capacity` readings for receivers which fall behind, at least one
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.clamp(1, MAX_CAPACITY));
+
+        ReadingBroadcast { sender }
+    }
+
+    /// Receives the readings published from now on
+    pub fn subscribe(&self) -> ReadingReceiver {
+        ReadingReceiver { receiver: self.sender.subscribe(), missed: 0 }
+    }
+
+    /// Number of receivers which aren't dropped yet
+    pub fn receivers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Sends the reading to all receivers without waiting for them
+    pub fn publish(&self, data: &Ccs811Data) {
+        // Only fails without receivers, which drops the reading like every broadcast does
+        let _ = self.sender.send(*data);
+    }
+}
+
+impl ReadingSink for ReadingBroadcast {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        ReadingBroadcast::publish(self, data);
+        Ok(())
+    }
+}
+
+/// Readings of a `ReadingBroadcast` for one consumer
+pub struct ReadingReceiver {
+    receiver: Receiver<Ccs811Data>,
+    missed: u64
+}
+
+impl ReadingReceiver {
+
+    /// Waits for the next reading. Returns `None` once all `ReadingBroadcast` clones are dropped and the
+    /// kept readings are received.
+    pub async fn recv(&mut self) -> Option<Received> {
+        match self.receiver.recv().await {
+            Ok(data) => Some(Received::Reading(data)),
+            Err(RecvError::Lagged(missed)) => Some(self.lagged(missed)),
+            Err(RecvError::Closed) => None
+        }
+    }
+
+    /// Returns the next reading if there is one without waiting
+    pub fn try_recv(&mut self) -> Option<Received> {
+        match self.receiver.try_recv() {
+            Ok(data) => Some(Received::Reading(data)),
+            Err(TryRecvError::Lagged(missed)) => Some(self.lagged(missed)),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => None
+        }
+    }
+
+    /// Readings dropped for this receiver so far because it fell behind
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// The tokio receiver, e.g. for `tokio_stream::wrappers::BroadcastStream`
+    pub fn into_inner(self) -> Receiver<Ccs811Data> {
+        self.receiver
+    }
+
+    fn lagged(&mut self, missed: u64) -> Received {
+        self.missed = self.missed.saturating_add(missed);
+
+        Received::Lagged { missed }
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::chip::Ccs811Data;
+
+// Readings have to fall at least this fast in ppm per minute to count as recovering
+const RECOVERING_RATE: f32 = 5.0;
+
+/// The room the sensor is in, which determines how fast opening a window replaces its air
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Room {
+    /// Volume in m³, e.g. 40 for a bedroom of 16 m²
+    pub volume: f32,
+    /// Air exchanged through the open window in m³ per hour, around 400 for a wide open window and
+    /// a tenth of it for a tilted one
+    pub airing_flow: f32,
+    /// eCO2 outdoors in ppm, which the room approaches while airing
+    pub outdoor: u16,
+    /// eCO2 in ppm above which to ventilate
+    pub limit: u16,
+    /// eCO2 in ppm to ventilate down to
+    pub fresh: u16
+}
+
+impl Default for Room {
+    fn default() -> Self {
+        Room { volume: 40.0, airing_flow: 400.0, outdoor: 420, limit: 1000, fresh: 600 }
+    }
+}
+
+impl Room {
+
+    /// Time the open window takes to lower eCO2 to the fresh level. The air mixes with the outdoor air,
+    /// so the difference to the outdoor level decays exponentially with the air changes per hour.
+    pub fn airing_time(&self, e_co2: u16) -> Duration {
+        let air_changes = self.airing_flow / self.volume;
+        let excess = f32::from(e_co2.saturating_sub(self.outdoor));
+        let target = f32::from(self.fresh.saturating_sub(self.outdoor)).max(1.0);
+        let hours = (excess / target).ln() / air_changes;
+
+        Duration::try_from_secs_f32(hours * 3600.0).unwrap_or_default()
+    }
+}
+
+/// What to do about the air in the room, see `VentilationAdvisor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advice {
+    /// The reading isn't valid or taken during the warm-up, so there's no advice
+    NotReady,
+    /// eCO2 is below the limit and not about to exceed it
+    Good,
+    /// eCO2 rises and will exceed the limit within about this time
+    VentilateSoon { within: Duration },
+    /// eCO2 is above the limit, open a window for about this time
+    Ventilate { duration: Duration },
+    /// eCO2 falls towards the fresh level, which takes about this time with the window open
+    Recovering { remaining: Duration }
+}
+
+/// Advice for people like `Open a window for ~10 minutes`
+impl fmt::Display for Advice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Advice::NotReady => write!(f, "No advice while the sensor warms up"),
+            Advice::Good => write!(f, "Air quality is good"),
+            Advice::VentilateSoon { within } => write!(f, "Open a window within ~{}", Minutes(*within)),
+            Advice::Ventilate { duration } => write!(f, "Open a window for ~{}", Minutes(*duration)),
+            Advice::Recovering { remaining } => write!(f, "Air quality recovering, ~{} until fresh", Minutes(*remaining))
+        }
+    }
+}
+
+/// Minutes rounded up, so there's never advice for 0 minutes
+struct Minutes(Duration);
+
+impl fmt::Display for Minutes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.as_secs().div_ceil(60).max(1) {
+            1 => write!(f, "1 minute"),
+            minutes => write!(f, "{} minutes", minutes)
+        }
+    }
+}
+
+/// Turns the eCO2 readings and their trend into advice when and how long to ventilate the room. The
+/// trend is the change of eCO2 over the last 5 minutes, while falling eCO2 above the fresh level
+/// means the room is being aired.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::ventilation::{Room, VentilationAdvisor};
+///
+/// let mut advisor = VentilationAdvisor::new(Room { volume: 60.0, ..Room::default() });
+///
+/// loop {
+///   let advice = advisor.record(&ccs811.sleep_until_next_sample().expect("Could not read data"));
+///   println!("{}", advice);
+/// }
+/// ```
+pub struct VentilationAdvisor {
+    room: Room,
+    trend_window: Duration,
+    warn_ahead: Duration,
+    history: VecDeque<(u16, Instant)>
+}
+
+impl VentilationAdvisor {
+
+    /// Warns 15 minutes before eCO2 exceeds the limit of the room
+    pub fn new(room: Room) -> Self {
+        VentilationAdvisor {
+            room,
+            trend_window: Duration::from_secs(5 * 60),
+            warn_ahead: Duration::from_secs(15 * 60),
+            history: VecDeque::new()
+        }
+    }
+
+    /// Time span the trend is calculated over
+    pub fn with_trend_window(self, trend_window: Duration) -> Self {
+        VentilationAdvisor { trend_window, ..self }
+    }
+
+    /// How long before eCO2 exceeds the limit to advise ventilating soon
+    pub fn with_warn_ahead(self, warn_ahead: Duration) -> Self {
+        VentilationAdvisor { warn_ahead, ..self }
+    }
+
+    pub fn room(&self) -> &Room {
+        &self.room
+    }
+
+    /// Change of eCO2 in ppm per minute over the trend window, `None` until a minute is recorded
+    pub fn trend(&self) -> Option<f32> {
+        let (first, since) = *self.history.front()?;
+        let (last, at) = *self.history.back()?;
+        let elapsed = at.saturating_duration_since(since);
+        if elapsed < Duration::from_secs(60) {
+            return None;
+        }
+
+        Some((f32::from(last) - f32::from(first)) / elapsed.as_secs_f32() * 60.0)
+    }
+
+    /// Records a reading taken just now and returns the advice
+    pub fn record(&mut self, data: &Ccs811Data) -> Advice {
+        self.record_at(data, Instant::now())
+    }
+
+    /// Records a reading taken at the given time and returns the advice
+    pub fn record_at(&mut self, data: &Ccs811Data, at: Instant) -> Advice {
+        if !data.is_valid() || data.is_warming_up() {
+            self.history.clear();
+            return Advice::NotReady;
+        }
+
+        while let Some((_, recorded)) = self.history.front() {
+            if at.saturating_duration_since(*recorded) <= self.trend_window {
+                break;
+            }
+            self.history.pop_front();
+        }
+        self.history.push_back((data.e_co2, at));
+
+        self.advise(data.e_co2)
+    }
+
+    fn advise(&self, e_co2: u16) -> Advice {
+        let trend = self.trend().unwrap_or(0.0);
+        let in_minutes = |value: f32| Duration::try_from_secs_f32(value * 60.0).unwrap_or(Duration::MAX);
+
+        // The trend lags behind the exponential decay while airing, so the remaining time is estimated
+        // like the airing time
+        if trend <= -RECOVERING_RATE && e_co2 > self.room.fresh {
+            return Advice::Recovering { remaining: self.room.airing_time(e_co2) };
+        }
+        if e_co2 >= self.room.limit {
+            return Advice::Ventilate { duration: self.room.airing_time(e_co2) };
+        }
+        if trend > 0.0 {
+            let within = in_minutes(f32::from(self.room.limit.saturating_sub(e_co2)) / trend);
+            if within <= self.warn_ahead {
+                return Advice::VentilateSoon { within };
+            }
+        }
+
+        Advice::Good
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::reading;
+    use super::*;
+
+    fn minutes(start: Instant, minutes: u32) -> Instant {
+        start.checked_add(Duration::from_secs(60).saturating_mul(minutes)).unwrap()
+    }
+
+    #[test]
+    fn airing_time() {
+        let room = Room::default();
+
+        // 10 air changes per hour lower the excess of 580ppm to 180ppm in ln(580 / 180) / 10 hours
+        let airing_time = room.airing_time(1000).as_secs();
+        assert!((415..425).contains(&airing_time), "{}", airing_time);
+        assert_eq!(room.airing_time(600), Duration::ZERO);
+        assert_eq!(room.airing_time(500), Duration::ZERO);
+    }
+
+    #[test]
+    fn advises_before_and_above_the_limit() {
+        let start = Instant::now();
+        let mut advisor = VentilationAdvisor::new(Room::default());
+
+        assert_eq!(advisor.record_at(&reading(800, 0), start), Advice::Good);
+        // Rising 20ppm per minute reaches the limit in 9 minutes
+        let advice = advisor.record_at(&reading(820, 0), minutes(start, 1));
+        assert_eq!(advice, Advice::VentilateSoon { within: Duration::from_secs(9 * 60) });
+        assert_eq!(advisor.trend(), Some(20.0));
+
+        let advice = advisor.record_at(&reading(1000, 0), minutes(start, 2));
+        assert_eq!(advice, Advice::Ventilate { duration: Room::default().airing_time(1000) });
+    }
+
+    #[test]
+    fn falling_readings_are_recovering() {
+        let start = Instant::now();
+        let mut advisor = VentilationAdvisor::new(Room::default());
+
+        advisor.record_at(&reading(1200, 0), start);
+        let advice = advisor.record_at(&reading(1100, 0), minutes(start, 1));
+        assert_eq!(advice, Advice::Recovering { remaining: Room::default().airing_time(1100) });
+
+        // Slowly falling readings below the limit are good
+        let mut advisor = VentilationAdvisor::new(Room::default());
+        advisor.record_at(&reading(700, 0), start);
+        assert_eq!(advisor.record_at(&reading(698, 0), minutes(start, 1)), Advice::Good);
+    }
+
+    #[test]
+    fn no_advice_during_the_warm_up() {
+        let mut advisor = VentilationAdvisor::new(Room::default());
+
+        let warming_up = Ccs811Data { since_start: Some(Duration::ZERO), ..reading(1200, 0) };
+        assert_eq!(advisor.record_at(&warming_up, Instant::now()), Advice::NotReady);
+        assert_eq!(advisor.record_at(&reading(0, 0), Instant::now()), Advice::NotReady);
+        assert_eq!(advisor.trend(), None);
+    }
+
+    #[test]
+    fn advice_in_minutes() {
+        assert_eq!(Advice::Ventilate { duration: Duration::from_secs(421) }.to_string(), "Open a window for ~8 minutes");
+        assert_eq!(Advice::VentilateSoon { within: Duration::from_secs(30) }.to_string(), "Open a window within ~1 minute");
+        assert_eq!(Advice::Recovering { remaining: Duration::ZERO }.to_string(), "Air quality recovering, ~1 minute until fresh");
+    }
+}
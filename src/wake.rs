@@ -0,0 +1,109 @@
+use embedded_hal::digital::v2::OutputPin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::chip::WakePolarity;
+
+struct Shared<P> {
+    pin: P,
+    polarity: WakePolarity,
+    /// Number of handles currently keeping the chips awake
+    awake: usize
+}
+
+/// Wake pin tied to the nWAKE lines of several chips. Every chip gets its own `SharedWakePin` handle
+/// which implements `OutputPin`. The pin is low as long as any chip is awake and only goes high when
+/// all of them sleep, so a chip isn't sent to sleep in the middle of a transaction with another one.
+/// For inverted wake lines use `with_polarity()` with the same polarity as the chips.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rppal::gpio::Gpio;
+/// use rppal::i2c::I2c;
+/// use ccs811::chip::CCS811;
+/// use ccs811::dual::DualCCS811;
+/// use ccs811::rppal::RppalPin;
+/// use ccs811::wake::SharedWake;
+///
+/// let wake = SharedWake::new(RppalPin::from(Gpio::new().unwrap().get(17).unwrap()));
+///
+/// let primary = CCS811::new(I2c::with_bus(1).unwrap(), Some(wake.pin()));
+/// let secondary = CCS811::new(I2c::with_bus(1).unwrap(), Some(wake.pin()))
+///     .with_address(ccs811::CCS811_SLAVEADDR_1);
+///
+/// let mut sensors = DualCCS811::new(primary, secondary, 100, 50);
+/// ```
+pub struct SharedWake<P> {
+    shared: Arc<Mutex<Shared<P>>>
+}
+
+impl<P: OutputPin> SharedWake<P> {
+
+    pub fn new(pin: P) -> Self {
+        SharedWake { shared: Arc::new(Mutex::new(Shared { pin, polarity: WakePolarity::ActiveLow, awake: 0 })) }
+    }
+
+    pub fn with_polarity(self, polarity: WakePolarity) -> Self {
+        self.shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).polarity = polarity;
+        self
+    }
+
+    /// A new handle for one of the chips
+    pub fn pin(&self) -> SharedWakePin<P> {
+        SharedWakePin { shared: self.shared.clone(), awake: false }
+    }
+}
+
+/// Handle of a `SharedWake` pin for a single chip
+pub struct SharedWakePin<P: OutputPin> {
+    shared: Arc<Mutex<Shared<P>>>,
+    awake: bool
+}
+
+impl<P: OutputPin> SharedWakePin<P> {
+
+    fn lock(&self) -> MutexGuard<'_, Shared<P>> {
+        // A panic while holding the lock can't leave the counter in an inconsistent state
+        self.shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Sets the level of the shared pin when the first chip wakes up or the last one goes to sleep
+    fn set_level(&mut self, low: bool) -> Result<(), P::Error> {
+        let mut shared = self.lock();
+        let awake = low == (shared.polarity == WakePolarity::ActiveLow);
+        if awake == self.awake {
+            return Ok(());
+        }
+
+        let last = if awake { shared.awake == 0 } else { shared.awake == 1 };
+        if last {
+            if low { shared.pin.set_low()? } else { shared.pin.set_high()? }
+        }
+        shared.awake = if awake { shared.awake.saturating_add(1) } else { shared.awake.saturating_sub(1) };
+        drop(shared);
+        self.awake = awake;
+
+        Ok(())
+    }
+}
+
+impl<P: OutputPin> OutputPin for SharedWakePin<P> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), P::Error> {
+        self.set_level(true)
+    }
+
+    fn set_high(&mut self) -> Result<(), P::Error> {
+        self.set_level(false)
+    }
+}
+
+impl<P: OutputPin> Drop for SharedWakePin<P> {
+    fn drop(&mut self) {
+        // Don't keep the other chips awake for a handle which is gone
+        if self.awake {
+            let low = self.lock().polarity != WakePolarity::ActiveLow;
+            let _ = self.set_level(low);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+use crate::alert::{Alert, Notifier, Thresholds};
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::sink::ReadingSink;
+
+pub use crate::alert::Metric;
+
+/// POSTs alerts as JSON to a URL like a Slack, ntfy or IFTTT webhook, see `Alert::to_json()`
+pub struct WebhookNotifier {
+    url: String
+}
+
+impl WebhookNotifier {
+
+    pub fn new(url: &str) -> Self {
+        WebhookNotifier { url: url.to_string() }
+    }
+
+    pub fn notify(&mut self, alert: &Alert) -> Result<(), ureq::Error> {
+        ureq::post(&self.url).content_type("application/json").send(&alert.to_json())?;
+
+        Ok(())
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&mut self, alert: &Alert) -> Result<(), BoxError> {
+        Ok(WebhookNotifier::notify(self, alert)?)
+    }
+}
+
+/// POSTs a JSON payload to a URL like a Slack, ntfy or IFTTT webhook when eCO2 or tVOC cross a level
+/// in either direction, e.g.
+/// `{"metric":"eco2","level":1000,"direction":"above","value":1040,"e_co2":1040,"t_voc":60}`.
+/// To not notify on every reading around a level, the readings have to stay on the other side of the
+/// level for the debounce duration (1 minute by default). It's an `AlertSink` with a `WebhookNotifier`
+/// which keeps the errors of ureq.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use ccs811::sink::ReadingSink;
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::webhook::{Metric, WebhookSink};
+///
+/// let mut webhook = WebhookSink::new("https://ntfy.sh/my-air")
+///     .level(Metric::ECo2, 1000)
+///     .level(Metric::ECo2, 2000)
+///     .level(Metric::TVoc, 500);
+///
+/// webhook.publish(&ccs811.read().expect("Could not read data"))
+///     .expect("Could not notify");
+/// ```
+pub struct WebhookSink {
+    notifier: WebhookNotifier,
+    thresholds: Thresholds
+}
+
+impl WebhookSink {
+
+    pub fn new(url: &str) -> Self {
+        WebhookSink { notifier: WebhookNotifier::new(url), thresholds: Thresholds::new() }
+    }
+
+    /// Notify when the metric crosses the level
+    pub fn level(self, metric: Metric, level: u16) -> Self {
+        WebhookSink { thresholds: self.thresholds.level(metric, level), ..self }
+    }
+
+    pub fn with_debounce(self, debounce: Duration) -> Self {
+        WebhookSink { thresholds: self.thresholds.with_debounce(debounce), ..self }
+    }
+
+    /// Checks the reading against the levels and sends a request per crossed level
+    pub fn publish(&mut self, data: &Ccs811Data) -> Result<(), ureq::Error> {
+        let mut result = Ok(());
+
+        for alert in self.thresholds.check(data) {
+            // Notify about the other levels even if one request fails
+            if let Err(error) = self.notifier.notify(&alert) {
+                result = Err(error);
+            }
+        }
+
+        result
+    }
+}
+
+impl ReadingSink for WebhookSink {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        Ok(WebhookSink::publish(self, data)?)
+    }
+}
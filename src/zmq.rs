@@ -0,0 +1,77 @@
+use ::zmq::{Context, Socket};
+use std::time::SystemTime;
+use crate::chip::Ccs811Data;
+use crate::error::BoxError;
+use crate::format::Format;
+use crate::sink::ReadingSink;
+
+// Milliseconds pending readings are still sent to subscribers when the sink is dropped
+const LINGER_MS: i32 = 1000;
+
+/// Broadcasts every reading on a ZeroMQ PUB socket, so any number of processes can subscribe to the
+/// live data with a SUB socket. Each message has two frames, the topic to filter with `set_subscribe()`
+/// and the reading formatted as JSON by default.
+///
+/// Publishing never blocks: readings are dropped for subscribers which don't keep up, and while nobody
+/// subscribes.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut ccs811 = ccs811::chip::CCS811::new(ccs811::sim::SimCcs811::new(), None::<ccs811::chip::NoPin>);
+/// use ccs811::zmq::ZmqSink;
+///
+/// // Subscribers connect to tcp://<host>:5556 and subscribe to "ccs811"
+/// let mut zmq = ZmqSink::bind("tcp://*:5556", "ccs811").expect("Could not bind");
+///
+/// zmq.publish(&ccs811.read().expect("Could not read data")).expect("Could not publish");
+/// ```
+pub struct ZmqSink {
+    socket: Socket,
+    topic: String,
+    format: Format
+}
+
+impl ZmqSink {
+
+    /// Binds the socket to the endpoint like `tcp://*:5556` or `ipc:///run/ccs811.sock`, subscribers
+    /// connect to it
+    pub fn bind(endpoint: &str, topic: &str) -> ::zmq::Result<Self> {
+        let socket = Self::socket()?;
+        socket.bind(endpoint)?;
+
+        Ok(ZmqSink { socket, topic: topic.to_string(), format: Format::Json })
+    }
+
+    /// Connects the socket to the endpoint, e.g. the XSUB socket of a proxy forwarding the readings of
+    /// many sensors
+    pub fn connect(endpoint: &str, topic: &str) -> ::zmq::Result<Self> {
+        let socket = Self::socket()?;
+        socket.connect(endpoint)?;
+
+        Ok(ZmqSink { socket, topic: topic.to_string(), format: Format::Json })
+    }
+
+    pub fn with_format(self, format: Format) -> Self {
+        ZmqSink { format, ..self }
+    }
+
+    pub fn publish(&mut self, data: &Ccs811Data) -> ::zmq::Result<()> {
+        let payload = self.format.format(data, SystemTime::now());
+
+        self.socket.send_multipart([self.topic.as_bytes(), payload.as_bytes()], ::zmq::DONTWAIT)
+    }
+
+    fn socket() -> ::zmq::Result<Socket> {
+        let socket = Context::new().socket(::zmq::PUB)?;
+        socket.set_linger(LINGER_MS)?;
+
+        Ok(socket)
+    }
+}
+
+impl ReadingSink for ZmqSink {
+    fn publish(&mut self, data: &Ccs811Data) -> Result<(), BoxError> {
+        Ok(ZmqSink::publish(self, data)?)
+    }
+}
@@ -0,0 +1,73 @@
+//! Tests against a real chip connected to I2C bus 1 of a Raspberry Pi as described in the README.
+//! Run them on the Pi with `cargo test --features hil-tests`.
+#![cfg(feature = "hil-tests")]
+
+use rppal::i2c::I2c;
+use std::sync::{Mutex, MutexGuard};
+use std::thread::sleep;
+use std::time::Duration;
+use ccs811::chip::CCS811;
+use ccs811::rppal::RppalPin;
+
+// All tests share the same chip, so they must not run in parallel
+static CHIP: Mutex<()> = Mutex::new(());
+
+fn begin() -> (MutexGuard<'static, ()>, CCS811<I2c, RppalPin>) {
+    let guard = CHIP.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let i2c = I2c::with_bus(1).expect("Couldn't start i2c. Is the interface enabled?");
+    let mut ccs811 = ccs811::new(i2c, None);
+    ccs811.begin().expect("Could not init the chip");
+
+    (guard, ccs811)
+}
+
+#[test]
+fn init() {
+    let (_guard, mut ccs811) = begin();
+
+    assert_eq!(ccs811.hardware_version().unwrap() & 0xF0, 0x10);
+    assert_ne!(ccs811.application_version().unwrap(), [0, 0]);
+}
+
+#[test]
+fn mode_changes() {
+    let (_guard, mut ccs811) = begin();
+
+    for mode in [ccs811::MODE::Sec1, ccs811::MODE::Sec10, ccs811::MODE::Sec60, ccs811::MODE::Idle] {
+        ccs811.start(mode).unwrap_or_else(|error| panic!("Could not start {:?}: {}", mode, error));
+    }
+}
+
+#[test]
+fn env_data() {
+    let (_guard, mut ccs811) = begin();
+
+    ccs811.set_env_data(50.0, 25.0).unwrap();
+    ccs811.set_env_data(ccs811::CCS811_HUMIDITY_MAX, ccs811::CCS811_TEMPERATURE_MAX).unwrap();
+    ccs811.set_env_data(ccs811::CCS811_HUMIDITY_MIN, ccs811::CCS811_TEMPERATURE_MIN).unwrap();
+}
+
+#[test]
+fn baseline_round_trip() {
+    // Firmware 2.0.x doesn't report the baseline during the warm-up after start()
+    let (_guard, mut ccs811) = begin();
+
+    let baseline = ccs811.get_baseline().unwrap();
+    ccs811.set_baseline(baseline).unwrap();
+
+    assert_eq!(ccs811.get_baseline().unwrap(), baseline);
+}
+
+#[test]
+fn read_cycle() {
+    let (_guard, mut ccs811) = begin();
+    ccs811.start(ccs811::MODE::Sec1).unwrap();
+
+    for _ in 0..5 {
+        sleep(Duration::from_millis(1100));
+        let data = ccs811.read().expect("Could not read data");
+
+        assert!(data.e_co2 == 0 || data.e_co2 >= 400, "eCO2 of {}ppm is below outdoor air", data.e_co2);
+        assert_eq!(data.raw.len(), 8);
+    }
+}
@@ -0,0 +1,34 @@
+//! The driver on a bus shared through the proxies of the shared-bus crate
+
+use embedded_hal::blocking::i2c::WriteRead;
+use ccs811::chip::{CCS811, NoPin};
+use ccs811::sim::SimCcs811;
+
+// MEAS_MODE and HW_ID, read back by the other driver on the bus
+const MEAS_MODE: u8 = 0x01;
+const HW_ID: u8 = 0x20;
+
+#[test]
+fn shared_bus_proxy() {
+    let bus = shared_bus::BusManagerStd::new(SimCcs811::new());
+    let mut ccs811 = CCS811::new(bus.acquire_i2c(), None::<NoPin>).with_discarded_samples(0);
+    let mut other = bus.acquire_i2c();
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            ccs811.begin().unwrap();
+            ccs811.start(ccs811::MODE::Sec1).unwrap();
+        });
+        scope.spawn(|| {
+            for _ in 0..100 {
+                let mut hw_id = [0];
+                other.write_read(0x5A, &[HW_ID], &mut hw_id).unwrap();
+                assert_eq!(hw_id, [0x81]);
+            }
+        });
+    });
+
+    let mut meas_mode = [0];
+    other.write_read(0x5A, &[MEAS_MODE], &mut meas_mode).unwrap();
+    assert_eq!(meas_mode[0] & 0x70, 0x10);
+}
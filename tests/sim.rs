@@ -0,0 +1,329 @@
+//! Tests of the driver against the simulated chip, they don't need any hardware
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use ccs811::chip::{CCS811, NoPin};
+use ccs811::codec::TemperatureEncoding;
+use ccs811::error::Ccs811Error;
+use ccs811::recovery::{LossReason, RecoveryEvent};
+use ccs811::sim::{SimCcs811, SimError, SimPin};
+
+// ALG_RESULT_DATA, the register read()s the samples from
+const ALG_RESULT_DATA: u8 = 0x02;
+
+fn begin(sim: SimCcs811) -> CCS811<SimCcs811> {
+    let mut ccs811 = CCS811::new(sim, None::<NoPin>).with_discarded_samples(0);
+    ccs811.begin().expect("Could not init the simulated chip");
+
+    ccs811
+}
+
+/// Records the recovery events as text, as they borrow the error
+fn record_events() -> (Arc<Mutex<Vec<String>>>, impl FnMut(&RecoveryEvent) + Send + 'static) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+
+    (events, move |event: &RecoveryEvent| {
+        let event = match event {
+            RecoveryEvent::Lost(LossReason::NoResponse) => "lost: no response".to_string(),
+            RecoveryEvent::Lost(LossReason::HardwareId(hw_id)) => format!("lost: HWID {:#x}", hw_id),
+            RecoveryEvent::Lost(LossReason::BootMode) => "lost: boot mode".to_string(),
+            RecoveryEvent::Recovered { attempts } => format!("recovered after {}", attempts),
+            RecoveryEvent::Failed { attempts, .. } => format!("failed after {}", attempts),
+            RecoveryEvent::Disconnected => "disconnected".to_string(),
+            RecoveryEvent::Reconnected { .. } => "reconnected".to_string()
+        };
+        recorded.lock().unwrap().push(event);
+    })
+}
+
+#[test]
+fn begin_start_read() {
+    let mut ccs811 = begin(SimCcs811::new());
+    assert!(ccs811.i2c.is_app_mode());
+
+    ccs811.start(ccs811::MODE::Sec1).unwrap();
+    ccs811.i2c.set_air(812, 63);
+    ccs811.i2c.elapse(Duration::from_secs(1));
+
+    let data = ccs811.read().unwrap();
+    assert_eq!((data.e_co2, data.t_voc), (812, 63));
+    assert!(data.status.is_data_ready());
+    assert_eq!(data.mode, ccs811::MODE::Sec1);
+}
+
+#[test]
+fn env_data_encoding() {
+    // Application 2.0 expects the temperature with an offset of 25°C
+    let mut ccs811 = begin(SimCcs811::new().with_application_version([0x20, 0x00]));
+    ccs811.set_env_data(48.5, 23.3).unwrap();
+    // 48.5 * 512 = 0x6100, (23.3 + 25) * 512 = 24729.6 rounded to 0x609A
+    assert_eq!(ccs811.i2c.env_data(), [0x61, 0x00, 0x60, 0x9A]);
+
+    // So does 1.x unless told otherwise
+    let mut ccs811 = begin(SimCcs811::new().with_application_version([0x11, 0x00]));
+    ccs811.set_env_data(48.5, 23.3).unwrap();
+    assert_eq!(ccs811.i2c.env_data(), [0x61, 0x00, 0x60, 0x9A]);
+
+    ccs811.set_temperature_encoding(Some(TemperatureEncoding::Absolute));
+    ccs811.set_env_data(48.5, 23.3).unwrap();
+    // 23.3 * 512 = 11929.6 rounded to 0x2E9A
+    assert_eq!(ccs811.i2c.env_data(), [0x61, 0x00, 0x2E, 0x9A]);
+}
+
+#[test]
+fn env_data_rejects_nan() {
+    let mut ccs811 = begin(SimCcs811::new());
+
+    assert!(matches!(ccs811.set_env_data(f32::NAN, 23.3), Err(Ccs811Error::InvalidHumidity(_))));
+    assert!(matches!(ccs811.set_env_data(48.5, f32::NAN), Err(Ccs811Error::InvalidTemperature(_))));
+    assert_eq!(ccs811.i2c.env_data(), [0x64, 0x00, 0x64, 0x00]);
+}
+
+#[test]
+fn no_valid_application() {
+    let mut ccs811 = CCS811::new(SimCcs811::new().without_application(), None::<NoPin>);
+
+    assert!(matches!(ccs811.begin(), Err(Ccs811Error::NoValidApplication(_))));
+    assert!(!ccs811.i2c.is_app_mode());
+}
+
+#[test]
+fn begin_stops_at_the_first_failure() {
+    let mut sim = SimCcs811::new();
+    // The reset fails, so the application must not be started
+    sim.fail_next(1);
+    let mut ccs811 = CCS811::new(sim, None::<NoPin>);
+
+    assert!(matches!(ccs811.begin(), Err(Ccs811Error::I2c { .. })));
+    assert!(!ccs811.i2c.is_app_mode());
+}
+
+#[cfg(feature = "flash")]
+#[test]
+fn flash() {
+    let mut ccs811 = CCS811::new(SimCcs811::new().with_application_version([0x20, 0x01]), None::<NoPin>);
+    let firmware: Vec<u8> = (0..20).collect();
+
+    let version = ccs811.flash(firmware.clone()).unwrap();
+
+    assert_eq!(version.to_string(), "2.0.1");
+    assert_eq!(ccs811.i2c.firmware(), firmware.as_slice());
+    ccs811.begin().unwrap();
+}
+
+#[cfg(feature = "flash")]
+#[test]
+fn flash_image_verifies_before_erasing() {
+    use ccs811::firmware::FirmwareImage;
+
+    let mut ccs811 = CCS811::new(SimCcs811::new(), None::<NoPin>);
+    let image = FirmwareImage::new((0..20).collect());
+
+    assert!(matches!(ccs811.flash_image(&image, Some("0000")), Err(Ccs811Error::Flash(_))));
+    // Neither version nor hash of the image are known
+    assert!(matches!(ccs811.flash_image(&image, None), Err(Ccs811Error::Flash(_))));
+    assert!(ccs811.i2c.firmware().is_empty());
+    ccs811.begin().unwrap();
+
+    let sha256 = image.sha256();
+    ccs811.flash_image(&image, Some(&sha256)).unwrap();
+    assert_eq!(ccs811.i2c.firmware(), image.data());
+}
+
+/// Passes the transactions to the simulated chip and lets a sample period elapse before each read of
+/// ALG_RESULT_DATA, with eCO2 increasing by 100ppm per sample
+struct Sampling {
+    sim: SimCcs811,
+    samples: u16
+}
+
+impl Write for Sampling {
+    type Error = SimError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), SimError> {
+        self.sim.write(address, bytes)
+    }
+}
+
+impl WriteRead for Sampling {
+    type Error = SimError;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), SimError> {
+        if bytes.first() == Some(&ALG_RESULT_DATA) {
+            self.samples += 1;
+            self.sim.set_air(400 + self.samples * 100, 0);
+            self.sim.elapse(Duration::from_secs(1));
+        }
+        self.sim.write_read(address, bytes, buffer)
+    }
+}
+
+#[test]
+fn discards_first_samples() {
+    let sampling = Sampling { sim: SimCcs811::new(), samples: 0 };
+    let mut ccs811 = CCS811::new(sampling, None::<NoPin>).with_discarded_samples(1);
+    ccs811.begin().unwrap();
+    ccs811.start(ccs811::MODE::Sec1).unwrap();
+
+    // The first sample of 500ppm is discarded and read() waits for the next one
+    assert_eq!(ccs811.read().unwrap().e_co2, 600);
+    assert_eq!(ccs811.read().unwrap().e_co2, 700);
+}
+
+#[test]
+fn auto_recovery() {
+    let (events, on_recovery) = record_events();
+    let mut ccs811 = begin(SimCcs811::new())
+        .with_auto_recovery(3)
+        .on_recovery(on_recovery);
+    ccs811.start(ccs811::MODE::Sec1).unwrap();
+    ccs811.set_env_data(48.5, 23.3).unwrap();
+
+    // A brown-out puts the chip back into boot loader mode
+    ccs811.i2c.unplug();
+    ccs811.i2c.plug();
+    let _ = ccs811.read();
+
+    assert_eq!(*events.lock().unwrap(), ["lost: boot mode", "recovered after 1"]);
+    assert!(ccs811.i2c.is_app_mode());
+    assert_eq!(ccs811.mode(), ccs811::MODE::Sec1);
+    assert_eq!(ccs811.i2c.env_data(), [0x61, 0x00, 0x60, 0x9A]);
+
+    ccs811.i2c.set_air(900, 80);
+    ccs811.i2c.elapse(Duration::from_secs(1));
+    assert_eq!(ccs811.read().unwrap().e_co2, 900);
+}
+
+#[test]
+fn hot_swap() {
+    let (events, on_recovery) = record_events();
+    let mut ccs811 = begin(SimCcs811::new())
+        .with_auto_recovery(1)
+        .with_hot_swap(3, Duration::ZERO)
+        .on_recovery(on_recovery);
+    ccs811.start(ccs811::MODE::Sec1).unwrap();
+
+    ccs811.i2c.unplug();
+    for _ in 0..2 {
+        assert!(!matches!(ccs811.read(), Err(Ccs811Error::Disconnected { .. })));
+    }
+    assert!(matches!(ccs811.read(), Err(Ccs811Error::Disconnected { .. })));
+    assert!(ccs811.is_disconnected());
+    assert!(matches!(ccs811.read(), Err(Ccs811Error::Disconnected { .. })));
+
+    ccs811.i2c.plug();
+    ccs811.i2c.set_air(700, 50);
+    let _ = ccs811.read();
+
+    assert!(!ccs811.is_disconnected());
+    assert_eq!(events.lock().unwrap().last().map(String::as_str), Some("reconnected"));
+    assert!(events.lock().unwrap().contains(&"disconnected".to_string()));
+    // The failed recoveries while the chip was gone must not lose its mode
+    assert_eq!(ccs811.mode(), ccs811::MODE::Sec1);
+    ccs811.i2c.elapse(Duration::from_secs(1));
+    assert_eq!(ccs811.read().unwrap().e_co2, 700);
+}
+
+/// Passes the transactions to the simulated chip and records when they happened and whether the wake
+/// pin was low at that time
+struct Watched {
+    sim: SimCcs811,
+    wake: SimPin,
+    transactions: Vec<(Instant, bool)>
+}
+
+impl Write for Watched {
+    type Error = SimError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), SimError> {
+        self.transactions.push((Instant::now(), self.wake.is_low()));
+        self.sim.write(address, bytes)
+    }
+}
+
+impl WriteRead for Watched {
+    type Error = SimError;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), SimError> {
+        self.transactions.push((Instant::now(), self.wake.is_low()));
+        self.sim.write_read(address, bytes, buffer)
+    }
+}
+
+fn begin_watched() -> (SimPin, CCS811<Watched, SimPin>) {
+    let wake = SimPin::new();
+    let watched = Watched { sim: SimCcs811::new(), wake: wake.clone(), transactions: Vec::new() };
+    let mut ccs811 = CCS811::new(watched, Some(wake.clone())).with_discarded_samples(0);
+    ccs811.begin().unwrap();
+    ccs811.start(ccs811::MODE::Sec1).unwrap();
+
+    (wake, ccs811)
+}
+
+/// Checks that the operation woke the chip once, waited at least 50µs before the first transaction
+/// and only let it sleep after the last one
+fn assert_woken_around(wake: &SimPin, transactions: &[(Instant, bool)]) {
+    let levels: Vec<bool> = wake.transitions().iter().map(|transition| transition.low).collect();
+    assert_eq!(levels, [true, false], "expected the wake pin to go low and back high once");
+    let (woken, asleep) = (wake.transitions()[0].at, wake.transitions()[1].at);
+
+    assert!(!transactions.is_empty());
+    for (at, low) in transactions {
+        assert!(*low, "transaction while the wake pin was high");
+        assert!(*at > woken && *at < asleep);
+    }
+    assert!(transactions[0].0 - woken >= Duration::from_micros(50), "no settle time after waking the chip");
+}
+
+#[test]
+fn read_wakes_the_chip() {
+    let (wake, mut ccs811) = begin_watched();
+    ccs811.i2c.sim.elapse(Duration::from_secs(1));
+    wake.clear();
+    ccs811.i2c.transactions.clear();
+
+    ccs811.read().unwrap();
+
+    assert_woken_around(&wake, &ccs811.i2c.transactions);
+    assert!(!wake.is_low());
+}
+
+#[test]
+fn set_env_data_wakes_the_chip() {
+    let (wake, mut ccs811) = begin_watched();
+    wake.clear();
+    ccs811.i2c.transactions.clear();
+
+    ccs811.set_env_data(48.5, 23.3).unwrap();
+
+    assert_woken_around(&wake, &ccs811.i2c.transactions);
+    assert_eq!(ccs811.i2c.sim.env_data(), [0x61, 0x00, 0x60, 0x9A]);
+}
+
+#[cfg(feature = "remote")]
+#[test]
+fn remote_bridge() {
+    use ccs811::remote::{Bridge, RemoteBus};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let bridge = std::thread::spawn(move || {
+        let mut bridge = Bridge::new(SimCcs811::new());
+        bridge.handle(listener.accept().unwrap().0).unwrap();
+        bridge.into_inner()
+    });
+
+    let bus = RemoteBus::connect(address).unwrap();
+    let mut ccs811 = CCS811::new(bus, None::<NoPin>).with_discarded_samples(0);
+    ccs811.begin().unwrap();
+    ccs811.start(ccs811::MODE::Sec1).unwrap();
+    ccs811.set_env_data(48.5, 23.3).unwrap();
+    drop(ccs811);
+
+    let sim = bridge.join().unwrap();
+    assert!(sim.is_app_mode());
+    assert_eq!(sim.env_data(), [0x61, 0x00, 0x60, 0x9A]);
+}